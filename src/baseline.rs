@@ -0,0 +1,82 @@
+//! `--baseline`: the unused dependencies which are accepted for now, so that only new ones fail.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use failure::{Fallible, ResultExt as _};
+use serde::{Deserialize, Serialize};
+
+use crate::dependency;
+use crate::package_id::PackageId;
+use crate::OutcomeUnusedDeps;
+
+/// The contents of the file of `--baseline` and `--write-baseline`.
+///
+/// The packages are identified by their names, so that the file does not depend on the
+/// checkout directory nor on the versions.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub(crate) struct Baseline {
+	unused_deps :BTreeSet<BaselineEntry>,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+struct BaselineEntry {
+	package :String,
+	kind :dependency::Kind,
+	dependency :String,
+}
+
+impl Baseline {
+	pub(crate) fn new(unused_deps :&BTreeMap<PackageId, OutcomeUnusedDeps>) -> Self {
+		let unused_deps = unused_deps
+			.iter()
+			.flat_map(|(id, unused)| {
+				unused.iter().map(move |(kind, dependency)| BaselineEntry {
+					package : id.name().to_string(),
+					kind,
+					dependency : dependency.to_string(),
+				})
+			})
+			.collect();
+		Self { unused_deps }
+	}
+
+	pub(crate) fn read(path :&Path) -> Fallible<Self> {
+		let baseline = fs::read_to_string(path).with_context(|_| format!("could not read `{}`", path.display()))?;
+		let baseline = serde_json::from_str(&baseline).with_context(|_| format!("could not parse `{}`", path.display()))?;
+		Ok(baseline)
+	}
+
+	pub(crate) fn write(&self, path :&Path) -> Fallible<()> {
+		let json = serde_json::to_string_pretty(self).expect("should not fail") + "\n";
+		fs::write(path, json).with_context(|_| format!("could not write `{}`", path.display()))?;
+		Ok(())
+	}
+
+	pub(crate) fn extend(&mut self, other :Self) {
+		self.unused_deps.extend(other.unused_deps);
+	}
+
+	/// Removes the entries of the baseline from `unused_deps` of the `checked` packages.
+	///
+	/// Returns the number of the removed ones, and of the entries of the `checked` packages which
+	/// are no longer unused.
+	pub(crate) fn apply(
+		&self,
+		checked :&[PackageId],
+		unused_deps :&mut BTreeMap<PackageId, OutcomeUnusedDeps>,
+	) -> (usize, usize) {
+		let mut removed = 0;
+		for (id, unused) in unused_deps.iter_mut() {
+			for entry in self.unused_deps.iter().filter(|e| e.package == *id.name()) {
+				if unused.unused_deps_mut(entry.kind).remove(&*entry.dependency) {
+					removed += 1;
+				}
+			}
+		}
+		unused_deps.retain(|_, unused| !unused.is_empty());
+		let applicable = self.unused_deps.iter().filter(|e| checked.iter().any(|id| e.package == *id.name())).count();
+		(removed, applicable - removed)
+	}
+}
@@ -0,0 +1,163 @@
+//! `rustc` wrapper for `--backend driver`.
+//!
+//! Invoked as `cargo-udeps-driver path/to/rustc ARGS..`. It compiles the crate as `rustc` would,
+//! then writes the crates its HIR refers to, including through macros, as a JSON array of
+//! `{"name": .., "paths": [..]}` to `<out-dir>/udeps-driver/<crate-name><extra-filename>.json`.
+//! The paths of the `.rlib`s and `.rmeta`s tell apart the versions of a crate.
+
+#![feature(rustc_private)]
+
+extern crate rustc;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_span;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::{env, fs};
+
+use rustc::hir::map::Map;
+use rustc::ty::{TyCtxt, TypeckTables};
+use rustc_driver::Compilation;
+use rustc_hir::def_id::{CrateNum, DefId, LOCAL_CRATE};
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_hir::{BodyId, Expr, ExprKind, HirId, Path};
+use rustc_interface::interface::Compiler;
+use rustc_interface::Queries;
+use rustc_span::Span;
+
+fn main() {
+	rustc_driver::init_rustc_env_logger();
+	// The first argument is the path to `rustc`, as with `RUSTC_WRAPPER`.
+	let mut args = env::args().skip(1).collect::<Vec<_>>();
+	if !args.iter().any(|a| a == "--sysroot" || a.starts_with("--sysroot=")) {
+		if let Some(sysroot) = sysroot(&args[0]) {
+			args.push("--sysroot".to_owned());
+			args.push(sysroot);
+		}
+	}
+	let output = output_path(&args);
+	let exit_code = rustc_driver::catch_with_exit_code(|| {
+		rustc_driver::run_compiler(&args, &mut Callbacks { output }, None, None)
+	});
+	process::exit(exit_code);
+}
+
+/// `librustc_driver` is not found in the sysroot of the driver itself.
+fn sysroot(rustc :&str) -> Option<String> {
+	let output = Command::new(rustc).args(&["--print", "sysroot"]).output().ok()?;
+	Some(String::from_utf8(output.stdout).ok()?.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+/// Where the used crates are written, if Cargo is compiling a crate.
+fn output_path(args :&[String]) -> Option<PathBuf> {
+	let value = |name :&str| args.windows(2).find(|w| w[0] == name).map(|w| &*w[1]);
+	let extra_filename = args
+		.windows(2)
+		.filter(|w| w[0] == "-C")
+		.find_map(|w| w[1].strip_prefix("extra-filename="))
+		.unwrap_or_default();
+	let file_name = format!("{}{}.json", value("--crate-name")?, extra_filename);
+	Some(PathBuf::from(value("--out-dir")?).join("udeps-driver").join(file_name))
+}
+
+struct Callbacks {
+	output :Option<PathBuf>,
+}
+
+impl rustc_driver::Callbacks for Callbacks {
+	fn after_analysis<'tcx>(&mut self, _compiler :&Compiler, queries :&'tcx Queries<'tcx>) -> Compilation {
+		let output = match &self.output {
+			Some(output) => output,
+			None => return Compilation::Continue,
+		};
+		queries.global_ctxt().unwrap().peek_mut().enter(|tcx| {
+			let mut visitor = UsedCrates { tcx, tables : None, crates : BTreeSet::new() };
+			tcx.hir().krate().visit_all_item_likes(&mut visitor.as_deep_visitor());
+			let crates = visitor
+				.crates
+				.iter()
+				.map(|&cnum| {
+					let source = tcx.used_crate_source(cnum);
+					let paths = source.paths().map(|p| p.display().to_string()).collect::<Vec<_>>();
+					let mut krate = BTreeMap::new();
+					krate.insert("name", serde_json::json!(tcx.crate_name(cnum).to_string()));
+					krate.insert("paths", serde_json::json!(paths));
+					krate
+				})
+				.collect::<Vec<_>>();
+			let written = output
+				.parent()
+				.map_or(Ok(()), fs::create_dir_all)
+				.and_then(|()| fs::write(output, serde_json::to_string(&crates).unwrap()));
+			if let Err(err) = written {
+				tcx.sess.fatal(&format!("could not write `{}`: {}", output.display(), err));
+			}
+		});
+		Compilation::Continue
+	}
+}
+
+struct UsedCrates<'tcx> {
+	tcx :TyCtxt<'tcx>,
+	tables :Option<&'tcx TypeckTables<'tcx>>,
+	crates :BTreeSet<CrateNum>,
+}
+
+impl UsedCrates<'_> {
+	fn insert(&mut self, def_id :DefId) {
+		if def_id.krate != LOCAL_CRATE {
+			self.crates.insert(def_id.krate);
+		}
+	}
+
+	/// Macros leave no paths behind when they expand to local or `std` items.
+	fn insert_macros(&mut self, span :Span) {
+		let mut ctxt = span.ctxt();
+		loop {
+			let expn_data = ctxt.outer_expn_data();
+			if expn_data.is_root() {
+				break;
+			}
+			if let Some(def_id) = expn_data.macro_def_id {
+				self.insert(def_id);
+			}
+			ctxt = expn_data.call_site.ctxt();
+		}
+	}
+}
+
+impl<'tcx> Visitor<'tcx> for UsedCrates<'tcx> {
+	type Map = Map<'tcx>;
+
+	fn nested_visit_map(&mut self) -> NestedVisitorMap<'_, Self::Map> {
+		NestedVisitorMap::All(&self.tcx.hir())
+	}
+
+	fn visit_nested_body(&mut self, body :BodyId) {
+		let tables = self.tables.replace(self.tcx.body_tables(body));
+		intravisit::walk_body(self, self.tcx.hir().body(body));
+		self.tables = tables;
+	}
+
+	fn visit_path(&mut self, path :&'tcx Path<'tcx>, _id :HirId) {
+		if let Some(def_id) = path.res.opt_def_id() {
+			self.insert(def_id);
+		}
+		self.insert_macros(path.span);
+		intravisit::walk_path(self, path);
+	}
+
+	fn visit_expr(&mut self, expr :&'tcx Expr<'tcx>) {
+		// Method calls have no paths.
+		if let (ExprKind::MethodCall(..), Some(tables)) = (&expr.kind, self.tables) {
+			if let Some(def_id) = tables.type_dependent_def_id(expr.hir_id) {
+				self.insert(def_id);
+			}
+		}
+		self.insert_macros(expr.span);
+		intravisit::walk_expr(self, expr);
+	}
+}
@@ -0,0 +1,63 @@
+//! `--blame`: the commits which added the declarations of the unused dependencies.
+
+use std::path::Path;
+
+use failure::{Fallible, ResultExt as _};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::process_builder::ProcessBuilder;
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct Blame {
+	commit :String,
+	author :String,
+	/// The first line of the commit message.
+	summary :String,
+}
+
+impl Blame {
+	/// `git blame` of the 1-based `line` of `path`, or `None` if it is not committed yet (or
+	/// `path` is not in a git repository).
+	pub(crate) fn line(path :&Path, line :usize) -> Fallible<Option<Self>> {
+		let (dir, file) = match (path.parent(), path.file_name()) {
+			(Some(dir), Some(file)) => (dir, file),
+			_ => return Ok(None),
+		};
+		let range = format!("{},{}", line, line);
+		let output = ProcessBuilder::new("git")
+			.args(&["blame", "--porcelain", "-L", &range, "--"])
+			.arg(file)
+			.cwd(dir)
+			.build_command()
+			.output()
+			.with_context(|_| "could not execute `git`")?;
+		if !output.status.success() {
+			return Ok(None);
+		}
+		Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+	}
+
+	/// Parses the output of `git blame --porcelain` for a single line.
+	fn parse(porcelain :&str) -> Option<Self> {
+		let mut lines = porcelain.lines();
+		let commit = lines.next()?.split(' ').next()?.to_owned();
+		if commit.bytes().all(|b| b == b'0') {
+			return None;
+		}
+		let (mut author, mut summary) = (None, None);
+		for line in lines {
+			if let Some(name) = line.strip_prefix("author ") {
+				author = Some(name.to_owned());
+			} else if let Some(message) = line.strip_prefix("summary ") {
+				summary = Some(message.to_owned());
+			}
+		}
+		Some(Self { commit, author : author?, summary : summary.unwrap_or_default() })
+	}
+
+	/// e.g. `added in 0123abc by Jane Doe`
+	pub(crate) fn describe(&self) -> String {
+		format!("added in {} by {}", &self.commit[..self.commit.len().min(7)], self.author)
+	}
+}
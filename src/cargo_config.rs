@@ -0,0 +1,131 @@
+//! The settings of Cargo's configuration which `cargo udeps` follows itself, e.g. `term.color`.
+//!
+//! Cargo reads its configuration on its own, so only the settings of `cargo udeps` are looked up.
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{Fallible, ResultExt as _};
+use toml_edit::{Document, Item, Table, Value};
+
+/// `.cargo/config.toml`s, `CARGO_*` and `--config`s.
+pub(crate) struct CargoConfig {
+	/// The strings and booleans of the files, by dotted keys (e.g. `term.progress.when`).
+	files :HashMap<String, String>,
+	/// The ones of `--config`, which take precedence over the environment.
+	overrides :HashMap<String, String>,
+}
+
+impl CargoConfig {
+	/// Reads the files Cargo reads when it runs in `cwd`.
+	pub(crate) fn load(cwd :&Path, overrides :&[String]) -> Fallible<Self> {
+		let mut files = HashMap::new();
+		// The ones closer to `cwd` take precedence.
+		for path in config_files(cwd).iter().rev() {
+			flatten_table("", read(path)?.as_table(), &mut files);
+		}
+
+		let mut config = Self { files, overrides : HashMap::new() };
+		for value in overrides {
+			config.add_override(cwd, value)?;
+		}
+		Ok(config)
+	}
+
+	/// `KEY=VALUE`, or a path to a file ending with `.toml`.
+	fn add_override(&mut self, cwd :&Path, value :&str) -> Fallible<()> {
+		if !value.contains('=') && value.ends_with(".toml") {
+			flatten_table("", read(&cwd.join(value))?.as_table(), &mut self.overrides);
+			return Ok(());
+		}
+		let invalid = || failure::format_err!("`--config` expects `KEY=VALUE` or a `.toml` file, got `{}`", value);
+		let (key, toml) = value.split_once('=').ok_or_else(invalid)?;
+		// `toml_edit` 0.1 does not parse dotted keys.
+		let toml = format!("value = {}", toml.trim()).parse::<Document>().map_err(|_| invalid())?;
+		if let Some(value) = toml["value"].as_value() {
+			flatten_value(key.trim(), value, &mut self.overrides);
+		}
+		Ok(())
+	}
+
+	/// The value of `key` in `--config`, `CARGO_*` (e.g. `CARGO_TERM_COLOR`) and the files, in
+	/// that order.
+	pub(crate) fn get_str(&self, key :&str) -> Option<String> {
+		let env = format!("CARGO_{}", key.to_uppercase().replace(|c| c == '.' || c == '-', "_"));
+		self.overrides
+			.get(key)
+			.cloned()
+			.or_else(|| env::var(env).ok())
+			.or_else(|| self.files.get(key).cloned())
+	}
+
+	pub(crate) fn get_bool(&self, key :&str) -> Fallible<Option<bool>> {
+		match self.get_str(key).as_deref() {
+			None => Ok(None),
+			Some("true") => Ok(Some(true)),
+			Some("false") => Ok(Some(false)),
+			Some(value) => Err(failure::format_err!("`{}` must be a boolean, got `{}`", key, value)),
+		}
+	}
+}
+
+/// `.cargo/config.toml` (or `.cargo/config`) of `cwd` and its ancestors, then the one of
+/// `$CARGO_HOME`.
+fn config_files(cwd :&Path) -> Vec<PathBuf> {
+	let cargo_home = env::var_os("CARGO_HOME")
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|h| Path::new(&h).join(".cargo")));
+	let dirs = cwd.ancestors().map(|d| d.join(".cargo")).chain(cargo_home);
+
+	let mut visited = HashSet::new();
+	let mut files = vec![];
+	for dir in dirs {
+		if !visited.insert(dir.clone()) {
+			continue;
+		}
+		// Cargo prefers the one without the extension.
+		if let Some(file) = ["config", "config.toml"].iter().map(|f| dir.join(f)).find(|f| f.is_file()) {
+			files.push(file);
+		}
+	}
+	files
+}
+
+fn read(path :&Path) -> Fallible<Document> {
+	let config = fs::read_to_string(path).with_context(|_| format!("could not read `{}`", path.display()))?;
+	let config = config.parse::<Document>().with_context(|_| format!("could not parse `{}`", path.display()))?;
+	Ok(config)
+}
+
+fn flatten_table(prefix :&str, table :&Table, flattened :&mut HashMap<String, String>) {
+	for (key, item) in table.iter() {
+		let key = join(prefix, key);
+		match item {
+			Item::Table(table) => flatten_table(&key, table, flattened),
+			Item::Value(value) => flatten_value(&key, value, flattened),
+			Item::None | Item::ArrayOfTables(_) => {}
+		}
+	}
+}
+
+fn flatten_value(key :&str, value :&Value, flattened :&mut HashMap<String, String>) {
+	if let Some(table) = value.as_inline_table() {
+		for (k, v) in table.iter() {
+			flatten_value(&join(key, k), v, flattened);
+		}
+	} else if let Some(s) = value.as_str() {
+		flattened.insert(key.to_owned(), s.to_owned());
+	} else if let Some(b) = value.as_bool() {
+		flattened.insert(key.to_owned(), b.to_string());
+	}
+}
+
+fn join(prefix :&str, key :&str) -> String {
+	if prefix.is_empty() {
+		key.to_owned()
+	} else {
+		format!("{}.{}", prefix, key)
+	}
+}
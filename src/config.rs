@@ -0,0 +1,65 @@
+use std::cell::{RefCell, RefMut};
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use failure::{Fallible, ResultExt as _};
+
+use crate::otel::Trace;
+use crate::process_builder::ProcessBuilder;
+use crate::shell::Shell;
+
+/// Where and how `cargo udeps` runs.
+pub struct Config {
+	shell :RefCell<Shell>,
+	cwd :PathBuf,
+	cargo :OsString,
+	rustc_wrapper :PathBuf,
+	trace :RefCell<Trace>,
+}
+
+impl Config {
+	/// Runs `$CARGO` (or `cargo`) with the current executable as `RUSTC_WORKSPACE_WRAPPER`.
+	pub fn new(shell :Shell, cwd :PathBuf) -> Fallible<Self> {
+		// `$CARGO` is set when `cargo-udeps` is executed as `cargo udeps ..`.
+		let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+		let rustc_wrapper = env::current_exe().with_context(|_| "could not get the current executable")?;
+		Ok(Self { shell : RefCell::new(shell), cwd, cargo, rustc_wrapper, trace : RefCell::default() })
+	}
+
+	/// Writes to stderr, in the current directory.
+	pub fn default() -> Fallible<Self> {
+		let cwd = env::current_dir().with_context(|_| "could not get the current directory")?;
+		Self::new(Shell::new(), cwd)
+	}
+
+	pub fn shell(&self) -> RefMut<'_, Shell> {
+		self.shell.borrow_mut()
+	}
+
+	/// The spans of the current run, for `--otel-endpoint`.
+	pub(crate) fn trace(&self) -> RefMut<'_, Trace> {
+		self.trace.borrow_mut()
+	}
+
+	pub fn cwd(&self) -> &Path {
+		&self.cwd
+	}
+
+	/// The executable which records the `rustc` invocations, i.e. `cargo-udeps` itself.
+	pub fn rustc_wrapper(&self) -> &Path {
+		&self.rustc_wrapper
+	}
+
+	/// Useful when `cargo-udeps` is used as a library.
+	pub fn set_rustc_wrapper(&mut self, path :PathBuf) {
+		self.rustc_wrapper = path;
+	}
+
+	/// `cargo <subcommand>` in the current directory.
+	pub(crate) fn cargo(&self, subcommand :&str) -> ProcessBuilder {
+		let mut cargo = ProcessBuilder::new(&self.cargo);
+		cargo.arg(subcommand).cwd(&self.cwd);
+		cargo
+	}
+}
@@ -28,6 +28,9 @@ pub struct Span {
 
 #[derive(Deserialize, Debug)]
 pub struct ExternalCrate {
+	/// The root module of the crate.
+	#[serde(default)]
+	pub file_name :Option<String>,
 	pub num :u32,
 	pub id :CrateId,
 }
@@ -0,0 +1,30 @@
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Which table a dependency is declared in.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+	/// `[dependencies]`
+	Normal,
+	/// `[dev-dependencies]`
+	Development,
+	/// `[build-dependencies]`
+	Build,
+}
+
+impl FromStr for Kind {
+	type Err = String;
+
+	/// `normal`, `dev` (or `development`) or `build`.
+	fn from_str(s :&str) -> Result<Self, String> {
+		match s {
+			"normal" => Ok(Self::Normal),
+			"dev" | "development" => Ok(Self::Development),
+			"build" => Ok(Self::Build),
+			_ => Err(format!("expected `normal`, `dev` or `build`, got {:?}", s)),
+		}
+	}
+}
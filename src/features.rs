@@ -0,0 +1,260 @@
+//! The `[features]` tables of the workspace members.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::ptr;
+
+use failure::{Fallible, ResultExt as _};
+use toml_edit::Document;
+
+use crate::dependency;
+use crate::interning::InternedString;
+use crate::metadata::{Dependency, Package, Workspace};
+use crate::source;
+
+/// An item of the list a feature enables.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum FeatureValue<'a> {
+	/// `feature`, or an optional dependency with the same name.
+	Feature(&'a str),
+	/// `dep:name`
+	Dep(&'a str),
+	/// `name/feature`, or `name?/feature` which does not enable `name` by itself.
+	DepFeature { dep :&'a str, feature :&'a str, weak :bool },
+}
+
+impl<'a> FeatureValue<'a> {
+	pub(crate) fn parse(value :&'a str) -> Self {
+		if let Some(dep) = value.strip_prefix("dep:") {
+			return FeatureValue::Dep(dep);
+		}
+		match value.find('/') {
+			Some(i) => {
+				let (dep, feature) = (&value[..i], &value[i + 1..]);
+				match dep.strip_suffix('?') {
+					Some(dep) => FeatureValue::DepFeature { dep, feature, weak : true },
+					None => FeatureValue::DepFeature { dep, feature, weak : false },
+				}
+			}
+			None => FeatureValue::Feature(value),
+		}
+	}
+
+	/// Whether this enables the optional dependency `name` by itself.
+	pub(crate) fn enables_dep(self, name :&str) -> bool {
+		match self {
+			FeatureValue::Feature(dep) | FeatureValue::Dep(dep) => dep == name,
+			FeatureValue::DepFeature { dep, weak, .. } => dep == name && !weak,
+		}
+	}
+}
+
+/// The names of the features written in `[features]` of `package`.
+///
+/// `cargo metadata` does not tell them from the ones Cargo creates for optional dependencies.
+pub(crate) fn declared(package :&Package) -> Fallible<BTreeSet<String>> {
+	let manifest_path = package.manifest_path();
+	let manifest = fs::read_to_string(manifest_path)
+		.with_context(|_| format!("could not read `{}`", manifest_path.display()))?;
+	let manifest = manifest
+		.parse::<Document>()
+		.with_context(|_| format!("could not parse `{}`", manifest_path.display()))?;
+	let features = manifest.as_table().iter().find(|(k, _)| *k == "features").and_then(|(_, f)| f.as_table());
+	Ok(features.into_iter().flat_map(|f| f.iter().map(|(k, _)| k.to_owned())).collect())
+}
+
+/// The optional dependencies of `package` which no feature in `[features]` enables, nor any
+/// member of `ws` through the feature Cargo creates for them.
+pub(crate) fn never_enabled(ws :&Workspace, package :&Package) -> Fallible<Vec<(dependency::Kind, InternedString)>> {
+	let declared = declared(package)?;
+	let values = package
+		.features()
+		.iter()
+		.filter(|(name, _)| declared.contains(*name))
+		.flat_map(|(_, values)| values.iter().map(|v| FeatureValue::parse(v)))
+		.collect::<Vec<_>>();
+	let enabled_by_dependents = |name :&str| {
+		ws.members().any(|member| {
+			member.dependencies().iter().filter(|d| d.matches(package)).any(|d| {
+				let dep_name = d.name_in_toml();
+				d.features().iter().any(|f| f == name)
+					|| member.features().values().flatten().any(|v| match FeatureValue::parse(v) {
+						FeatureValue::DepFeature { dep, feature, .. } => dep == &*dep_name && feature == name,
+						_ => false,
+					})
+			})
+		})
+	};
+
+	let mut never_enabled = package
+		.dependencies()
+		.iter()
+		.filter(|d| d.is_optional())
+		.map(|d| (d.kind(), d.name_in_toml()))
+		.filter(|(_, name)| !values.iter().any(|v| v.enables_dep(name)) && !enabled_by_dependents(name))
+		.collect::<Vec<_>>();
+	never_enabled.sort();
+	never_enabled.dedup();
+	Ok(never_enabled)
+}
+
+/// Whether `dependency` of `package` is enabled with the features `ws` was resolved with.
+///
+/// `cargo metadata` resolves optional dependencies which the enabled features refer to only
+/// with `dep?/feature`, although Cargo does not build them.
+pub(crate) fn is_enabled(ws :&Workspace, package :&Package, dependency :&Dependency) -> bool {
+	let name = dependency.name_in_toml();
+	!dependency.is_optional()
+		|| ws.features(package.package_id()).iter().any(|feature| {
+			*feature == *name
+				|| package
+					.features()
+					.get(feature)
+					.into_iter()
+					.flatten()
+					.any(|v| FeatureValue::parse(v).enables_dep(&name))
+		})
+}
+
+/// Features of a used dependency whose items are never referred to.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) struct UnusedFeatures {
+	pub(crate) kind :dependency::Kind,
+	pub(crate) name :InternedString,
+	/// The unused ones of `features = [..]`.
+	pub(crate) features :Vec<String>,
+	/// The features in `default`, if none of them is used and `default-features = false` can
+	/// be set.
+	pub(crate) default :Vec<String>,
+}
+
+/// The features `package` enables on its used dependencies, none of whose items behind
+/// `#[cfg(feature = "..")]` its sources refer to.
+///
+/// Features which gate no named items (e.g. only `impl`s) are never reported.
+pub(crate) fn unused_features(
+	ws :&Workspace,
+	package :&Package,
+	is_unused :impl Fn(dependency::Kind, InternedString) -> bool,
+) -> Fallible<Vec<UnusedFeatures>> {
+	let id = package.package_id();
+	let mut unused_features = vec![];
+	for dependency in package.dependencies() {
+		let (kind, name) = (dependency.kind(), dependency.name_in_toml());
+		let enables_features = !dependency.features().is_empty() || dependency.uses_default_features();
+		if !enables_features || is_unused(kind, name) {
+			continue;
+		}
+		let resolved = ws.deps(id).map(|d| ws.package(d)).find(|d| dependency.matches(d));
+		let (resolved, lib) = match resolved.and_then(|d| Some((d, d.targets().iter().find(|t| t.is_lib())?))) {
+			Some(found) => found,
+			None => continue,
+		};
+		let extern_name = match ws.lib_deps(id).iter().find(|d| d.pkg == resolved.package_id()) {
+			Some(node_dep) => &node_dep.name,
+			None => continue,
+		};
+
+		// Build scripts are the only targets which can use `[build-dependencies]`.
+		let roots = package
+			.targets()
+			.iter()
+			.filter(|t| t.is_custom_build() == (kind == dependency::Kind::Build))
+			.map(|t| t.src_path());
+		let mut referred = HashSet::new();
+		let mut glob = false;
+		for root in roots {
+			match source::referred_items(root, extern_name)? {
+				Some(names) => referred.extend(names),
+				None => glob = true,
+			}
+		}
+		if glob {
+			continue;
+		}
+
+		// Cargo unifies the features of the declarations of the same package, except the ones of
+		// build-dependencies with the others since resolver 2. Dropping a feature which another
+		// of them enables does not change the build.
+		let unified = package
+			.dependencies()
+			.iter()
+			.filter(|d| !ptr::eq(*d, dependency) && d.matches(resolved))
+			.filter(|d| ws.resolver() < 2 || (d.kind() == dependency::Kind::Build) == (kind == dependency::Kind::Build))
+			.collect::<Vec<_>>();
+		let enabled_elsewhere = unified
+			.iter()
+			.flat_map(|d| {
+				let default = if d.uses_default_features() { Some("default") } else { None };
+				d.features().iter().map(|f| &**f).chain(default).flat_map(|f| implied(resolved, f))
+			})
+			.collect::<BTreeSet<_>>();
+
+		let gated = source::feature_gated_items(lib.src_path())?;
+		let is_unused_feature = |feature :&str| {
+			let items = implied(resolved, feature)
+				.into_iter()
+				.flat_map(|f| gated.get(f))
+				.flatten()
+				.collect::<HashSet<_>>();
+			!items.is_empty() && !items.iter().any(|item| referred.contains(*item))
+		};
+		let features = dependency
+			.features()
+			.iter()
+			.filter(|feature| !enabled_elsewhere.contains(&***feature) && is_unused_feature(feature))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		// The features enabled explicitly stay enabled without the default ones.
+		let explicit = dependency.features().iter().flat_map(|f| implied(resolved, f)).collect::<BTreeSet<_>>();
+		let default = resolved
+			.features()
+			.get("default")
+			.into_iter()
+			.flatten()
+			.filter_map(|value| match FeatureValue::parse(value) {
+				FeatureValue::Feature(feature) | FeatureValue::DepFeature { dep : feature, weak : false, .. } => {
+					Some(feature)
+				}
+				FeatureValue::Dep(_) | FeatureValue::DepFeature { weak : true, .. } => None,
+			})
+			.filter(|feature| !explicit.contains(feature) && !enabled_elsewhere.contains(feature))
+			.collect::<BTreeSet<_>>();
+		let default = if dependency.uses_default_features()
+			&& !default.is_empty()
+			&& default.iter().all(|f| is_unused_feature(f))
+		{
+			default.into_iter().map(ToOwned::to_owned).collect()
+		} else {
+			vec![]
+		};
+
+		if !features.is_empty() || !default.is_empty() {
+			unused_features.push(UnusedFeatures { kind, name, features, default });
+		}
+	}
+	unused_features.sort();
+	Ok(unused_features)
+}
+
+/// `feature` and the features of `package` it enables, directly or not.
+fn implied<'a>(package :&'a Package, feature :&'a str) -> BTreeSet<&'a str> {
+	let mut implied = BTreeSet::new();
+	let mut stack = vec![feature];
+	while let Some(feature) = stack.pop() {
+		if !implied.insert(feature) {
+			continue;
+		}
+		for value in package.features().get(feature).into_iter().flatten() {
+			match FeatureValue::parse(value) {
+				FeatureValue::Feature(feature) => stack.push(feature),
+				// The feature Cargo creates for the optional dependency, unless it is hidden
+				// with `dep:`.
+				FeatureValue::DepFeature { dep, weak : false, .. } => stack.push(dep),
+				FeatureValue::Dep(_) | FeatureValue::DepFeature { weak : true, .. } => {}
+			}
+		}
+	}
+	implied
+}
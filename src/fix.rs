@@ -0,0 +1,214 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{Fallible, ResultExt as _};
+use toml_edit::{Document, Item, Table};
+
+use crate::dependency;
+use crate::features::FeatureValue;
+use crate::interning::InternedString;
+use crate::manifest::tables;
+use crate::OutcomeUnusedDeps;
+
+/// Removes the dependencies in `unused` from its manifest, keeping the formatting and comments.
+///
+/// Returns the removed dependencies.
+pub(crate) fn remove_unused_deps(unused :&OutcomeUnusedDeps) -> Fallible<Vec<String>> {
+	let manifest_path = Path::new(&unused.manifest_path);
+	let mut manifest = read(manifest_path)?;
+
+	let mut removed = vec![];
+	for (names, kind) in &[
+		(&unused.normal, dependency::Kind::Normal),
+		(&unused.development, dependency::Kind::Development),
+		(&unused.build, dependency::Kind::Build),
+	] {
+		for name in *names {
+			if remove_dependency(manifest.as_table_mut(), *kind, name) {
+				removed.push(format!("`{}` ({:?})", name, kind));
+			}
+		}
+	}
+
+	if !removed.is_empty() {
+		fs::write(manifest_path, manifest.to_string())
+			.with_context(|_| format!("could not write `{}`", manifest_path.display()))?;
+	}
+	Ok(removed)
+}
+
+/// Removes the entries in `candidates` from `[workspace.dependencies]` of `root_manifest_path`
+/// unless some of `member_manifest_paths` still inherits them with `workspace = true`.
+///
+/// Returns the removed entries.
+pub(crate) fn remove_unreferenced_workspace_deps(
+	root_manifest_path :&Path,
+	member_manifest_paths :&[PathBuf],
+	candidates :&BTreeSet<InternedString>,
+) -> Fallible<Vec<String>> {
+	let mut root = read(root_manifest_path)?;
+	let members = member_manifest_paths.iter().map(|p| read(p)).collect::<Fallible<Vec<_>>>()?;
+
+	let mut removed = vec![];
+	if let Some(deps) = get_mut(root.as_table_mut(), "workspace")
+		.and_then(Item::as_table_mut)
+		.and_then(|w| get_mut(w, "dependencies"))
+	{
+		for name in candidates {
+			if !members.iter().any(|m| inherits(m.as_table(), name)) && remove_key(deps, name) {
+				removed.push(format!("`{}` (workspace)", name));
+			}
+		}
+	}
+
+	if !removed.is_empty() {
+		fs::write(root_manifest_path, root.to_string())
+			.with_context(|_| format!("could not write `{}`", root_manifest_path.display()))?;
+	}
+	Ok(removed)
+}
+
+fn read(manifest_path :&Path) -> Fallible<Document> {
+	let manifest = fs::read_to_string(manifest_path)
+		.with_context(|_| format!("could not read `{}`", manifest_path.display()))?;
+	let manifest = manifest
+		.parse::<Document>()
+		.with_context(|_| format!("could not parse `{}`", manifest_path.display()))?;
+	Ok(manifest)
+}
+
+/// The dependencies `manifest_path` declares with `workspace = true`.
+pub(crate) fn inherited_deps(manifest_path :&Path) -> Fallible<BTreeSet<(dependency::Kind, String)>> {
+	Ok(inherited(read(manifest_path)?.as_table()))
+}
+
+/// Whether `root` declares `name = { workspace = true }` in any dependency table.
+fn inherits(root :&Table, name :&str) -> bool {
+	inherited(root).iter().any(|(_, n)| n == name)
+}
+
+/// The `name = { workspace = true }`s in the dependency tables of `root`.
+fn inherited(root :&Table) -> BTreeSet<(dependency::Kind, String)> {
+	declarations(root)
+		.filter(|(_, _, dep)| {
+			let workspace = if let Some(dep) = dep.as_table() {
+				dep.get("workspace").and_then(Item::as_value).and_then(|w| w.as_bool())
+			} else if let Some(dep) = dep.as_inline_table() {
+				dep.get("workspace").and_then(|w| w.as_bool())
+			} else {
+				None
+			};
+			workspace == Some(true)
+		})
+		.map(|(kind, name, _)| (kind, name.to_owned()))
+		.collect()
+}
+
+/// The dependencies `manifest_path` declares with a trailing `# cargo-udeps: ignore` comment,
+/// e.g. `if_chain = "1.0.0" # cargo-udeps: ignore (used only in doctests)`.
+pub(crate) fn ignored_by_comment(manifest_path :&Path) -> Fallible<BTreeSet<(dependency::Kind, String)>> {
+	let manifest = read(manifest_path)?;
+	let ignored = declarations(manifest.as_table())
+		.filter(|(_, _, dep)| {
+			dep.as_value().map_or(false, |dep| {
+				let comment = dep.decor().suffix().trim().trim_start_matches('#').trim();
+				comment.strip_prefix("cargo-udeps:").map_or(false, |c| c.trim_start().starts_with("ignore"))
+			})
+		})
+		.map(|(kind, name, _)| (kind, name.to_owned()))
+		.collect();
+	Ok(ignored)
+}
+
+/// The entries of the dependency tables of `root`, including the `[target.<platform>.*]` ones.
+fn declarations(root :&Table) -> impl Iterator<Item = (dependency::Kind, &str, &Item)> {
+	let mut deps_tables = vec![root];
+	if let Some(targets) = root.get("target").and_then(Item::as_table) {
+		deps_tables.extend(targets.iter().filter_map(|(_, t)| t.as_table()));
+	}
+	const KINDS :[dependency::Kind; 3] =
+		[dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build];
+	deps_tables
+		.into_iter()
+		.flat_map(|t| {
+			KINDS.iter().flat_map(move |&k| tables(k).iter().filter_map(move |&key| Some((k, t.get(key)?))))
+		})
+		.filter_map(|(kind, deps)| deps.as_table().map(|deps| (kind, deps)))
+		.flat_map(|(kind, deps)| deps.iter().map(move |(name, dep)| (kind, name, dep)))
+}
+
+fn remove_dependency(root :&mut Table, kind :dependency::Kind, name :&str) -> bool {
+	let keys = tables(kind);
+
+	let mut removed = false;
+	for &key in keys {
+		if let Some(deps) = get_mut(root, key) {
+			removed |= remove_key(deps, name);
+		}
+	}
+	if let Some(targets) = get_mut(root, "target").and_then(Item::as_table_mut) {
+		let platforms = targets.iter().map(|(k, _)| k.to_owned()).collect::<Vec<_>>();
+		for platform in &platforms {
+			if let Some(target) = get_mut(targets, platform).and_then(Item::as_table_mut) {
+				for &key in keys {
+					if let Some(deps) = get_mut(target, key) {
+						removed |= remove_key(deps, name);
+					}
+				}
+			}
+		}
+	}
+
+	// Only optional dependencies can be referred from `[features]`, and dev-dependencies cannot
+	// be optional.
+	if removed && kind != dependency::Kind::Development {
+		remove_feature_references(root, name);
+	}
+	removed
+}
+
+/// `Table::get_mut` is missing in `toml_edit` 0.1, and `Table::entry` inserts `key`.
+fn get_mut<'a>(table :&'a mut Table, key :&str) -> Option<&'a mut Item> {
+	if table.contains_key(key) {
+		Some(table.entry(key))
+	} else {
+		None
+	}
+}
+
+fn remove_key(item :&mut Item, key :&str) -> bool {
+	if let Some(table) = item.as_table_mut() {
+		table.remove(key).is_some()
+	} else if let Some(table) = item.as_inline_table_mut() {
+		table.remove(key).is_some()
+	} else {
+		false
+	}
+}
+
+/// Removes `"dep:name"`, `"name/feature"` and `"name?/feature"` from `[features]`, and `"name"`
+/// unless `name` is also a feature.
+fn remove_feature_references(root :&mut Table, name :&str) {
+	let features = match get_mut(root, "features").and_then(Item::as_table_mut) {
+		Some(features) => features,
+		None => return,
+	};
+	let is_feature = features.contains_key(name);
+	let refers = |value :&str| match FeatureValue::parse(value) {
+		FeatureValue::Feature(feature) => feature == name && !is_feature,
+		FeatureValue::Dep(dep) | FeatureValue::DepFeature { dep, .. } => dep == name,
+	};
+	let keys = features.iter().map(|(k, _)| k.to_owned()).collect::<Vec<_>>();
+	for key in &keys {
+		if let Some(array) = get_mut(features, key).and_then(Item::as_array_mut) {
+			loop {
+				let i = array.iter().position(|v| v.as_str().map_or(false, refers));
+				match i {
+					Some(i) => drop(array.remove(i)),
+					None => break,
+				}
+			}
+		}
+	}
+}
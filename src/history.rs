@@ -0,0 +1,147 @@
+//! `cargo udeps record` and `cargo udeps trend`: the numbers of the unused dependencies over time,
+//! kept in SQLite.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use failure::{Fallible, ResultExt as _};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::dependency;
+use crate::process_builder::ProcessBuilder;
+use crate::{write_tree, Charset, Config, Layout, Outcome, OutputKind};
+
+static DEFAULT_DATABASE :&str = "udeps-history.sqlite";
+
+static SCHEMA :&str = "
+	CREATE TABLE IF NOT EXISTS runs (
+		id INTEGER PRIMARY KEY,
+		recorded_at TEXT NOT NULL DEFAULT (datetime('now')),
+		git_commit TEXT
+	);
+	CREATE TABLE IF NOT EXISTS unused_deps (
+		run_id INTEGER NOT NULL REFERENCES runs (id),
+		package TEXT NOT NULL,
+		kind TEXT NOT NULL,
+		dependency TEXT NOT NULL
+	);
+";
+
+#[derive(StructOpt, Clone, Debug)]
+pub struct OptRecord {
+	#[structopt(
+		long,
+		value_name("PATH"),
+		help("The SQLite database to append to (default: `udeps-history.sqlite`)")
+	)]
+	database: Option<PathBuf>,
+	#[structopt(value_name("REPORT"), help("A report written with `--output json`"))]
+	report: PathBuf,
+}
+
+impl OptRecord {
+	pub(crate) fn run(&self, config :&Config) -> Fallible<i32> {
+		let outcome = Outcome::read(&config.cwd().join(&self.report))?;
+		let mut db = open(config, &self.database)?;
+		let tx = db.transaction()?;
+		tx.execute("INSERT INTO runs (git_commit) VALUES (?1)", params![git_commit(config)])?;
+		let run_id = tx.last_insert_rowid();
+		for (id, unused) in &outcome.unused_deps {
+			for (kind, dependency) in unused.iter() {
+				let kind = match kind {
+					dependency::Kind::Normal => "normal",
+					dependency::Kind::Development => "development",
+					dependency::Kind::Build => "build",
+				};
+				tx.execute(
+					"INSERT INTO unused_deps (run_id, package, kind, dependency) VALUES (?1, ?2, ?3, ?4)",
+					params![run_id, &*id.name(), kind, &*dependency],
+				)?;
+			}
+		}
+		tx.commit()?;
+		Ok(0)
+	}
+}
+
+#[derive(StructOpt, Clone, Debug)]
+pub struct OptTrend {
+	#[structopt(
+		long,
+		value_name("PATH"),
+		help("The SQLite database written by `cargo udeps record` (default: `udeps-history.sqlite`)")
+	)]
+	database: Option<PathBuf>,
+}
+
+impl OptTrend {
+	pub(crate) fn run(&self, config :&Config, output :OutputKind, layout :Layout, mut stdout :impl Write) -> Fallible<i32> {
+		let db = open(config, &self.database)?;
+		// Every run counts for every package which ever had unused dependencies, so that the fixed
+		// ones go down to 0.
+		let mut statement = db.prepare(
+			"SELECT runs.id, runs.recorded_at, runs.git_commit, packages.package,
+				(SELECT COUNT(*) FROM unused_deps
+					WHERE unused_deps.run_id = runs.id AND unused_deps.package = packages.package)
+			FROM runs, (SELECT DISTINCT package FROM unused_deps) AS packages
+			ORDER BY packages.package, runs.id",
+		)?;
+		let rows = statement.query_map(params![], |row| {
+			Ok(TrendRow {
+				run : row.get(0)?,
+				recorded_at : row.get(1)?,
+				git_commit : row.get(2)?,
+				package : row.get(3)?,
+				unused : row.get(4)?,
+			})
+		})?;
+		let rows = rows.collect::<Result<Vec<_>, _>>()?;
+		match output {
+			OutputKind::Human => print_trend(&rows, layout.charset, &mut stdout)?,
+			OutputKind::Json => writeln!(stdout, "{}", serde_json::to_string(&rows).expect("should not fail"))?,
+			_ => return Err(failure::err_msg("`cargo udeps trend` supports only `--output human` and `--output json`")),
+		}
+		stdout.flush()?;
+		Ok(0)
+	}
+}
+
+/// The number of the unused dependencies of a package in a run.
+#[derive(Debug, Serialize)]
+struct TrendRow {
+	run :i64,
+	/// `YYYY-MM-DD HH:MM:SS` in UTC.
+	recorded_at :String,
+	git_commit :Option<String>,
+	package :String,
+	unused :i64,
+}
+
+fn print_trend(rows :&[TrendRow], charset :Charset, mut stdout :impl Write) -> io::Result<()> {
+	let mut by_package = BTreeMap::<_, Vec<_>>::new();
+	for row in rows {
+		let commit = row.git_commit.as_ref().map(|c| format!(" ({})", &c[..c.len().min(7)])).unwrap_or_default();
+		by_package.entry(&row.package).or_default().push(format!("{}{}: {}", row.recorded_at, commit, row.unused));
+	}
+	for (package, items) in by_package {
+		writeln!(stdout, "`{}`", package)?;
+		write_tree(&mut stdout, charset, &[("unused dependencies".to_owned(), items)])?;
+	}
+	Ok(())
+}
+
+fn open(config :&Config, database :&Option<PathBuf>) -> Fallible<Connection> {
+	let path = config.cwd().join(database.as_deref().unwrap_or_else(|| Path::new(DEFAULT_DATABASE)));
+	let db = Connection::open(&path).with_context(|_| format!("could not open `{}`", path.display()))?;
+	db.execute_batch(SCHEMA)?;
+	Ok(db)
+}
+
+/// `HEAD`, if the current directory is in a git repository.
+fn git_commit(config :&Config) -> Option<String> {
+	let output = ProcessBuilder::new("git").args(&["rev-parse", "HEAD"]).cwd(config.cwd()).exec_with_output().ok()?;
+	Some(String::from_utf8(output.stdout).ok()?.trim().to_owned())
+}
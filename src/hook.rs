@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::{fs, str};
+
+use failure::{Fallible, ResultExt as _};
+use structopt::StructOpt;
+
+use crate::Config;
+
+/// Identifies hooks written by `cargo udeps hook install`, so that others are never touched.
+static MARKER :&str = "# Installed by `cargo udeps hook install`.";
+
+#[derive(StructOpt, Clone, Debug)]
+pub enum OptHook {
+	#[structopt(about("Install a git hook running `cargo udeps` on the changed packages"))]
+	Install(OptHookInstall),
+	#[structopt(about("Remove a git hook installed by `cargo udeps hook install`"))]
+	Uninstall(OptHookUninstall),
+}
+
+impl OptHook {
+	pub fn run(&self, config :&Config) -> Fallible<i32> {
+		match self {
+			OptHook::Install(opt) => opt.run(config),
+			OptHook::Uninstall(opt) => opt.run(config),
+		}
+	}
+}
+
+#[derive(StructOpt, Clone, Debug)]
+pub struct OptHookInstall {
+	#[structopt(long, help("Install a `pre-push` hook instead of a `pre-commit` one"))]
+	pre_push: bool,
+	#[structopt(long, help("Overwrite an existing hook"))]
+	force: bool,
+	#[structopt(
+		value_name("ARGS"),
+		last(true),
+		help("Additional arguments for `cargo udeps`, e.g. `-- --all-targets`")
+	)]
+	args: Vec<String>,
+}
+
+impl OptHookInstall {
+	fn run(&self, config :&Config) -> Fallible<i32> {
+		let kind = HookKind::new(self.pre_push);
+		let path = hooks_dir(config)?.join(kind.file_name());
+		if let Ok(existing) = fs::read_to_string(&path) {
+			if !existing.contains(MARKER) && !self.force {
+				return Err(failure::format_err!(
+					"`{}` already exists. Use `--force` to overwrite it",
+					path.display(),
+				));
+			}
+		}
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		fs::write(&path, kind.script(&self.args))
+			.with_context(|_| format!("could not write `{}`", path.display()))?;
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt as _;
+			fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+		}
+		config.shell().info(format_args!("Installed `{}`", path.display()))?;
+		Ok(0)
+	}
+}
+
+#[derive(StructOpt, Clone, Debug)]
+pub struct OptHookUninstall {
+	#[structopt(long, help("Remove the `pre-push` hook instead of the `pre-commit` one"))]
+	pre_push: bool,
+}
+
+impl OptHookUninstall {
+	fn run(&self, config :&Config) -> Fallible<i32> {
+		let path = hooks_dir(config)?.join(HookKind::new(self.pre_push).file_name());
+		match fs::read_to_string(&path) {
+			Err(_) => {
+				config.shell().info(format_args!("`{}` does not exist", path.display()))?;
+			}
+			Ok(existing) => {
+				if !existing.contains(MARKER) {
+					return Err(failure::format_err!(
+						"`{}` was not installed by `cargo udeps hook install`",
+						path.display(),
+					));
+				}
+				fs::remove_file(&path)?;
+				config.shell().info(format_args!("Removed `{}`", path.display()))?;
+			}
+		}
+		Ok(0)
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+enum HookKind {
+	PreCommit,
+	PrePush,
+}
+
+impl HookKind {
+	fn new(pre_push :bool) -> Self {
+		if pre_push {
+			HookKind::PrePush
+		} else {
+			HookKind::PreCommit
+		}
+	}
+
+	fn file_name(self) -> &'static str {
+		match self {
+			HookKind::PreCommit => "pre-commit",
+			HookKind::PrePush => "pre-push",
+		}
+	}
+
+	fn script(self, args :&[String]) -> String {
+		let mut cargo_udeps = "cargo +nightly udeps".to_owned();
+		for arg in args {
+			cargo_udeps += " '";
+			cargo_udeps += &arg.replace('\'', r"'\''");
+			cargo_udeps += "'";
+		}
+		// `xargs` runs the command once even without input. `--changed-files` without any value
+		// checks nothing then.
+		let body = match self {
+			HookKind::PreCommit => format!(
+				"git diff --cached --name-only -z | xargs -0 {} --changed-files\n",
+				cargo_udeps,
+			),
+			HookKind::PrePush => format!(
+				"if git rev-parse --verify --quiet '@{{upstream}}' > /dev/null; then\n\
+				 \tgit diff --name-only -z '@{{upstream}}...HEAD' | xargs -0 {0} --changed-files\n\
+				 else\n\
+				 \t{0}\n\
+				 fi\n",
+				cargo_udeps,
+			),
+		};
+		format!("#!/bin/sh\n{}\n{}", MARKER, body)
+	}
+}
+
+fn hooks_dir(config :&Config) -> Fallible<PathBuf> {
+	let output = Command::new("git")
+		.args(&["rev-parse", "--git-path", "hooks"])
+		.current_dir(config.cwd())
+		.output()
+		.with_context(|_| "could not execute `git`")?;
+	if !output.status.success() {
+		return Err(failure::format_err!(
+			"`git rev-parse --git-path hooks` failed: {}",
+			String::from_utf8_lossy(&output.stderr).trim(),
+		));
+	}
+	Ok(config.cwd().join(str::from_utf8(&output.stdout)?.trim()))
+}
@@ -0,0 +1,100 @@
+//! Cheaply copyable strings for package and dependency names.
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+lazy_static::lazy_static! {
+	static ref STRINGS :Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+}
+
+/// A string which lives until the process exits. Equal strings share the same allocation.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternedString {
+	inner :&'static str,
+}
+
+impl InternedString {
+	pub fn new(s :&str) -> Self {
+		let mut strings = STRINGS.lock().unwrap();
+		let inner = match strings.get(s) {
+			Some(&inner) => inner,
+			None => {
+				let inner = &*Box::leak(s.to_owned().into_boxed_str());
+				strings.insert(inner);
+				inner
+			}
+		};
+		Self { inner }
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		self.inner
+	}
+}
+
+impl Deref for InternedString {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		self.inner
+	}
+}
+
+impl Borrow<str> for InternedString {
+	fn borrow(&self) -> &str {
+		self.inner
+	}
+}
+
+impl AsRef<str> for InternedString {
+	fn as_ref(&self) -> &str {
+		self.inner
+	}
+}
+
+impl fmt::Debug for InternedString {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self.inner, f)
+	}
+}
+
+impl fmt::Display for InternedString {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(self.inner, f)
+	}
+}
+
+impl Serialize for InternedString {
+	fn serialize<S: Serializer>(&self, serializer :S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.inner)
+	}
+}
+
+impl JsonSchema for InternedString {
+	fn is_referenceable() -> bool {
+		false
+	}
+
+	fn schema_name() -> String {
+		String::schema_name()
+	}
+
+	fn json_schema(gen :&mut SchemaGenerator) -> Schema {
+		String::json_schema(gen)
+	}
+}
+
+impl<'de> Deserialize<'de> for InternedString {
+	fn deserialize<D: Deserializer<'de>>(deserializer :D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		Ok(Self::new(&s))
+	}
+}
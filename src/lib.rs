@@ -1,45 +1,167 @@
+mod baseline;
+mod blame;
+mod cargo_config;
+mod config;
 mod defs;
+mod dependency;
+mod features;
+mod fix;
+#[cfg(feature = "history")]
+mod history;
+mod hook;
+mod interning;
+mod manifest;
+mod metadata;
+mod otel;
+mod output;
+mod package_id;
+mod package_key;
+mod process_builder;
+mod shell;
+mod source;
+mod timings;
+pub mod usage;
+mod wrapper;
 
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ffi::OsString;
-use std::fmt::Write as _;
-use std::io::{self, Write};
-use std::ops::{Deref, Index, IndexMut};
-use std::path::{Path, PathBuf};
+use std::io::{self, BufRead as _, BufReader, Write};
+use std::iter;
+use std::ops::{Index, IndexMut};
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::Mutex;
-use std::{env, fmt};
-
-use ansi_term::Colour;
-use cargo::core::compiler::{DefaultExecutor, Executor, Unit};
-use cargo::core::resolver::ResolveOpts;
-use cargo::core::manifest::Target;
-use cargo::core::package_id::PackageId;
-use cargo::core::shell::Shell;
-use cargo::core::{dependency, InternedString, Package, Resolve};
-use cargo::ops::Packages;
-use cargo::util::command_prelude::{ArgMatchesExt, CompileMode, ProfileChecking};
-use cargo::util::process_builder::ProcessBuilder;
-use cargo::{CargoResult, CliError, CliResult, Config};
-use failure::ResultExt as _;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{cmp, env, fmt, fs, mem};
+
+use ansi_term::{Colour, Style};
+use failure::{Fallible, ResultExt as _};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
-use structopt::clap::{AppSettings, ArgMatches};
+use structopt::clap::AppSettings;
+use url::Url;
 
+use crate::baseline::Baseline;
+use crate::blame::Blame;
+use crate::cargo_config::CargoConfig;
 use crate::defs::CrateSaveAnalysis;
+use crate::features::FeatureValue;
+use crate::hook::OptHook;
+use crate::interning::InternedString;
+use crate::metadata::{Package, Workspace};
+use crate::usage::{Location, Usage, UsageCollector};
+use crate::wrapper::{DoctestRecord, Record, RustdocWrapperConfig, WrapperConfig};
+
+pub use crate::config::Config;
+pub use crate::metadata::Target;
+pub use crate::package_id::PackageId;
+use crate::package_key::{PackageEntry, PackageKey};
+use crate::timings::Timings;
+pub use crate::process_builder::ProcessBuilder;
+pub use crate::shell::{Shell, Verbosity};
+pub use crate::wrapper::run_as_rustc_wrapper;
+
+/// Returns the exit code.
+pub fn run<I: IntoIterator<Item = OsString>, W: Write>(args :I, config :&mut Config, stdout: W) -> Fallible<i32> {
+	run_inner(args, config, stdout, None)
+}
 
-pub fn run<I: IntoIterator<Item = OsString>, W: Write>(args :I, config :&mut Config, stdout: W) -> CliResult {
-	let args = args.into_iter().collect::<Vec<_>>();
+/// Same as [`run`], but detects the usage of dependencies with `collectors` instead of the ones
+/// selected by `--backend`.
+pub fn run_with_collectors<I: IntoIterator<Item = OsString>, W: Write>(
+	args :I,
+	config :&mut Config,
+	stdout :W,
+	collectors :Vec<Box<dyn UsageCollector>>,
+) -> Fallible<i32> {
+	run_inner(args, config, stdout, Some(collectors))
+}
+
+fn run_inner<I: IntoIterator<Item = OsString>, W: Write>(
+	args :I,
+	config :&mut Config,
+	stdout :W,
+	collectors :Option<Vec<Box<dyn UsageCollector>>>,
+) -> Fallible<i32> {
+	// Colors written by `ansi_term` are not rendered by the legacy Windows console otherwise.
+	#[cfg(windows)]
+	let _ = ansi_term::enable_ansi_support();
+
+	let args = expand_response_files(args, config.cwd())?;
 	let Opt::Udeps(opt) = Opt::from_iter_safe(&args)?;
-	let clap_matches = Opt::clap().get_matches_from_safe(args)?;
-	cargo::core::maybe_allow_nightly_features();
-	match opt.run(config, stdout, clap_matches.subcommand_matches("udeps").unwrap())? {
-		0 => Ok(()),
-		code => Err(CliError::code(code)),
+	opt.run(config, stdout, collectors)
+}
+
+/// Writes `err` as `{"success":false,"error":{"message":..,"causes":[..]}}`.
+fn print_json_error(err :&failure::Error, mut stdout :impl Write) -> io::Result<()> {
+	let causes = err.iter_causes().map(ToString::to_string).collect::<Vec<_>>();
+	let json = serde_json::json!({
+		"success" : false,
+		"error" : { "message" : err.to_string(), "causes" : causes },
+	});
+	writeln!(stdout, "{}", json)?;
+	stdout.flush()
+}
+
+/// Unused dependencies were found, unless `--exit-code-unused` says otherwise.
+const EXIT_UNUSED :i32 = 1;
+/// `--max-duration` stopped the analysis.
+const EXIT_INCOMPLETE :i32 = 2;
+/// The toolchain lacks a nightly-only feature the options need, e.g. `-Z` flags on stable.
+const EXIT_UNSUPPORTED_TOOLCHAIN :i32 = 3;
+/// Any other error, as with Cargo.
+const EXIT_ERROR :i32 = 101;
+
+/// Prints `err` and exits, in the same way as Cargo. `--help` and `--version` exit successfully.
+pub fn exit_with_error(err :failure::Error, shell :&mut Shell) -> ! {
+	if let Some(err) = err.downcast_ref::<structopt::clap::Error>() {
+		err.exit();
+	}
+	let _ = shell.error(&err);
+	for cause in err.iter_causes() {
+		let _ = writeln!(shell.err(), "\nCaused by:\n  {}", cause);
+	}
+	std::process::exit(error_exit_code(&err));
+}
+
+/// The exit code for `err`: 3 if the toolchain is not a nightly one and the options need it,
+/// 101 otherwise.
+pub fn error_exit_code(err :&failure::Error) -> i32 {
+	if err.iter_chain().any(|cause| is_nightly_only(&cause.to_string())) {
+		EXIT_UNSUPPORTED_TOOLCHAIN
+	} else {
+		EXIT_ERROR
 	}
 }
 
+/// Whether `message` of `cargo` or `rustc` says that something is available only on nightly.
+fn is_nightly_only(message :&str) -> bool {
+	message.contains("only accepted on the nightly") || message.contains("requires a nightly version")
+		|| message.contains("may not be used on the stable release channel")
+}
+
+/// Replaces every `@path` argument with the lines of the file at `path`, one argument per line.
+fn expand_response_files(args :impl IntoIterator<Item = OsString>, cwd :&Path) -> Fallible<Vec<OsString>> {
+	let mut expanded = vec![];
+	for arg in args {
+		let path = match arg.to_str() {
+			Some(arg) if arg.starts_with('@') && arg.len() > 1 => cwd.join(&arg[1..]),
+			_ => {
+				expanded.push(arg);
+				continue;
+			}
+		};
+		let content = fs::read_to_string(&path)
+			.with_context(|_| format!("could not read response file `{}`", path.display()))?;
+		expanded.extend(content.lines().filter(|l| !l.is_empty()).map(OsString::from));
+	}
+	Ok(expanded)
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(
 	about,
@@ -52,13 +174,15 @@ enum Opt {
 		name = "udeps",
 		after_help(
 			"\
+Arguments can also be read from a file, one per line, with `@path/to/file`.
+
 If the `--package` argument is given, then SPEC is a package ID specification
 which indicates which package should be built. If it is not given, then the
 current package is built. For more information on SPEC and its format, see the
 `cargo help pkgid` command.
 
-All packages in the workspace are checked if the `--workspace` flag is supplied. The
-`--workspace` flag is automatically assumed for a virtual manifest.
+All packages in the workspace are checked if the `--workspace` flag is supplied. For a
+virtual manifest, the `default-members` of the workspace (or all of them) are checked.
 Note that `--exclude` has to be specified in conjunction with the `--workspace` flag.
 
 Compilation can be configured via the use of profiles which are configured in
@@ -72,7 +196,7 @@ The `--profile test` flag can be used to check unit tests with the
 	Udeps(OptUdeps),
 }
 
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Clone, Debug)]
 struct OptUdeps {
 	#[structopt(short, long, help("[cargo] No output printed to stdout"))]
 	quiet: bool,
@@ -85,6 +209,12 @@ struct OptUdeps {
 		help("[cargo] Package(s) to check")
 	)]
 	package: Vec<String>,
+	#[structopt(
+		long,
+		requires("package"),
+		help("Analyze the whole workspace, but report only the packages given with `-p`")
+	)]
+	report_only: bool,
 	#[structopt(long, help("[cargo] Alias for --workspace (deprecated)"))]
 	all: bool,
 	#[structopt(long, help("[cargo] Check all packages in the workspace"))]
@@ -94,9 +224,18 @@ struct OptUdeps {
 		value_name("SPEC"),
 		min_values(1),
 		number_of_values(1),
-		help("[cargo] Exclude packages from the check")
+		help("[cargo] Exclude packages from the check (globs like `experimental-*` are allowed, and the ones with `/` match the directories, e.g. `crates/experimental-*`)")
 	)]
 	exclude: Vec<String>,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		number_of_values(1),
+		help("Exclude the packages in PATH (e.g. `vendor/`) from the check")
+	)]
+	exclude_path: Vec<PathBuf>,
+	#[structopt(long, help("Exclude the packages with `publish = false` from the check"))]
+	exclude_unpublished: bool,
 	#[structopt(
 		short,
 		long,
@@ -165,10 +304,42 @@ struct OptUdeps {
 	features: Vec<String>,
 	#[structopt(long, help("[cargo] Activate all available features"))]
 	all_features: bool,
+	#[structopt(
+		long,
+		value_name("FEATURES"),
+		min_values(0),
+		conflicts_with("all-features"),
+		help("Check once per feature set (default: `features-matrix` in `[workspace.metadata.cargo-udeps]`), and report only the dependencies unused in every one")
+	)]
+	features_matrix: Option<Vec<String>>,
+	#[structopt(
+		long,
+		conflicts_with_all(&["all-features", "features-matrix", "feature-powerset"]),
+		help("Check without additional features, then with each feature of the members (like `cargo hack`)")
+	)]
+	each_feature: bool,
+	#[structopt(
+		long,
+		conflicts_with_all(&["all-features", "features-matrix"]),
+		help("Check with every combination of the features of the members (like `cargo hack`)")
+	)]
+	feature_powerset: bool,
+	#[structopt(
+		long,
+		value_name("N"),
+		requires("feature-powerset"),
+		help("Combine at most N features with `--feature-powerset` (like `cargo hack`)")
+	)]
+	depth: Option<usize>,
 	#[structopt(long, help("[cargo] Do not activate the `default` feature"))]
 	no_default_features: bool,
-	#[structopt(long, value_name("TRIPLE"), help("[cargo] Check for the target triple"))]
-	target: Option<String>,
+	#[structopt(
+		long,
+		value_name("TRIPLE"),
+		number_of_values(1),
+		help("[cargo] Check for the target triple. Dependencies are reported if unused on every one given")
+	)]
+	target: Vec<String>,
 	#[structopt(
 		long,
 		value_name("DIRECTORY"),
@@ -183,7 +354,7 @@ struct OptUdeps {
 		case_insensitive(true),
 		possible_values(&["human", "json", "short"]),
 		default_value("human"),
-		help("[cargo] Error format")
+		help("[cargo] Error format. The JSON messages are written to stderr")
 	)]
 	message_format: Vec<String>,
 	#[structopt(
@@ -207,96 +378,1220 @@ struct OptUdeps {
 	locked: bool,
 	#[structopt(long, help("[cargo] Run without accessing the network"))]
 	offline: bool,
+	#[structopt(
+		long,
+		value_name("KEY=VALUE"),
+		number_of_values(1),
+		help("[cargo] Override a configuration value")
+	)]
+	config: Vec<String>,
+	#[structopt(
+		short("Z"),
+		value_name("FLAG"),
+		number_of_values(1),
+		help("[cargo] Unstable (nightly-only) flags to Cargo, passed to every `cargo` command")
+	)]
+	unstable_flags: Vec<String>,
 	#[structopt(
 		long,
 		value_name("OUTPUT"),
-		default_value("human"),
+		env("CARGO_UDEPS_OUTPUT"),
 		possible_values(OutputKind::VARIANTS),
-		help("Output format"))
+		help("Output format (default: `output` in `udeps.toml`, or `human`)"))
 	]
-	output: OutputKind,
+	output: Option<OutputKind>,
+	#[structopt(
+		long,
+		value_name("PATHS"),
+		default_value("absolute"),
+		possible_values(PathsStyle::VARIANTS),
+		help("Whether the paths in the report are absolute or relative to the workspace root")
+	)]
+	paths: PathsStyle,
+	#[structopt(
+		long,
+		value_name("GROUP"),
+		default_value("package"),
+		possible_values(GroupBy::VARIANTS),
+		help("Whether the human output lists the unused dependencies by package, by dependency or by kind")
+	)]
+	group_by: GroupBy,
+	#[structopt(
+		long,
+		value_name("ORDER"),
+		default_value("name"),
+		possible_values(SortBy::VARIANTS),
+		help("Whether the human output sorts the groups of `--group-by` by name, or by the number of unused dependencies, most first")
+	)]
+	sort: SortBy,
+	#[structopt(
+		long,
+		help("Make the human output list each unused dependency on a single line with the packages declaring it, the most widespread first")
+	)]
+	aggregate: bool,
+	#[structopt(
+		long,
+		help("End the human output with a table of the numbers of unused and ignored dependencies of each package")
+	)]
+	summary: bool,
+	#[structopt(
+		long,
+		value_name("CHARSET"),
+		default_value("auto"),
+		possible_values(Charset::VARIANTS),
+		help("Whether the trees of the human output are drawn with the Unicode box-drawing characters or with ASCII")
+	)]
+	charset: Charset,
+	#[structopt(
+		long,
+		help("Print only the number of unused dependencies instead of the report of `--output`")
+	)]
+	count: bool,
+	#[structopt(
+		long,
+		value_name("BACKEND"),
+		env("CARGO_UDEPS_BACKEND"),
+		possible_values(Backend::VARIANTS),
+		use_delimiter(true),
+		help("How to detect the usage of dependencies (default: `backend` in `udeps.toml`, or `auto`: `save-analysis` if available, `lint` otherwise). Multiple backends are combined")
+	)]
+	backend: Vec<Backend>,
+	#[structopt(
+		long,
+		help("Also run each `--backend` alone, and report the dependencies they disagree on")
+	)]
+	compare_backends: bool,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		env("CARGO_UDEPS_REPORT_PATH"),
+		help("Write the report to PATH instead of stdout")
+	)]
+	report_path: Option<PathBuf>,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		help("Report only the unused dependencies which are not in the baseline PATH")
+	)]
+	baseline: Option<PathBuf>,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		help("Write the unused dependencies to the baseline PATH, and accept them")
+	)]
+	write_baseline: Option<PathBuf>,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		help("Also write the numbers of the unused dependencies to PATH in the text format of Prometheus")
+	)]
+	metrics_file: Option<PathBuf>,
+	#[structopt(
+		long,
+		value_name("URL"),
+		env("CARGO_UDEPS_OTEL_ENDPOINT"),
+		help("Export the spans of the compilation and the analysis to the OTLP/HTTP collector at URL, e.g. `http://localhost:4318`")
+	)]
+	otel_endpoint: Option<Url>,
+	#[structopt(
+		long,
+		value_name("FMTS"),
+		min_values(0),
+		possible_values(TimingsFormat::VARIANTS),
+		use_delimiter(true),
+		help("Show how long each phase and the compilation of each package took (`human`, default), or write it to `udeps/timings.json` or `udeps/timings.html` in the target directory")
+	)]
+	timings: Option<Vec<TimingsFormat>>,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		help("Write the commands run, the files analyzed and the `--extern`s of each unit to PATH")
+	)]
+	log_file: Option<PathBuf>,
+	#[structopt(
+		long,
+		value_name("LEVEL"),
+		requires("log-file"),
+		possible_values(&["error", "warn", "info", "debug", "trace"]),
+		help("The most detailed level written to `--log-file` (default: `debug`)")
+	)]
+	log_level: Option<tracing::Level>,
+	#[structopt(long, requires("report-path"), help("Open the report in a browser"))]
+	open: bool,
+	#[structopt(
+		long,
+		conflicts_with_all(&[
+			"lib", "bin", "bins", "example", "examples", "test", "tests", "bench", "benches",
+			"all-targets", "profile",
+		]),
+		help("Check only what `cargo publish` would ship (lib and bins, without dev-dependencies)")
+	)]
+	publish_check: bool,
+	#[structopt(
+		long,
+		value_name("K/N"),
+		env("CARGO_UDEPS_SHARD"),
+		help("Check only the K-th of N deterministic partitions of the workspace members")
+	)]
+	shard: Option<Shard>,
+	#[structopt(
+		long,
+		value_name("[KIND:]NAME"),
+		env("CARGO_UDEPS_IGNORE"),
+		number_of_values(1),
+		use_delimiter(true),
+		help("Ignore the dependency (KIND: `normal`, `dev` or `build`, every one if omitted)")
+	)]
+	ignore: Vec<DependencySpec>,
+	#[structopt(
+		long,
+		value_name("[KIND:]NAME"),
+		env("CARGO_UDEPS_ONLY"),
+		number_of_values(1),
+		use_delimiter(true),
+		help("Check only the given dependencies (default: `only` in `package.metadata.cargo-udeps`, or every one)")
+	)]
+	only: Vec<DependencySpec>,
+	#[structopt(
+		long,
+		value_name("KINDS"),
+		possible_values(&["normal", "dev", "development", "build"]),
+		use_delimiter(true),
+		help("Check only the dependencies of the given kinds (default: every kind)")
+	)]
+	kinds: Vec<dependency::Kind>,
+	#[structopt(
+		long,
+		value_name("PATH"),
+		min_values(0),
+		help("Check only the packages owning the given files (e.g. from a pre-commit hook)")
+	)]
+	changed_files: Option<Vec<PathBuf>>,
+	#[structopt(
+		long,
+		requires("changed-files"),
+		help("Also check the workspace members depending on the packages owning `--changed-files`")
+	)]
+	with_dependents: bool,
+	#[structopt(
+		long,
+		value_name("REF"),
+		conflicts_with("changed-files"),
+		help("Check only the packages whose files changed since the git REF, and the members depending on them")
+	)]
+	since: Option<String>,
+	#[structopt(
+		long,
+		value_name("DURATION"),
+		env("CARGO_UDEPS_MAX_DURATION"),
+		parse(try_from_str = parse_duration),
+		help("Stop compiling after DURATION (e.g. `90s`, `15m`, `1h`) and report what was analyzed so far")
+	)]
+	max_duration: Option<Duration>,
+	#[structopt(long, help("Fail if some targets or platform-specific dependencies were not analyzed"))]
+	ensure_full_coverage: bool,
+	#[structopt(
+		long,
+		value_name("LINT"),
+		possible_values(Lint::VARIANTS),
+		use_delimiter(true),
+		help(
+			"Fail on LINT, which is otherwise only reported (`never-enabled`, `feature-gated`, `unused-features`, \
+			 `misplaced`, `target-specific` or `unnecessary-ignore`)"
+		)
+	)]
+	deny: Vec<Lint>,
+	#[structopt(
+		long,
+		value_name("KIND"),
+		possible_values(&["normal", "dev", "development", "build"]),
+		use_delimiter(true),
+		help("Report the unused dependencies of KIND as warnings without failing (default: `allow` in `udeps.toml` and in `package.metadata.cargo-udeps`)")
+	)]
+	allow: Vec<dependency::Kind>,
+	#[structopt(
+		long,
+		value_name("N"),
+		default_value("1"),
+		help("Exit with N when unused dependencies are found")
+	)]
+	exit_code_unused: i32,
+	#[structopt(
+		long,
+		help("Exit with 0 whatever the findings, e.g. for scheduled audits. Errors still fail")
+	)]
+	no_fail: bool,
+	#[structopt(long, help("Remove the unused dependencies from Cargo.toml"))]
+	fix: bool,
+	#[structopt(long, help("Tell the commit and the author which added each unused dependency to Cargo.toml (git only)"))]
+	blame: bool,
+	#[structopt(
+		long,
+		conflicts_with("publish-check"),
+		help("Also compile the doctests of the libraries (nightly only), and count the crates they use")
+	)]
+	doc: bool,
+	#[structopt(
+		long,
+		help("Also report the features enabled on used dependencies whose items are never referred to")
+	)]
+	unused_features: bool,
+	#[structopt(
+		long,
+		help("Also tell how the used dependencies are used (e.g. only by derives), in `--output json`")
+	)]
+	classify_usage: bool,
+	#[structopt(subcommand)]
+	subcommand: Option<Subcommand>,
+	/// `ignore` of `udeps.toml` or `[workspace.metadata.cargo-udeps]`.
+	#[structopt(skip)]
+	workspace_ignore: WorkspaceMetadataCargoUdepsIgnore,
+}
+
+#[derive(StructOpt, Clone, Debug)]
+enum Subcommand {
+	#[structopt(about("Merge reports written with `--output json` (e.g. by `--shard`) into one"))]
+	Merge(OptMerge),
+	#[structopt(about("Compare two reports written with `--output json`, e.g. of the base and the head of a pull request"))]
+	Diff(OptDiff),
+	#[cfg(feature = "history")]
+	#[structopt(about("Append a report written with `--output json` to a SQLite database"))]
+	Record(history::OptRecord),
+	#[cfg(feature = "history")]
+	#[structopt(about("Show the numbers of the unused dependencies recorded by `cargo udeps record` over time"))]
+	Trend(history::OptTrend),
+	#[structopt(about("Manage a git hook running `cargo udeps`"))]
+	Hook(OptHook),
+	#[structopt(about("Print the JSON Schema of the reports written with `--output json`"))]
+	Schema,
+	#[structopt(about("Show where a dependency is referred from in the sources"))]
+	Explain(OptExplain),
+}
+
+#[derive(StructOpt, Clone, Debug)]
+struct OptMerge {
+	#[structopt(value_name("REPORT"), required(true), min_values(1), help("Reports to merge"))]
+	reports: Vec<PathBuf>,
+}
+
+impl OptMerge {
+	fn run(&self, config :&Config, output :OutputKind, layout :Layout, stdout :impl Write) -> Fallible<i32> {
+		let mut outcome = Outcome { layout, ..Outcome::default() };
+		// Each report was judged with the options of its run, e.g. `--allow`.
+		let mut success = true;
+		for path in &self.reports {
+			let report = Outcome::read(&config.cwd().join(path))?;
+			success &= report.success;
+			outcome.merge(report);
+		}
+		outcome.success = success;
+		outcome.print(output, config.cwd(), stdout)?;
+		Ok(outcome.exit_code())
+	}
+}
+
+#[derive(StructOpt, Clone, Debug)]
+struct OptDiff {
+	#[structopt(value_name("OLD"), help("The report of the previous run"))]
+	old: PathBuf,
+	#[structopt(value_name("NEW"), help("The report of the current run"))]
+	new: PathBuf,
+}
+
+#[derive(StructOpt, Clone, Debug)]
+struct OptExplain {
+	#[structopt(short, long, value_name("SPEC"), help("Package whose dependency to explain"))]
+	package: Option<String>,
+	#[structopt(value_name("DEPENDENCY"), help("The dependency, as named in Cargo.toml"))]
+	dependency: String,
+}
+
+impl OptExplain {
+	/// Writes the lines referring to the dependency in each member declaring it.
+	fn print(&self, ws :&Workspace, members :&[PackageId], outcome :&Outcome, mut stdout :impl Write) -> Fallible<i32> {
+		let mut declared = false;
+		for &id in members {
+			let package = ws.package(id);
+			let dependency = match package.dependencies().iter().find(|d| *d.name_in_toml() == *self.dependency) {
+				Some(dependency) => dependency.name_in_toml(),
+				None => continue,
+			};
+			declared = true;
+			let unused = outcome.unused_deps.get(&id).map_or(false, |u| u.iter().any(|(_, d)| d == dependency));
+			let locations = outcome.locations.get(&(id, dependency)).into_iter().flatten().collect::<Vec<_>>();
+			if unused {
+				writeln!(stdout, "`{}` does not use {:?}.", id, dependency)?;
+			} else if locations.is_empty() {
+				writeln!(stdout, "`{}` uses {:?}, but the backend does not tell where.", id, dependency)?;
+				writeln!(stdout, "Note: `--backend save-analysis` and `--backend source` tell the lines.")?;
+			} else {
+				writeln!(stdout, "`{}` uses {:?} at:", id, dependency)?;
+				for location in locations {
+					let file = location.file.strip_prefix(ws.root()).unwrap_or(&location.file);
+					writeln!(stdout, "  {}:{}", file.display(), location.line)?;
+				}
+			}
+		}
+		stdout.flush()?;
+		if !declared {
+			return Err(failure::format_err!("no package selected declares `{}`", self.dependency));
+		}
+		Ok(0)
+	}
+}
+
+impl OptDiff {
+	/// Fails if some unused dependencies were introduced.
+	fn run(&self, config :&Config, output :OutputKind, layout :Layout, mut stdout :impl Write) -> Fallible<i32> {
+		// The packages are compared by their names, since the reports may be written in different
+		// checkouts.
+		let unused_deps = |path :&Path| -> Fallible<BTreeSet<_>> {
+			let outcome = Outcome::read(&config.cwd().join(path))?;
+			Ok(outcome
+				.unused_deps
+				.iter()
+				.flat_map(|(id, unused)| unused.iter().map(move |(kind, dependency)| (id.name(), kind, dependency)))
+				.collect())
+		};
+		let (old, new) = (unused_deps(&self.old)?, unused_deps(&self.new)?);
+		let entries = |deps :BTreeSet<_>| {
+			deps.into_iter().map(|(package, kind, dependency)| OutcomeDiffEntry { package, kind, dependency }).collect()
+		};
+		let diff = OutcomeDiff {
+			introduced : entries(new.difference(&old).copied().collect()),
+			fixed : entries(old.difference(&new).copied().collect()),
+			unchanged : entries(new.intersection(&old).copied().collect()),
+		};
+		match output {
+			OutputKind::Human => diff.print_human(&mut stdout, layout)?,
+			OutputKind::Json => writeln!(stdout, "{}", serde_json::to_string(&diff).expect("should not fail"))?,
+			_ => return Err(failure::err_msg("`cargo udeps diff` supports only `--output human` and `--output json`")),
+		}
+		stdout.flush()?;
+		Ok(if diff.introduced.is_empty() { 0 } else { 1 })
+	}
+}
+
+/// The output of `cargo udeps diff`.
+#[derive(Debug, Serialize)]
+struct OutcomeDiff {
+	/// Unused in the new report only.
+	introduced: Vec<OutcomeDiffEntry>,
+	/// Unused in the old report only.
+	fixed: Vec<OutcomeDiffEntry>,
+	unchanged: Vec<OutcomeDiffEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct OutcomeDiffEntry {
+	package: InternedString,
+	kind: dependency::Kind,
+	dependency: InternedString,
+}
+
+impl OutcomeDiff {
+	fn print_human(&self, mut stdout :impl Write, layout :Layout) -> io::Result<()> {
+		if self.introduced.is_empty() && self.fixed.is_empty() {
+			writeln!(stdout, "No unused dependencies were introduced or fixed.")?;
+		}
+		for (header, entries) in &[
+			("introduced unused dependencies:", &self.introduced),
+			("fixed unused dependencies:", &self.fixed),
+			("unchanged unused dependencies:", &self.unchanged),
+		] {
+			if !entries.is_empty() {
+				writeln!(stdout, "{}", header)?;
+				write_by_package(
+					&mut stdout,
+					layout,
+					entries.iter().map(|e| (e.package, e.kind, format!("{:?}", e.dependency))),
+				)?;
+			}
+		}
+		Ok(())
+	}
 }
 
 impl OptUdeps {
 	fn run<W: Write>(
+		&self,
+		config :&mut Config,
+		mut stdout :W,
+		collectors :Option<Vec<Box<dyn UsageCollector>>>,
+	) -> Fallible<i32> {
+		let result = self.run_with_log_file(config, &mut stdout, collectors);
+		// The consumers of `--output json` tell a failure of the run from unused dependencies.
+		if let (Err(err), OutputKind::Json) = (&result, self.output()) {
+			print_json_error(err, &mut stdout)?;
+		}
+		result
+	}
+
+	/// [`run`](Self::run) with the subscriber of `--log-file` set, if any.
+	fn run_with_log_file<W: Write>(
+		&self,
+		config :&mut Config,
+		stdout :W,
+		collectors :Option<Vec<Box<dyn UsageCollector>>>,
+	) -> Fallible<i32> {
+		let log_file = match &self.log_file {
+			Some(path) => {
+				let path = config.cwd().join(path);
+				fs::File::create(&path).with_context(|_| format!("could not create `{}`", path.display()))?
+			}
+			None => return self.run_logged(config, stdout, collectors),
+		};
+		let subscriber = tracing_subscriber::fmt()
+			.with_writer(Mutex::new(log_file))
+			.with_ansi(false)
+			.with_max_level(self.log_level.unwrap_or(tracing::Level::DEBUG))
+			.finish();
+		tracing::subscriber::with_default(subscriber, || self.run_logged(config, stdout, collectors))
+	}
+
+	fn run_logged<W: Write>(
 		&self,
 		config :&mut Config,
 		stdout :W,
-		clap_matches :&ArgMatches
-	) -> CargoResult<i32> {
-		if self.verbose > 0 {
+		collectors :Option<Vec<Box<dyn UsageCollector>>>,
+	) -> Fallible<i32> {
+		tracing::info!("cargo-udeps {}", env!("CARGO_PKG_VERSION"));
+		{
+			// The flags take precedence over `[term]` of `.cargo/config.toml`.
+			let cargo_config = CargoConfig::load(config.cwd(), &self.config)?;
+			let verbosity = match (self.verbose, self.quiet) {
+				(0, false) if cargo_config.get_bool("term.verbose")? == Some(true) => Verbosity::Verbose,
+				(0, false) if cargo_config.get_bool("term.quiet")? == Some(true) => Verbosity::Quiet,
+				(0, false) => Verbosity::Normal,
+				(0, true) => Verbosity::Quiet,
+				_ => Verbosity::Verbose,
+			};
+			let color = self.color.clone().or_else(|| cargo_config.get_str("term.color"));
+			let progress = cargo_config.get_str("term.progress.when");
+
 			let mut shell = config.shell();
-			shell.warn(
-				"currently verbose command informations (\"Running `..`\") are not correct.",
-			)?;
-			shell.warn("for example, `cargo-udeps` does these modifications:")?;
-			shell.warn("- changes `$CARGO` to the value given from `cargo`")?;
-			shell.warn("- sets `$RUST_CONFIG_SAVE_ANASYSIS` (for crates on the local filesystem)")?;
-			shell.warn("- adds `-Z save-analysis` (〃)")?;
-		}
-
-		config.configure(
-			match self.verbose {
-				0 => 0,
-				1 => 1,
-				_ => 2,
-			},
-			if self.quiet { Some(true) } else { None }, // https://docs.rs/cargo/0.39.0/src/cargo/util/config.rs.html#602-604
-			&self.color,
-			self.frozen,
-			self.locked,
-			self.offline,
-			&self.target_dir,
-			&[],
-		)?;
-		let ws = clap_matches.workspace(config)?;
-		let test = match self.profile.as_ref().map(Deref::deref) {
-			None => false,
-			Some("test") => true,
+			shell.set_verbosity(verbosity);
+			shell.set_color_choice(color.as_deref())?;
+			shell.set_progress_choice(progress.as_deref())?;
+		}
+
+		match &self.subcommand {
+			Some(Subcommand::Merge(merge)) => return merge.run(config, self.output(), self.layout(config), stdout),
+			Some(Subcommand::Diff(diff)) => return diff.run(config, self.output(), self.layout(config), stdout),
+			#[cfg(feature = "history")]
+			Some(Subcommand::Record(record)) => return record.run(config),
+			#[cfg(feature = "history")]
+			Some(Subcommand::Trend(trend)) => return trend.run(config, self.output(), self.layout(config), stdout),
+			Some(Subcommand::Hook(hook)) => return hook.run(config),
+			Some(Subcommand::Schema) => return Outcome::print_schema(stdout).map(|()| 0).map_err(Into::into),
+			Some(Subcommand::Explain(explain)) => {
+				let mut udeps = self.clone();
+				udeps.package.extend(explain.package.clone());
+				return udeps.run_checks(config, stdout, collectors);
+			}
+			None => {}
+		}
+		self.run_checks(config, stdout, collectors)
+	}
+
+	/// Checks the selected packages, or explains a dependency of them with `cargo udeps explain`.
+	fn run_checks<W: Write>(
+		&self,
+		config :&mut Config,
+		stdout :W,
+		collectors :Option<Vec<Box<dyn UsageCollector>>>,
+	) -> Fallible<i32> {
+		match self.profile.as_deref() {
+			None | Some("test") => {}
 			Some(profile) => return Err(failure::format_err!(
 				"unknown profile: `{}`, only `test` is currently supported",
 				profile,
 			)),
-		};
-		let mode = CompileMode::Check { test };
-		let pc = ProfileChecking::Unchecked;
-		let compile_opts = clap_matches.compile_options(config, mode, Some(&ws), pc)?;
-
-		let opts = ResolveOpts::new(
-			/*dev_deps*/ true,
-			&self.features,
-			self.all_features,
-			!self.no_default_features,
-
-		);
-		let ws_resolve = cargo::ops::resolve_ws_with_opts(
-			&ws,
-			opts,
-			&Packages::All.to_package_id_specs(&ws)?,
-		)?;
-
-		let packages = ws_resolve.pkg_set
-			.get_many(ws_resolve.pkg_set.package_ids())?
-			.into_iter()
-			.map(|p| (p.package_id(), p))
-			.collect::<HashMap<_, _>>();
+		}
 
-		let dependency_names = ws
-			.members()
-			.map(|from| {
-				let val = DependencyNames::new(from, &packages, &ws_resolve.targeted_resolve, &mut config.shell())?;
-				let key = from.package_id();
-				Ok((key, val))
-			})
-			.collect::<CargoResult<HashMap<_, _>>>()?;
+		let run_span = config.trace().start("cargo udeps");
+		let resolve_span = config.trace().start("resolve");
+		let ws = Workspace::load(config, &self.metadata_args(config)?)?;
+		let udeps = self.with_workspace_config(&ws)?;
+		let ws = if udeps.target != self.target {
+			Workspace::load(config, &udeps.metadata_args(config)?)?
+		} else {
+			ws
+		};
+		config.trace().end(resolve_span);
+		let udeps_dir = udeps.target_dir(config, &ws).join("udeps");
+		let code = udeps.run_in_workspace(config, ws, stdout, collectors)?;
+		config.trace().set_attribute(run_span, "cargo_udeps.exit_code", code);
+		config.trace().end(run_span);
+		udeps.finish_trace(config, &udeps_dir)?;
+		Ok(code)
+	}
 
-		let data = Arc::new(Mutex::new(ExecData::new(config)?));
-		let exec :Arc<dyn Executor + 'static> = Arc::new(Exec { data : data.clone() });
-		cargo::ops::compile_with_exec(&ws, &compile_opts, &exec)?;
-		let data = data.lock().unwrap();
+	/// Exports the spans of the run with `--otel-endpoint`, and shows or writes the timings with
+	/// `--timings`.
+	fn finish_trace(&self, config :&Config, udeps_dir :&Path) -> Fallible<()> {
+		if let Some(endpoint) = &self.otel_endpoint {
+			let exported = config.trace().export(endpoint);
+			if let Err(err) = exported {
+				config.shell().warn(format_args!("could not export the trace to `{}`: {}", endpoint, err))?;
+			}
+		}
+		if let Some(formats) = &self.timings {
+			let timings = Timings::new(&config.trace());
+			if formats.is_empty() || formats.contains(&TimingsFormat::Human) {
+				timings.print_human(config.shell().err())?;
+			}
+			for (format, file_name) in &[(TimingsFormat::Json, "timings.json"), (TimingsFormat::Html, "timings.html")] {
+				if formats.contains(format) {
+					let path = udeps_dir.join(file_name);
+					fs::create_dir_all(udeps_dir)
+						.with_context(|_| format!("could not create `{}`", udeps_dir.display()))?;
+					let file = fs::File::create(&path)
+						.with_context(|_| format!("could not create `{}`", path.display()))?;
+					match format {
+						TimingsFormat::Json => timings.print_json(io::BufWriter::new(file))?,
+						_ => timings.print_html(io::BufWriter::new(file))?,
+					}
+					config.shell().info(format_args!("Wrote the timings to `{}`", path.display()))?;
+				}
+			}
+		}
+		Ok(())
+	}
 
-		let mut used_normal_dev_dependencies = HashSet::new();
+	/// Fills in the options the flags leave unset from `udeps.toml` or
+	/// `[workspace.metadata.cargo-udeps]`.
+	fn with_workspace_config(&self, ws :&Workspace) -> Fallible<Self> {
+		let workspace_config = WorkspaceMetadataCargoUdeps::load(ws)?;
+		let mut udeps = self.clone();
+		if udeps.output.is_none() {
+			udeps.output = workspace_config.output.map(|o| o.parse()).transpose().map_err(failure::err_msg)?;
+		}
+		if udeps.backend.is_empty() {
+			udeps.backend = workspace_config
+				.backend
+				.iter()
+				.map(|b| b.parse())
+				.collect::<Result<_, _>>()
+				.map_err(failure::err_msg)?;
+		}
+		if udeps.target.is_empty() {
+			udeps.target = workspace_config.target;
+		}
+		udeps.ensure_full_coverage |= workspace_config.ensure_full_coverage;
+		if udeps.allow.is_empty() {
+			udeps.allow = workspace_config
+				.allow
+				.iter()
+				.map(|k| k.parse())
+				.collect::<Result<_, _>>()
+				.map_err(|e| failure::format_err!("could not parse `allow` of the workspace: {}", e))?;
+		}
+		udeps.workspace_ignore = workspace_config.ignore;
+		Ok(udeps)
+	}
+
+	fn output(&self) -> OutputKind {
+		self.output.unwrap_or(OutputKind::Human)
+	}
+
+	/// Whether `package_ignore` (the [`package_ignore`] of `package`), the `ignore` of the
+	/// workspace or `--ignore` lists `dependency`.
+	fn ignored(
+		&self,
+		package :&Package,
+		package_ignore :&PackageMetadataCargoUdepsIgnore,
+		kind :dependency::Kind,
+		dependency :InternedString,
+	) -> bool {
+		package_ignore.contains(kind, dependency)
+			|| self.workspace_ignore.contains(package, kind, dependency)
+			|| self.ignore.iter().any(|i| i.matches(kind, dependency))
+	}
+
+	/// Whether `dependency` is checked, i.e. `--only` or `only` of the package metadata (if
+	/// any) lists it, and `--kinds` (if given) lists its kind.
+	fn in_scope(&self, package :&Package, kind :dependency::Kind, dependency :InternedString) -> Fallible<bool> {
+		if !self.kinds.is_empty() && !self.kinds.contains(&kind) {
+			return Ok(false);
+		}
+		let only = if self.only.is_empty() { package_only(package)? } else { self.only.clone() };
+		Ok(only.is_empty() || only.iter().any(|o| o.matches(kind, dependency)))
+	}
+
+	/// Whether the unused dependencies of `kind` in `package` are reported without failing, i.e.
+	/// `--allow` or `allow` of the package metadata lists `kind`.
+	fn allows(&self, package :&Package, kind :dependency::Kind) -> Fallible<bool> {
+		Ok(self.allow.contains(&kind) || package_allow(package)?.contains(&kind))
+	}
+
+	fn run_in_workspace<W: Write>(
+		&self,
+		config :&mut Config,
+		ws :Workspace,
+		stdout :W,
+		collectors :Option<Vec<Box<dyn UsageCollector>>>,
+	) -> Fallible<i32> {
+		// `cargo package` ships the `lib` and the `bin`s. Tests, examples and benches may be
+		// included as files but they are never built by dependents.
+		let shipped_files = if self.publish_check {
+			let mut shipped_files = HashSet::new();
+			for member in ws.members().filter(|m| is_published(m)) {
+				shipped_files.extend(self.list_files(config, member)?);
+			}
+			Some(shipped_files)
+		} else {
+			None
+		};
+
+		// With `--report-only`, the workspace is analyzed as with `--workspace`, and the outcome
+		// is narrowed down to the `-p` packages at the end.
+		let reported = if self.report_only {
+			Some(self.selected_members(config, &ws)?.iter().map(|m| m.package_id()).collect::<HashSet<_>>())
+		} else {
+			None
+		};
+		let mut members = if self.report_only {
+			let mut udeps = self.clone();
+			udeps.package.clear();
+			udeps.workspace = true;
+			udeps.selected_members(config, &ws)?
+		} else {
+			self.selected_members(config, &ws)?
+		};
+		let num_selected = members.len();
+		let mut skipped = vec![];
+		for member in &members {
+			if package_metadata(member)?.skip {
+				config.shell().info(format_args!("Skipping `{}` (`package.metadata.cargo-udeps.skip`)", member.name()))?;
+				skipped.push(member.package_id());
+			}
+		}
+		members.retain(|m| !skipped.contains(&m.package_id()));
+		if self.publish_check || self.exclude_unpublished {
+			members.retain(|m| is_published(m));
+		}
+		if let Some(shard) = self.shard {
+			members.retain(|m| shard.contains(m));
+		}
+		if let Some(changed_files) = &self.changed_files {
+			let changed = changed_members(&ws, config.cwd(), changed_files, self.with_dependents);
+			members.retain(|m| changed.contains(&m.package_id()));
+		}
+		if let Some(since) = &self.since {
+			let changed_files = changed_since(config, since)?;
+			let changed = changed_members(&ws, config.cwd(), &changed_files, true);
+			members.retain(|m| changed.contains(&m.package_id()));
+		}
+		if members.len() != num_selected && members.is_empty() {
+			config.shell().info("No packages to check")?;
+			let outcome = Outcome { success : true, ..Outcome::default() };
+			// CI may expect the metrics file with every run.
+			self.write_metrics(config, &outcome, &[])?;
+			self.report(config, &outcome, stdout)?;
+			return Ok(0);
+		}
+
+		let collectors = match collectors {
+			Some(collectors) => collectors,
+			None => {
+				let mut backends = vec![];
+				for &backend in self.backend.iter().chain(Some(&Backend::Auto).filter(|_| self.backend.is_empty())) {
+					backends.push(match backend {
+						Backend::Auto => Backend::detect(config)?,
+						backend => backend,
+					});
+				}
+				backends.into_iter().map(Backend::collector).collect()
+			}
+		};
+		if self.compare_backends && collectors.len() < 2 {
+			return Err(failure::format_err!("`--compare-backends` needs two or more `--backend`s"));
+		}
+		if self.compare_backends && self.target.len() > 1 {
+			return Err(failure::format_err!("`--compare-backends` takes at most one `--target`"));
+		}
+		let scans_sources = collectors.iter().any(|c| !c.compiles());
+		let members = members.iter().map(|m| m.package_id()).collect::<Vec<_>>();
+
+		let mut outcome = if self.target.len() > 1 {
+			let mut outcomes = vec![];
+			for target in &self.target {
+				config.shell().status("Target", target)?;
+				let mut udeps = self.clone();
+				udeps.target = vec![target.clone()];
+				let ws = Workspace::load(config, &udeps.metadata_args(config)?)?;
+				let outcome = udeps.analyze_features(config, &ws, &members, &shipped_files, &collectors)?;
+				outcomes.push((target.clone(), outcome));
+			}
+			let by_target = outcomes
+				.iter()
+				.map(|(target, outcome)| (target.clone(), outcome.unused_deps.clone()))
+				.collect();
+			// Dependencies used only on some targets are not feature-gated.
+			let mut feature_gated = outcomes.iter_mut().flat_map(|(_, o)| o.feature_gated.drain(..)).collect::<Vec<_>>();
+			feature_gated.sort_by_key(|g| (g.package, g.kind, g.dependency));
+			feature_gated.dedup_by_key(|g| (g.package, g.kind, g.dependency));
+			let mut outcome = Outcome::intersect(outcomes);
+			outcome.feature_gated = feature_gated;
+			outcome.by_target = by_target;
+			outcome
+		} else {
+			self.analyze_features(config, &ws, &members, &shipped_files, &collectors)?
+		};
+		if self.compare_backends {
+			outcome.backend_disagreements = self.backend_disagreements(config, &ws, &members, &shipped_files, &collectors)?;
+		}
+
+		for warning in &outcome.warnings {
+			config.shell().warn(&warning.message)?;
+		}
+		let unnecessary_ignores = outcome
+			.unnecessary_ignores
+			.iter()
+			.map(|u| (u.package, u.kind, u.dependency))
+			.collect::<Vec<_>>();
+		for (package, kind, dependency) in unnecessary_ignores {
+			let message = format!("`{}` ignores `{}` ({:?}), which is used", ws.package(package).name(), dependency, kind);
+			outcome.warn(&mut config.shell(), "unnecessary-ignore", Some(package), message)?;
+		}
+		for &id in &members {
+			let package = ws.package(id);
+			let ignore = package_ignore(package)?;
+			let member_ignore = self.workspace_ignore.members.get(&*package.name());
+			let mut entries = ignore.iter().chain(member_ignore.into_iter().flat_map(|i| i.iter())).collect::<Vec<_>>();
+			entries.sort();
+			entries.dedup();
+			for (kind, name) in entries {
+				if !package.dependencies().iter().any(|d| d.kind() == kind && *d.name_in_toml() == *name) {
+					let message = format!("`{}` ignores `{}` ({:?}), which it does not declare", package.name(), name, kind);
+					outcome.warn(&mut config.shell(), "stale-ignore", Some(id), message)?;
+					outcome.stale_ignores.push(OutcomeStaleIgnore { package : id, kind, dependency : name.to_owned() });
+				}
+			}
+			for (kind, name) in features::never_enabled(&ws, package)? {
+				if !self.in_scope(package, kind, name)? {
+					continue;
+				}
+				outcome
+					.never_enabled
+					.entry(id)
+					.or_insert(OutcomeUnusedDeps::new(package.manifest_path())?)
+					.unused_deps_mut(kind)
+					.insert(name);
+			}
+			if self.unused_features {
+				let unused = outcome.unused_deps.get(&id);
+				let is_unused = |kind, name| unused.map_or(false, |u| u.iter().any(|d| d == (kind, name)));
+				for unused in features::unused_features(&ws, package, is_unused)? {
+					let (kind, dependency) = (unused.kind, unused.name);
+					if !self.in_scope(package, kind, dependency)? {
+						continue;
+					}
+					if !unused.features.is_empty() {
+						let features = unused.features;
+						outcome.unused_features.push(OutcomeUnusedFeatures { package : id, kind, dependency, features });
+					}
+					if !unused.default.is_empty() {
+						let features = unused.default;
+						outcome.unused_default_features.push(OutcomeUnusedFeatures { package : id, kind, dependency, features });
+					}
+				}
+			}
+			if self.classify_usage {
+				let unused = outcome.unused_deps.get(&id);
+				let is_unused = |kind, name| unused.map_or(false, |u| u.iter().any(|d| d == (kind, name)));
+				for ((kind, dependency), kinds) in classify_usage(&ws, package, is_unused)? {
+					if self.in_scope(package, kind, dependency)? {
+						outcome.usage_kinds.push(OutcomeUsageKinds { package : id, kind, dependency, kinds });
+					}
+				}
+			}
+		}
+
+		if self.baseline.is_some() || self.write_baseline.is_some() {
+			let mut baseline = match &self.baseline {
+				Some(path) => Baseline::read(&config.cwd().join(path))?,
+				None => Baseline::default(),
+			};
+			if let Some(path) = &self.write_baseline {
+				let current = Baseline::new(&outcome.unused_deps);
+				current.write(&config.cwd().join(path))?;
+				config.shell().status("Writing", path.display())?;
+				baseline.extend(current);
+			}
+			let (removed, fixed) = baseline.apply(&members, &mut outcome.unused_deps);
+			if removed > 0 {
+				config.shell().info(format_args!("{} unused dependencies are in the baseline", removed))?;
+			}
+			if fixed > 0 {
+				config.shell().info(format_args!(
+					"{} entries of the baseline are no longer unused, update it with `--write-baseline`",
+					fixed,
+				))?;
+			}
+		}
+
+		for (&id, unused) in outcome.unused_deps.iter_mut().chain(&mut outcome.never_enabled) {
+			let deps = ws.package(id).dependencies();
+			for dep in deps {
+				if let Some(name) = dep.explicit_name_in_toml() {
+					if unused.iter().any(|(kind, n)| (kind, n) == (dep.kind(), name)) {
+						unused.renamed.insert(name, dep.package_name().to_owned());
+					}
+				}
+			}
+			for (kind, name) in unused.iter().collect::<Vec<_>>() {
+				let platforms = deps
+					.iter()
+					.filter(|d| d.kind() == kind && d.name_in_toml() == name)
+					.map(|d| d.platform())
+					.collect::<Option<BTreeSet<_>>>();
+				if let Some(platforms) = platforms.filter(|p| !p.is_empty()) {
+					unused.platforms.insert(name, platforms.into_iter().collect::<Vec<_>>().join(", "));
+				}
+			}
+			if self.blame {
+				let manifest_path = Path::new(&unused.manifest_path);
+				let manifest = fs::read_to_string(manifest_path)?;
+				for (kind, name) in unused.iter().collect::<Vec<_>>() {
+					if let Some(line) = manifest::dependency_line(&manifest, kind, &name) {
+						if let Some(blame) = Blame::line(manifest_path, line)? {
+							unused.blame.insert(name, blame);
+						}
+					}
+				}
+			}
+		}
+
+		if outcome.unused_deps.values().any(|u| !u.is_empty()) {
+			let mut inherited = HashMap::new();
+			for member in ws.members() {
+				inherited.insert(member.package_id(), fix::inherited_deps(member.manifest_path())?);
+			}
+			for (&id, unused) in &outcome.unused_deps {
+				for (kind, name) in unused.iter() {
+					if !inherited[&id].contains(&(kind, name.to_string())) {
+						continue;
+					}
+					let entry = outcome.inherited.entry(name).or_insert_with(OutcomeInherited::default);
+					entry.unused_by.insert(id);
+				}
+			}
+			let unused_deps = &outcome.unused_deps;
+			let is_unused = |id, kind, name| {
+				unused_deps.get(id).map_or(false, |u :&OutcomeUnusedDeps| u.iter().any(|d| d == (kind, name)))
+			};
+			for (&name, entry) in &mut outcome.inherited {
+				// Members which inherit it for another kind, or in which it is used.
+				entry.used_by = inherited
+					.iter()
+					.filter(|(id, deps)| deps.iter().any(|(kind, n)| *n == *name && !is_unused(*id, *kind, name)))
+					.map(|(&id, _)| id)
+					.collect();
+			}
+		}
+
+		let mut suggestions = vec![];
+		for (&id, unused) in &outcome.unused_deps {
+			let manifest = fs::read_to_string(&unused.manifest_path)?;
+			for (kind, name) in unused.iter() {
+				for declaration in manifest::find_dependency(&manifest, kind, &name) {
+					suggestions.push(OutcomeSuggestion::removal(id, unused, kind, name, declaration));
+				}
+			}
+		}
+		for unused in &outcome.unused_default_features {
+			let manifest_path = ws.package(unused.package).manifest_path();
+			let manifest = fs::read_to_string(manifest_path)?;
+			let manifest_path = manifest_path
+				.to_str()
+				.ok_or_else(|| failure::format_err!("{:?} is not valid utf-8", manifest_path))?;
+			for declaration in manifest::find_dependency(&manifest, unused.kind, &unused.dependency) {
+				let lines = &manifest[declaration.span.clone()];
+				if let Some(replacement) = manifest::without_default_features(lines) {
+					suggestions.push(OutcomeSuggestion::new(
+						unused.package,
+						manifest_path.to_owned(),
+						unused.kind,
+						unused.dependency,
+						declaration,
+						replacement,
+					));
+				}
+			}
+		}
+		outcome.suggestions = suggestions;
+
+		if let Some(reported) = &reported {
+			outcome.retain_packages(|id| reported.contains(&id));
+		}
+
+		// The unused dependencies of the allowed kinds, or of the packages with `deny = false` or
+		// within `max-unused`, are warned about instead.
+		let mut fails = false;
+		let mut allowed = vec![];
+		for (&id, unused) in &outcome.unused_deps {
+			let package = ws.package(id);
+			let metadata = package_metadata(package)?;
+			let mut denied = vec![];
+			for &kind in &[dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build] {
+				let names = unused.iter().filter(|&(k, _)| k == kind).map(|(_, name)| format!("{:?}", name)).collect::<Vec<_>>();
+				if names.is_empty() {
+					continue;
+				}
+				if self.allows(package, kind)? {
+					allowed.push((id, kind, names));
+				} else {
+					denied.push((id, kind, names));
+				}
+			}
+			let num_denied = denied.iter().map(|(_, _, names)| names.len()).sum::<usize>();
+			if metadata.deny == Some(false) || metadata.max_unused.map_or(false, |max| num_denied <= max) {
+				allowed.extend(denied);
+			} else {
+				fails |= num_denied > 0;
+			}
+		}
+		let allowed = allowed
+			.into_iter()
+			.map(|(id, kind, names)| {
+				let message = format!("unused {} of `{}` are allowed: {}", table_name(kind), id.name(), names.join(", "));
+				(id, message)
+			})
+			.collect::<Vec<_>>();
+		for (id, message) in allowed {
+			outcome.warn(&mut config.shell(), "allowed", Some(id), message)?;
+		}
+
+		// Only the unused dependencies go through `--allow`, the baseline and `max-unused`. The
+		// other findings are advice unless `--deny` names them.
+		let denied = |lint, found :bool| found && self.deny.contains(&lint);
+		outcome.success = !fails
+			&& !denied(Lint::NeverEnabled, !outcome.never_enabled.is_empty())
+			&& !denied(Lint::FeatureGated, !outcome.feature_gated.is_empty())
+			&& !denied(
+				Lint::UnusedFeatures,
+				!outcome.unused_features.is_empty() || !outcome.unused_default_features.is_empty(),
+			)
+			&& !denied(Lint::Misplaced, !outcome.misplaced.is_empty())
+			&& !denied(Lint::TargetSpecific, !outcome.target_specific.is_empty())
+			&& !denied(Lint::UnnecessaryIgnore, !outcome.unnecessary_ignores.is_empty())
+			&& !(self.ensure_full_coverage && !outcome.coverage.is_empty());
+
+		// Advice such as `target_specific` comes with its note even if the run succeeds.
+		let mut notes = vec![];
+		if !outcome.success {
+			if !self.all_targets && !self.publish_check && !scans_sources {
+				let suggest_all_targets = !self.lib
+					&& !self.bins
+					&& !self.examples
+					&& !self.tests
+					&& !self.benches
+					&& self.bin.is_empty()
+					&& self.example.is_empty()
+					&& self.test.is_empty()
+					&& self.bench.is_empty();
+				notes.push(OutcomeNote::MissingAllTargets { suggest_all_targets });
+			}
+		}
+		if !outcome.never_enabled.is_empty() {
+			notes.push(OutcomeNote::NeverEnabled);
+		}
+		if !outcome.feature_gated.is_empty() {
+			notes.push(OutcomeNote::FeatureGated);
+		}
+		if !outcome.unused_features.is_empty() {
+			notes.push(OutcomeNote::UnusedFeatures);
+		}
+		if !outcome.unused_default_features.is_empty() {
+			notes.push(OutcomeNote::UnusedDefaultFeatures);
+		}
+		if !outcome.misplaced.is_empty() {
+			notes.push(OutcomeNote::Misplaced);
+		}
+		if !outcome.target_specific.is_empty() {
+			notes.push(OutcomeNote::TargetSpecific);
+		}
+		if !outcome.success {
+			if outcome.non_lib {
+				notes.push(OutcomeNote::NonLibDependencies);
+			}
+			notes.push(OutcomeNote::PossibleFalsePositive { doctests_analyzed : self.doc });
+		}
+		outcome.notes = notes;
+
+		if let Some(Subcommand::Explain(explain)) = &self.subcommand {
+			return explain.print(&ws, &members, &outcome, stdout);
+		}
+
+		if self.fix {
+			self.fix(config, &ws, &outcome)?;
+		}
+
+		self.write_metrics(config, &outcome, &members)?;
+
+		if self.paths == PathsStyle::Relative {
+			outcome.relativize_paths(ws.root());
+		}
+		outcome.layout = self.layout(config);
+
+		self.report(config, &outcome, stdout)?;
+		Ok(match outcome.exit_code() {
+			_ if self.no_fail => 0,
+			EXIT_UNUSED => self.exit_code_unused,
+			code => code,
+		})
+	}
+
+	/// Writes the metrics of the `checked` members to `--metrics-file`, if given.
+	fn write_metrics(&self, config :&Config, outcome :&Outcome, checked :&[PackageId]) -> Fallible<()> {
+		if let Some(path) = &self.metrics_file {
+			let path = config.cwd().join(path);
+			let mut metrics = vec![];
+			output::print_metrics(outcome, checked, &mut metrics)?;
+			fs::write(&path, metrics).with_context(|_| format!("could not write `{}`", path.display()))?;
+		}
+		Ok(())
+	}
+
+	/// For `--compare-backends`: the dependencies some of `collectors` find unused, the others used.
+	fn backend_disagreements(
+		&self,
+		config :&Config,
+		ws :&Workspace,
+		members :&[PackageId],
+		shipped_files :&Option<HashSet<PathBuf>>,
+		collectors :&[Box<dyn UsageCollector>],
+	) -> Fallible<Vec<OutcomeBackendDisagreement>> {
+		let mut outcomes = vec![];
+		for collector in collectors {
+			config.shell().status("Comparing", format!("`--backend {}`", collector.name()))?;
+			let outcome = self.analyze_features(config, ws, members, shipped_files, std::slice::from_ref(collector))?;
+			outcomes.push((collector.name(), outcome));
+		}
+		let mut unused_by = BTreeMap::<_, Vec<String>>::new();
+		for (name, outcome) in &outcomes {
+			for (&id, deps) in &outcome.unused_deps {
+				for (kind, dependency) in deps.iter() {
+					unused_by.entry((id, kind, dependency)).or_default().push(name.clone());
+				}
+			}
+		}
+		let disagreements = unused_by
+			.into_iter()
+			.filter(|(_, unused_by)| unused_by.len() < outcomes.len())
+			.map(|((package, kind, dependency), unused_by)| {
+				// The lines the other backends see it referred from, if they tell.
+				let used_by = outcomes
+					.iter()
+					.filter(|(name, _)| !unused_by.contains(name))
+					.map(|(name, outcome)| {
+						let locations = outcome
+							.locations
+							.get(&(package, dependency))
+							.into_iter()
+							.flatten()
+							.map(|location| {
+								let file = location.file.strip_prefix(ws.root()).unwrap_or(&location.file);
+								format!("{}:{}", file.display(), location.line)
+							})
+							.collect();
+						(name.clone(), locations)
+					})
+					.collect();
+				OutcomeBackendDisagreement { package, kind, dependency, unused_by, used_by }
+			})
+			.collect();
+		Ok(disagreements)
+	}
+
+	/// Analyzes `members` once, or once for each feature set of the matrix.
+	fn analyze_features(
+		&self,
+		config :&Config,
+		ws :&Workspace,
+		members :&[PackageId],
+		shipped_files :&Option<HashSet<PathBuf>>,
+		collectors :&[Box<dyn UsageCollector>],
+	) -> Fallible<Outcome> {
+		let outcome = match self.features_matrix(ws, members)? {
+			None => self.analyze(config, ws, members, shipped_files, collectors)?,
+			Some(matrix) => {
+				let mut outcomes = vec![];
+				for features in matrix {
+					let shown = if features.is_empty() { "(none)" } else { &*features };
+					config.shell().status("Features", shown)?;
+					let mut udeps = self.clone();
+					udeps.features.push(features.clone());
+					let ws = Workspace::load(config, &udeps.metadata_args(config)?)?;
+					let outcome = udeps.analyze(config, &ws, members, shipped_files, collectors)?;
+					outcomes.push((features, outcome));
+				}
+				let mut outcome = Outcome::intersect(outcomes);
+				// Optional dependencies are expected to be used only with some features.
+				outcome.feature_gated.retain(|g| {
+					!ws.package(g.package).dependencies().iter().any(|d| {
+						d.name_in_toml() == g.dependency && d.kind() == g.kind && d.is_optional()
+					})
+				});
+				outcome
+			}
+		};
+		Ok(outcome)
+	}
+
+	/// Analyzes `members` with the features `ws` was loaded with.
+	fn analyze(
+		&self,
+		config :&Config,
+		ws :&Workspace,
+		members :&[PackageId],
+		shipped_files :&Option<HashSet<PathBuf>>,
+		collectors :&[Box<dyn UsageCollector>],
+	) -> Fallible<Outcome> {
+		let members = members.iter().map(|&id| ws.package(id)).collect::<Vec<_>>();
+		let dependency_names = members
+			.iter()
+			.map(|from| {
+				let val = DependencyNames::new(from, ws)?;
+				let key = from.package_id();
+				Ok((key, val))
+			})
+			.collect::<Fallible<HashMap<_, _>>>()?;
+		let package_ignores = members
+			.iter()
+			.map(|package| Ok((package.package_id(), package_ignore(package)?)))
+			.collect::<Fallible<HashMap<_, _>>>()?;
+
+		let compiles = collectors.iter().any(|c| c.compiles());
+		let scans_sources = collectors.iter().any(|c| !c.compiles());
+
+		let (cmd_infos, timed_out) = if compiles {
+			self.compile(config, &ws, &members, &collectors)?
+		} else {
+			(vec![], false)
+		};
+		let doctests = if self.doc && !timed_out {
+			let span = config.trace().start("doctests");
+			let doctests = self.check_doctests(config, &ws, &members, &cmd_infos)?;
+			config.trace().end(span);
+			doctests
+		} else {
+			vec![]
+		};
+		let analysis_span = config.trace().start("analyze");
+		let mut ambiguous_lib_names = vec![];
+		let mut locations = BTreeMap::new();
+		let members = members.iter().map(|m| m.package_id()).collect::<HashSet<_>>();
+
+		let mut used_normal_dev_dependencies = HashSet::new();
 		let mut used_build_dependencies = HashSet::new();
+		// Normal dependencies used by the targets other than tests, benches and examples.
+		let mut used_outside_tests = HashSet::new();
+		let mut analyzed_outside_tests = HashSet::new();
+		// Normal dependencies which the sources of build scripts refer to.
+		let mut used_by_build_scripts = HashSet::new();
 		let mut normal_dependencies = dependency_names
 			.iter()
 			.flat_map(|(&m, d)| d[dependency::Kind::Normal].non_lib.iter().map(move |&s| (m, s)))
@@ -310,23 +1605,53 @@ impl OptUdeps {
 			.flat_map(|(&m, d)| d[dependency::Kind::Build].non_lib.iter().map(move |&s| (m, s)))
 			.collect::<HashSet<_>>();
 
-		for cmd_info in data.relevant_cmd_infos.iter() {
-			let analysis = cmd_info.get_save_analysis(&mut config.shell())?;
+		for (i, cmd_info) in cmd_infos.iter().enumerate() {
+			config.shell().progress("Analyzing", i, cmd_infos.len(), &cmd_info.crate_name)?;
+			if let (Some(shipped_files), Some(src_path)) = (&shipped_files, &cmd_info.src_path) {
+				// Targets whose sources are excluded from the `.crate` file are not published.
+				if !shipped_files.contains(src_path) {
+					continue;
+				}
+			}
+			let span = config.trace().start(format!("collect {}", cmd_info.crate_name));
+			config.trace().set_attribute(span, "cargo.target", &cmd_info.target_description);
+			let mut usage = Usage::default();
+			for collector in collectors.iter().filter(|c| c.compiles()) {
+				usage.extend(collector.collect_compiled(cmd_info, &mut config.shell())?);
+			}
+			usage.resolve(cmd_info, ws);
+			config.trace().end(span);
 			// may not be workspace member
 			if let Some(dependency_names) = dependency_names.get(&cmd_info.pkg) {
+				for names in &[&dependency_names.normal, &dependency_names.development, &dependency_names.build] {
+					insert_locations(cmd_info.pkg, &usage, &names.by_extern_crate_name, &names.by_lib_true_snakecased_name, &mut locations);
+				}
+				for name in &usage.lib_names {
+					for names in &[&dependency_names.normal, &dependency_names.development, &dependency_names.build] {
+						let mut candidates = names.by_lib_true_snakecased_name.get(name).into_iter().flatten().collect::<Vec<_>>();
+						if candidates.len() > 1 {
+							candidates.sort();
+							let candidates = candidates.iter().map(|c| format!("`{}`", c)).collect::<Vec<_>>().join(", ");
+							let message = format!(
+								"`{}` uses `{}`, which may be any of {}. They are all regarded as used",
+								ws.package(cmd_info.pkg).name(),
+								name,
+								candidates,
+							);
+							let warning = OutcomeWarning { code : "ambiguous-lib-name".to_owned(), package : Some(cmd_info.pkg), message };
+							if !ambiguous_lib_names.contains(&warning) {
+								ambiguous_lib_names.push(warning);
+							}
+						}
+					}
+				}
 				let collect_names = |
 					by_extern_crate_name: &HashMap<String, InternedString>,
 					by_lib_true_snakecased_name: &HashMap<String, HashSet<InternedString>>,
 					used_dependencies: &mut HashSet<(PackageId, InternedString)>,
 					dependencies: &mut HashSet<(PackageId, InternedString)>,
 				| {
-					for ext in &analysis.prelude.external_crates {
-						if let Some(dependency_names) = by_lib_true_snakecased_name.get(&*ext.id.name) {
-							for dependency_name in dependency_names {
-								used_dependencies.insert((cmd_info.pkg, *dependency_name));
-							}
-						}
-					}
+					insert_used(cmd_info.pkg, &usage, by_extern_crate_name, by_lib_true_snakecased_name, used_dependencies);
 
 					for (name, _) in &cmd_info.externs {
 						// We ignore the `lib` that `bin`s, `example`s, and `test`s in the same
@@ -355,188 +1680,1008 @@ impl OptUdeps {
 					&mut used_build_dependencies,
 					&mut build_dependencies,
 				);
+				if !cmd_info.test {
+					insert_used(
+						cmd_info.pkg,
+						&usage,
+						&dependency_names.normal.by_extern_crate_name,
+						&dependency_names.normal.by_lib_true_snakecased_name,
+						&mut used_outside_tests,
+					);
+					analyzed_outside_tests.insert((cmd_info.pkg, &*cmd_info.target_description));
+				}
 			}
 		}
 
-		let mut outcome = Outcome::default();
+		config.shell().clear_progress()?;
 
-		for (dependencies, used_dependencies, kind) in &[
-			(&normal_dependencies, &used_normal_dev_dependencies, dependency::Kind::Normal),
-			(&dev_dependencies, &used_normal_dev_dependencies, dependency::Kind::Development),
-			(&build_dependencies, &used_build_dependencies, dependency::Kind::Build),
-		] {
-			for &(id, dependency) in *dependencies {
-				let ignore = ws_resolve
-					.pkg_set
-					.get_one(id)?
-					.manifest()
-					.custom_metadata()
-					.map::<CargoResult<_>, _>(|package_metadata| {
-						let PackageMetadata {
-							cargo_udeps: PackageMetadataCargoUdeps { ignore },
-						} = package_metadata
-							.clone()
-							.try_into()
-							.with_context(|_| "could not parse `package.metadata.cargo-udeps`")?;
-						Ok(ignore)
-					})
-					.transpose()?;
+		for doctest in &doctests {
+			let member = members.iter().copied().find(|&m| ws.package(m).root() == doctest.manifest_dir);
+			if let Some((id, dependency_names)) = member.and_then(|m| Some((m, dependency_names.get(&m)?))) {
+				let usage = Usage { extern_crate_names : doctest.used_externs.iter().cloned().collect(), ..Usage::default() };
+				// Doctests are given the dev-dependencies as well.
+				for names in &[&dependency_names.normal, &dependency_names.development] {
+					insert_used(id, &usage, &names.by_extern_crate_name, &names.by_lib_true_snakecased_name, &mut used_normal_dev_dependencies);
+				}
+			}
+		}
 
-				if !used_dependencies.contains(&(id, dependency)) {
-					let outcome = outcome
-						.unused_deps
-						.entry(id)
-						.or_insert(OutcomeUnusedDeps::new(packages[&id].manifest_path())?)
-						.unused_deps_mut(*kind);
+		let mut build_scripts = cmd_infos
+			.iter()
+			.filter(|c| c.custom_build)
+			.filter_map(|c| Some((c.pkg, c.src_path.clone()?)))
+			.collect::<Vec<_>>();
 
-					if ignore.map_or(false, |ignore| ignore.contains(*kind, dependency)) {
-						config.shell().info(format_args!("Ignoring `{}` ({:?})", dependency, kind))?;
+		if scans_sources {
+			for (&id, dependency_names) in &dependency_names {
+				for target in ws.package(id).targets() {
+					let src_path = target.src_path();
+					if self.publish_check
+						&& (!(target.is_lib() || target.is_bin() || target.is_custom_build())
+							|| shipped_files.as_ref().map_or(false, |s| !s.contains(src_path)))
+					{
+						continue;
+					}
+					let mut usage = Usage::default();
+					for collector in collectors.iter().filter(|c| !c.compiles()) {
+						usage.extend(collector.collect_uncompiled(target)?);
+					}
+					for names in &[&dependency_names.normal, &dependency_names.development, &dependency_names.build] {
+						insert_locations(id, &usage, &names.by_extern_crate_name, &names.by_lib_true_snakecased_name, &mut locations);
+					}
+					let kinds :&[_] = if target.is_custom_build() {
+						build_scripts.push((id, src_path.to_owned()));
+						// Build scripts cannot use them, but they may be in the wrong table.
+						let names = &dependency_names.normal;
+						insert_used(id, &usage, &names.by_extern_crate_name, &names.by_lib_true_snakecased_name, &mut used_by_build_scripts);
+						&[dependency::Kind::Build]
 					} else {
-						outcome.insert(dependency);
+						// Unit tests in `lib` and `bin` targets may use dev-dependencies.
+						&[dependency::Kind::Normal, dependency::Kind::Development]
+					};
+					for &kind in kinds {
+						let (used_dependencies, dependencies) = match kind {
+							dependency::Kind::Normal => (&mut used_normal_dev_dependencies, &mut normal_dependencies),
+							dependency::Kind::Development => (&mut used_normal_dev_dependencies, &mut dev_dependencies),
+							dependency::Kind::Build => (&mut used_build_dependencies, &mut build_dependencies),
+						};
+						let names = &dependency_names[kind];
+						dependencies.extend(names.by_extern_crate_name.values().map(|&name| (id, name)));
+						insert_used(id, &usage, &names.by_extern_crate_name, &names.by_lib_true_snakecased_name, used_dependencies);
+						// The sources of `lib`s and `bin`s include their unit tests.
+						if kind == dependency::Kind::Normal && (target.is_lib() || target.is_bin()) {
+							insert_used(id, &usage, &names.by_extern_crate_name, &names.by_lib_true_snakecased_name, &mut used_outside_tests);
+						}
 					}
 				}
 			}
 		}
 
-		outcome.success = outcome
-			.unused_deps
-			.values()
-			.all(|OutcomeUnusedDeps { normal, development, build, .. }| {
-				normal.is_empty() && development.is_empty() && build.is_empty()
-			});
-
-		if !outcome.success {
-			let mut note = "".to_owned();
-
-			if !self.all_targets {
-				note += "Note: These dependencies might be used by other targets.\n";
-
-				if !self.lib
-					&& !self.bins
-					&& !self.examples
-					&& !self.tests
-					&& !self.benches
-					&& self.bin.is_empty()
-					&& self.example.is_empty()
-					&& self.test.is_empty()
-					&& self.bench.is_empty()
-				{
-					note += "      To find dependencies that are not used by any target, enable `--all-targets`.\n";
+		// `links` dependencies may be used only through the `DEP_<links>_<key>` variables which
+		// Cargo sets for the build script of their immediate dependents.
+		for (id, src_path) in &build_scripts {
+			if let Some(dependency_names) = dependency_names.get(id) {
+				let build_rs = fs::read_to_string(src_path)?;
+				for (links, names) in &dependency_names.normal.by_links {
+					if build_rs.contains(&format!("DEP_{}_", links)) {
+						for &name in names {
+							used_normal_dev_dependencies.insert((*id, name));
+							used_outside_tests.insert((*id, name));
+						}
+					}
 				}
 			}
+		}
 
-			if dependency_names.values().any(DependencyNames::has_non_lib) {
-				note += "Note: Some dependencies are non-library packages.\n";
-				note += "      `cargo-udeps` regards them as unused.\n";
+		let mut outcome = Outcome {
+			incomplete : timed_out,
+			non_lib : dependency_names.values().any(DependencyNames::has_non_lib),
+			locations,
+			..Outcome::default()
+		};
+		if timed_out {
+			let message = "the time budget is exhausted. Only the finished units are analyzed".to_owned();
+			outcome.add_warning(OutcomeWarning { code : "incomplete".to_owned(), package : None, message });
+		}
+		let mut ids = dependency_names.keys().copied().collect::<Vec<_>>();
+		ids.sort();
+		for id in ids {
+			let names = &dependency_names[&id];
+			for &kind in &[dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build] {
+				let mut non_lib = names[kind].non_lib.iter().collect::<Vec<_>>();
+				non_lib.sort();
+				for name in non_lib {
+					let message = format!(
+						"`{}` depends on `{}` ({:?}), which has no library. It is regarded as unused",
+						ws.package(id).name(),
+						name,
+						kind,
+					);
+					outcome.add_warning(OutcomeWarning { code : "non-lib".to_owned(), package : Some(id), message });
+				}
 			}
+		}
+		for warning in ambiguous_lib_names {
+			outcome.add_warning(warning);
+		}
 
-			note += "Note: They might be false-positive.\n";
-			note += "      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.\n";
-			note += "      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.\n";
+		let analyzed_targets = cmd_infos
+			.iter()
+			.map(|c| (c.pkg, &*c.target_description))
+			.collect::<HashSet<_>>();
+		for &id in &members {
+			let package = ws.package(id);
+			let enabled_features = ws.features(id);
+			let mut coverage = OutcomeCoverage::default();
+			for target in package.targets() {
+				let description = target.description_named();
+				if scans_sources || analyzed_targets.contains(&(id, &*description)) {
+					continue;
+				}
+				let missing_features = target
+					.required_features()
+					.into_iter()
+					.flatten()
+					.filter(|f| !f.contains('/') && !enabled_features.contains(f))
+					.cloned()
+					.collect::<Vec<_>>();
+				let reason = if !missing_features.is_empty() {
+					SkipReason::RequiredFeatures { missing : missing_features }
+				} else if timed_out {
+					SkipReason::Unfinished
+				} else {
+					SkipReason::NotSelected
+				};
+				coverage.skipped_targets.push(OutcomeSkippedTarget { target : description, reason });
+			}
+			// Dependencies of other platforms are never passed to rustc.
+			for dep in package.dependencies() {
+				if let Some(platform) = dep.platform() {
+					let declared = match dep.kind() {
+						dependency::Kind::Normal => &normal_dependencies,
+						dependency::Kind::Development => &dev_dependencies,
+						dependency::Kind::Build => &build_dependencies,
+					};
+					if !declared.contains(&(id, dep.name_in_toml())) {
+						coverage
+							.unevaluated_platforms
+							.entry(platform.to_string())
+							.or_insert_with(BTreeSet::new)
+							.insert(dep.name_in_toml());
+					}
+				}
+			}
+			// `dep?/feature` enables `feature` only if `dep` is enabled by something else.
+			for dep in package.dependencies().iter().filter(|d| d.is_optional()) {
+				let name = dep.name_in_toml();
+				if features::is_enabled(ws, package, dep) {
+					continue;
+				}
+				let weak = enabled_features
+					.iter()
+					.filter(|f| {
+						package.features().get(*f).into_iter().flatten().any(|v| match FeatureValue::parse(v) {
+							FeatureValue::DepFeature { dep, weak, .. } => weak && dep == &*name,
+							_ => false,
+						})
+					})
+					.cloned()
+					.collect::<BTreeSet<_>>();
+				if !weak.is_empty() {
+					coverage.weakly_enabled.insert(name, weak);
+				}
+			}
+			if !coverage.is_empty() {
+				outcome.coverage.insert(id, coverage);
+			}
+		}
 
-			outcome.note = Some(note);
+		// Dependencies out of the scope of `--only` are neither used nor unused.
+		for (dependencies, kind) in &mut [
+			(&mut normal_dependencies, dependency::Kind::Normal),
+			(&mut dev_dependencies, dependency::Kind::Development),
+			(&mut build_dependencies, dependency::Kind::Build),
+		] {
+			let mut out_of_scope = vec![];
+			for &(id, dependency) in &**dependencies {
+				if !self.in_scope(ws.package(id), *kind, dependency)? {
+					out_of_scope.push((id, dependency));
+				}
+			}
+			for key in out_of_scope {
+				dependencies.remove(&key);
+			}
 		}
 
-		outcome.print(self.output, stdout)?;
-		Ok(if outcome.success { 0 } else { 1 })
-	}
-}
+		for &(id, dependency) in &normal_dependencies {
+			if used_by_build_scripts.contains(&(id, dependency))
+				&& !used_normal_dev_dependencies.contains(&(id, dependency))
+				&& !self.ignored(ws.package(id), &package_ignores[&id], dependency::Kind::Normal, dependency)
+			{
+				outcome.misplaced.push(OutcomeMisplaced {
+					package : id,
+					kind : dependency::Kind::Normal,
+					dependency,
+					suggested : dependency::Kind::Build,
+				});
+			}
+		}
+		// Only if every `lib` and `bin` is analyzed apart from its unit tests.
+		for &(id, dependency) in &normal_dependencies {
+			let package = ws.package(id);
+			let analyzed = scans_sources
+				|| package
+					.targets()
+					.iter()
+					.filter(|t| t.is_lib() || t.is_bin())
+					.all(|t| analyzed_outside_tests.contains(&(id, &*t.description_named())));
+			// `[dev-dependencies]` cannot be optional.
+			let optional = package
+				.dependencies()
+				.iter()
+				.any(|d| d.kind() == dependency::Kind::Normal && d.name_in_toml() == dependency && d.is_optional());
+			let ignored = self.ignored(package, &package_ignores[&id], dependency::Kind::Normal, dependency);
+			if analyzed
+				&& !optional
+				&& !ignored
+				&& used_normal_dev_dependencies.contains(&(id, dependency))
+				&& !used_outside_tests.contains(&(id, dependency))
+			{
+				outcome.misplaced.push(OutcomeMisplaced {
+					package : id,
+					kind : dependency::Kind::Normal,
+					dependency,
+					suggested : dependency::Kind::Development,
+				});
+			}
+		}
+		outcome.misplaced.sort_by_key(|m| (m.package, m.kind, m.dependency));
 
-struct ExecData {
-	cargo_exe :OsString,
-	supports_color :bool,
-	relevant_cmd_infos :Vec<CmdInfo>,
-}
+		// Dependencies referred only behind `#[cfg(unix)]` and so on.
+		for (&id, dependency_names) in &dependency_names {
+			let package = ws.package(id);
+			let mut candidates = vec![];
+			for &(kind, used_dependencies) in &[
+				(dependency::Kind::Normal, &used_normal_dev_dependencies),
+				(dependency::Kind::Development, &used_normal_dev_dependencies),
+				(dependency::Kind::Build, &used_build_dependencies),
+			] {
+				for (extern_crate_name, &dependency) in &dependency_names[kind].by_extern_crate_name {
+					let declared_for_every_platform = package
+						.dependencies()
+						.iter()
+						.filter(|d| d.kind() == kind && d.name_in_toml() == dependency)
+						.all(|d| d.platform().is_none());
+					if declared_for_every_platform
+						&& used_dependencies.contains(&(id, dependency))
+						&& !self.ignored(package, &package_ignores[&id], kind, dependency)
+						&& self.in_scope(package, kind, dependency)?
+					{
+						candidates.push((kind, extern_crate_name, dependency));
+					}
+				}
+			}
+			if candidates.is_empty() {
+				continue;
+			}
 
-impl ExecData {
-	fn new(config :&Config) -> CargoResult<Self> {
-		// `$CARGO` should be present when `cargo-udeps` is executed as `cargo udeps ..` or `cargo run -- udeps ..`.
-		let cargo_exe = env::var_os(cargo::CARGO_ENV)
-			.map(Ok::<_, failure::Error>)
-			.unwrap_or_else(|| {
-				// Unless otherwise specified, `$CARGO` is set to `config.cargo_exe()` for compilation commands which points at `cargo-udeps`.
-				let cargo_exe = config.cargo_exe()?;
-				config.shell().warn(format!(
-					"Couldn't find $CARGO environment variable. Setting it to {}",
-					cargo_exe.display(),
-				))?;
-				config.shell().warn(
-					"`cargo-udeps` currently does not support basic Cargo commands such as `build`",
+			let (mut referred, mut referred_by_build_scripts) = (HashMap::new(), HashMap::new());
+			for target in package.targets() {
+				let referred = if target.is_custom_build() { &mut referred_by_build_scripts } else { &mut referred };
+				// This is only advice. A target `syn` cannot read is not worth failing the run.
+				let names = match source::referred_names_by_platform(target.src_path()) {
+					Ok(names) => names,
+					Err(_) => continue,
+				};
+				for (name, platforms) in names {
+					referred.entry(name).or_insert_with(BTreeSet::new).extend(platforms);
+				}
+			}
+			for (kind, extern_crate_name, dependency) in candidates {
+				let referred = if kind == dependency::Kind::Build { &referred_by_build_scripts } else { &referred };
+				let platforms = referred.get(extern_crate_name).into_iter().flatten().collect::<Vec<_>>();
+				if let [Some(cfg)] = &*platforms {
+					outcome.target_specific.push(OutcomeTargetSpecific { package : id, kind, dependency, cfg : cfg.clone() });
+				}
+			}
+		}
+		outcome.target_specific.sort_by_key(|t| (t.package, t.kind, t.dependency));
+
+		for (dependencies, used_dependencies, kind) in &[
+			(&normal_dependencies, &used_normal_dev_dependencies, dependency::Kind::Normal),
+			(&dev_dependencies, &used_normal_dev_dependencies, dependency::Kind::Development),
+			(&build_dependencies, &used_build_dependencies, dependency::Kind::Build),
+		] {
+			if self.publish_check && *kind == dependency::Kind::Development {
+				continue;
+			}
+			for &(id, dependency) in *dependencies {
+				// Misplaced ones are reported as such instead.
+				let used = used_dependencies.contains(&(id, dependency))
+					|| outcome.misplaced.iter().any(|m| (m.package, m.kind, m.dependency) == (id, *kind, dependency));
+				let ignored = self.ignored(ws.package(id), &package_ignores[&id], *kind, dependency);
+				if used && ignored {
+					outcome.unnecessary_ignores.push(OutcomeUnnecessaryIgnore { package : id, kind : *kind, dependency });
+				}
+				if !used {
+					let outcome = outcome
+						.unused_deps
+						.entry(id)
+						.or_insert(OutcomeUnusedDeps::new(ws.package(id).manifest_path())?);
+
+					if ignored {
+						config.shell().info(format_args!("Ignoring `{}` ({:?})", dependency, kind))?;
+						outcome.ignored.insert(dependency);
+					} else {
+						outcome.unused_deps_mut(*kind).insert(dependency);
+					}
+				}
+
+				let to = ws.deps(id).find(|&to| {
+					ws.package(id)
+						.dependencies()
+						.iter()
+						.any(|d| d.kind() == *kind && d.name_in_toml() == dependency && d.matches(ws.package(to)))
+				});
+				outcome.edges.push(OutcomeEdge {
+					from : id,
+					to,
+					name : dependency,
+					kind : *kind,
+					unused : !used && !ignored,
+				});
+			}
+		}
+
+		config.trace().end(analysis_span);
+		Ok(outcome)
+	}
+
+	fn report(&self, config :&Config, outcome :&Outcome, stdout :impl Write) -> Fallible<()> {
+		let span = config.trace().start("report");
+		match &self.report_path {
+			Some(path) => {
+				let path = config.cwd().join(path);
+				let file = fs::File::create(&path)
+					.with_context(|_| format!("could not create `{}`", path.display()))?;
+				self.print(config, outcome, io::BufWriter::new(file))?;
+				if self.open {
+					open_report(&path, &mut config.shell())?;
+				}
+			}
+			None => self.print(config, outcome, stdout)?,
+		}
+		config.trace().end(span);
+		Ok(())
+	}
+
+	/// How the human output is laid out. Colored only on stdout.
+	fn layout(&self, config :&Config) -> Layout {
+		Layout {
+			group_by : self.group_by,
+			sort : self.sort,
+			aggregate : self.aggregate,
+			summary : self.summary,
+			charset : self.charset.resolve(),
+			color : self.report_path.is_none() && config.shell().out_supports_color(),
+		}
+	}
+
+	fn print(&self, config :&Config, outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+		if self.count {
+			writeln!(stdout, "{}", outcome.unused_deps.values().map(|deps| deps.iter().count()).sum::<usize>())?;
+			stdout.flush()
+		} else {
+			outcome.print(self.output(), config.cwd(), stdout)
+		}
+	}
+
+	fn fix(&self, config :&Config, ws :&Workspace, outcome :&Outcome) -> Fallible<()> {
+		if outcome.incomplete {
+			config.shell().warn("not fixing anything since the results are incomplete")?;
+			return Ok(());
+		}
+		if !self.all_targets {
+			config.shell().warn("`--fix` without `--all-targets` may remove dependencies used by other targets")?;
+		}
+		for unused in outcome.unused_deps.values().filter(|d| !d.is_empty()) {
+			let removed = fix::remove_unused_deps(unused)?;
+			if !removed.is_empty() {
+				config.shell().status(
+					"Fixed",
+					format!("`{}`: removed {}", unused.manifest_path, removed.join(", ")),
 				)?;
-				Ok(cargo_exe.into())
-			})?;
-		Ok(Self {
-			cargo_exe,
-			supports_color :config.shell().supports_color(),
-			relevant_cmd_infos : Vec::new(),
-		})
+			}
+		}
+
+		// `[workspace.dependencies]` entries are left alone while some member still inherits them.
+		let removed_names = outcome
+			.unused_deps
+			.values()
+			.flat_map(|u| u.iter().map(|(_, name)| name))
+			.collect::<BTreeSet<_>>();
+		let root_manifest_path = ws.root().join("Cargo.toml");
+		let member_manifest_paths = ws.members().map(|m| m.manifest_path().to_owned()).collect::<Vec<_>>();
+		let removed = fix::remove_unreferenced_workspace_deps(&root_manifest_path, &member_manifest_paths, &removed_names)?;
+		if !removed.is_empty() {
+			config.shell().status(
+				"Fixed",
+				format!("`{}`: removed {}", root_manifest_path.display(), removed.join(", ")),
+			)?;
+		}
+		Ok(())
 	}
-}
 
-struct Exec {
-	data :Arc<Mutex<ExecData>>,
-}
+	/// The feature sets of `--features-matrix`, `--each-feature` or `--feature-powerset`, or
+	/// `None` if none of them is given.
+	fn features_matrix(&self, ws :&Workspace, members :&[PackageId]) -> Fallible<Option<Vec<String>>> {
+		if self.each_feature || self.feature_powerset {
+			// The members are checked together, so the `i`-th set of every member is enabled in the
+			// `i`-th run.
+			let sets = members
+				.iter()
+				.map(|&id| {
+					let package = ws.package(id);
+					let features = package.optional_features().collect::<Vec<_>>();
+					let sets = if self.each_feature {
+						iter::once(vec![]).chain(features.iter().map(|&f| vec![f])).collect::<Vec<_>>()
+					} else {
+						let depth = self.depth.unwrap_or(features.len());
+						let num_sets = num_combinations(features.len(), depth);
+						if num_sets > MAX_FEATURE_SETS {
+							return Err(failure::format_err!(
+								"`--feature-powerset` would check `{}` with {} feature sets (at most {}). Limit it with `--depth`",
+								package.name(),
+								if num_sets == usize::MAX { "too many".to_owned() } else { num_sets.to_string() },
+								MAX_FEATURE_SETS,
+							));
+						}
+						combinations(&features, depth)
+					};
+					Ok((package.name(), sets))
+				})
+				.collect::<Fallible<Vec<_>>>()?;
+			let num_runs = sets.iter().map(|(_, sets)| sets.len()).max().unwrap_or(1);
+			let matrix = (0..num_runs)
+				.map(|i| {
+					sets.iter()
+						.filter_map(|(name, sets)| Some((name, sets.get(i)?)))
+						.flat_map(|(name, set)| {
+							set.iter().map(move |f| if members.len() > 1 { format!("{}/{}", name, f) } else { f.to_string() })
+						})
+						.collect::<Vec<_>>()
+						.join(",")
+				})
+				.collect();
+			return Ok(Some(matrix));
+		}
+		let matrix = match &self.features_matrix {
+			None => return Ok(None),
+			Some(matrix) if !matrix.is_empty() => matrix.clone(),
+			Some(_) => {
+				let WorkspaceMetadataCargoUdeps { features_matrix, .. } = WorkspaceMetadataCargoUdeps::load(ws)?;
+				if features_matrix.is_empty() {
+					return Err(failure::err_msg(
+						"`--features-matrix` requires feature sets, or `features-matrix` in `udeps.toml` or `[workspace.metadata.cargo-udeps]`",
+					));
+				}
+				features_matrix
+			}
+		};
+		Ok(Some(matrix))
+	}
 
-impl Executor for Exec {
-	fn exec(&self, mut cmd :ProcessBuilder, id :PackageId, target :&Target,
-			mode :CompileMode, on_stdout_line :&mut dyn FnMut(&str) -> CargoResult<()>,
-			on_stderr_line :&mut dyn FnMut(&str) -> CargoResult<()>) -> CargoResult<()> {
+	/// The arguments of `cargo metadata`.
+	fn metadata_args(&self, config :&Config) -> Fallible<Vec<OsString>> {
+		let mut args = self.cargo_args(config);
+		args.extend(self.feature_args());
+		// The dependencies of the other platforms are never given to `rustc`, so they are not
+		// checked.
+		let platform = match &*self.target {
+			[] => Some(host_triple(config)?.into()),
+			[target] => Some(target_spec(config, target)),
+			// Each of them is loaded again.
+			_ => None,
+		};
+		if let Some(platform) = platform {
+			args.push("--filter-platform".into());
+			args.push(platform);
+		}
+		Ok(args)
+	}
 
-		let cmd_info = cmd_info(id, target.is_custom_build(), &cmd).unwrap_or_else(|e| {
-			panic!("Couldn't obtain crate info {:?}: {:?}", id, e);
-		});
-		let is_path = id.source_id().is_path();
+	/// The arguments of every `cargo` command: the manifest, the lockfile, the network, the
+	/// configuration and the unstable flags.
+	fn cargo_args(&self, config :&Config) -> Vec<OsString> {
+		let mut args = Vec::<OsString>::new();
+		if let Some(manifest_path) = &self.manifest_path {
+			args.push("--manifest-path".into());
+			args.push(config.cwd().join(manifest_path).into());
+		}
+		for (flag, enabled) in &[("--frozen", self.frozen), ("--locked", self.locked), ("--offline", self.offline)] {
+			if *enabled {
+				args.push(flag.into());
+			}
+		}
+		// Cargo writes to a pipe, which it does not color by itself.
+		args.push("--color".into());
+		args.push(if config.shell().supports_color() { "always" } else { "never" }.into());
+		for config in &self.config {
+			args.push("--config".into());
+			args.push(config.into());
+		}
+		for flag in &self.unstable_flags {
+			args.push("-Z".into());
+			args.push(flag.into());
+		}
+		// Recent nightlies accept custom target specs only with the flag, and older ones do not
+		// know it.
+		if self.target.iter().any(|t| t.ends_with(".json"))
+			&& !self.unstable_flags.iter().any(|f| f == "json-target-spec")
+			&& has_unstable_flag(config, "json-target-spec")
 		{
-			// TODO unwrap used
-			let mut bt = self.data.lock().unwrap();
-
-			// If the crate is not a library crate,
-			// we are not interested in its information.
-			if is_path {
-				bt.relevant_cmd_infos.push(cmd_info.clone());
-			}
-			if (!cmd_info.cap_lints_allow) != is_path {
-				on_stderr_line(&format!(
-					"{} (!cap_lints_allow)={} differs from is_path={} for id={}",
-					if bt.supports_color {
-						Colour::Yellow.bold().paint("warning:").to_string()
-					} else {
-						"warning:".to_owned()
-					},
-					!cmd_info.cap_lints_allow,
-					is_path,
-					id,
-				))?;
+			args.push("-Z".into());
+			args.push("json-target-spec".into());
+		}
+		args
+	}
+
+	fn feature_args(&self) -> Vec<OsString> {
+		let mut args = Vec::<OsString>::new();
+		for features in &self.features {
+			args.push("--features".into());
+			args.push(features.into());
+		}
+		if self.all_features {
+			args.push("--all-features".into());
+		}
+		if self.no_default_features {
+			args.push("--no-default-features".into());
+		}
+		args
+	}
+
+	/// The arguments selecting the profile and the output directory, shared by `cargo check` and
+	/// `cargo clean`.
+	fn profile_args(&self, config :&Config) -> Vec<OsString> {
+		let mut args = Vec::<OsString>::new();
+		if self.release {
+			args.push("--release".into());
+		}
+		if let Some(profile) = &self.profile {
+			args.push("--profile".into());
+			args.push(profile.into());
+		}
+		for target in &self.target {
+			args.push("--target".into());
+			args.push(target_spec(config, target));
+		}
+		if let Some(target_dir) = &self.target_dir {
+			args.push("--target-dir".into());
+			args.push(config.cwd().join(target_dir).into());
+		}
+		args
+	}
+
+	/// The targets to check, as `--lib`, `--bins` and so on.
+	fn target_args(&self, members :&[&Package]) -> Vec<OsString> {
+		let mut args = Vec::<OsString>::new();
+		if self.publish_check {
+			if members.iter().any(|m| m.targets().iter().any(Target::is_lib)) {
+				args.push("--lib".into());
+			}
+			args.push("--bins".into());
+			return args;
+		}
+		for (flag, enabled) in &[
+			("--lib", self.lib),
+			("--bins", self.bins),
+			("--examples", self.examples),
+			("--tests", self.tests),
+			("--benches", self.benches),
+			("--all-targets", self.all_targets),
+		] {
+			if *enabled {
+				args.push(flag.into());
 			}
-			cmd.env(cargo::CARGO_ENV, &bt.cargo_exe);
 		}
-		if is_path {
-			std::env::set_var("RUST_SAVE_ANALYSIS_CONFIG",
-				r#"{ "reachable_only": true, "full_docs": false, "pub_only": false, "distro_crate": false, "signatures": false, "borrow_data": false }"#);
-			cmd.arg("-Z").arg("save-analysis");
+		for (flag, names) in &[("--bin", &self.bin), ("--example", &self.example), ("--test", &self.test), ("--bench", &self.bench)] {
+			for name in *names {
+				args.push(flag.into());
+				args.push(name.into());
+			}
 		}
-		DefaultExecutor.exec(cmd, id, target, mode, on_stdout_line, on_stderr_line)?;
-		Ok(())
+		args
+	}
+
+	/// The members selected with `--package`, `--workspace` and `--exclude`, in the same way as
+	/// Cargo. `--exclude-path` is applied with `--exclude`.
+	fn selected_members<'a>(&self, config :&Config, ws :&'a Workspace) -> Fallible<Vec<&'a Package>> {
+		if !self.package.is_empty() {
+			let mut members = vec![];
+			for spec in &self.package {
+				let mut matched = vec![];
+				for member in ws.members() {
+					if matches_spec(ws, member, spec)? {
+						matched.push(member);
+					}
+				}
+				if matched.is_empty() {
+					return Err(failure::format_err!(
+						"package ID specification `{}` did not match any packages",
+						spec,
+					));
+				}
+				members.extend(matched);
+			}
+			members.sort_by_key(|m| m.package_id());
+			members.dedup_by_key(|m| m.package_id());
+			return Ok(members);
+		}
+		let members = if self.workspace || self.all {
+			ws.members().collect::<Vec<_>>()
+		} else if let Some(current) = ws.current() {
+			return Ok(vec![current]);
+		} else {
+			// A virtual manifest.
+			ws.default_members().collect()
+		};
+		let exclude_paths = self.exclude_path.iter().map(|p| config.cwd().join(p)).collect::<Vec<_>>();
+		let mut selected = vec![];
+		for member in members {
+			let mut excluded = exclude_paths.iter().any(|p| member.root().starts_with(p));
+			for spec in &self.exclude {
+				excluded |= matches_spec(ws, member, spec)?;
+			}
+			if !excluded {
+				selected.push(member);
+			}
+		}
+		Ok(selected)
+	}
+
+	/// The files `cargo package` would ship for `member`.
+	fn list_files(&self, config :&Config, member :&Package) -> Fallible<Vec<PathBuf>> {
+		let mut cargo = config.cargo("package");
+		cargo.arg("--list").arg("--manifest-path").arg(member.manifest_path());
+		for (flag, enabled) in &[("--frozen", self.frozen), ("--locked", self.locked), ("--offline", self.offline)] {
+			if *enabled {
+				cargo.arg(flag);
+			}
+		}
+		let output = cargo.exec_with_output()?;
+		let files = String::from_utf8(output.stdout).with_context(|_| "`cargo package --list` printed non-utf8 paths")?;
+		Ok(files.lines().map(|f| member.root().join(f)).collect())
 	}
-	fn force_rebuild(&self, unit :&Unit) -> bool {
-		let source_id = (*unit).pkg.summary().source_id();
-		source_id.is_path()
+
+	/// Runs `cargo check` with `cargo-udeps` as `RUSTC_WORKSPACE_WRAPPER`, and returns the `rustc`
+	/// invocations for `members`, and whether `--max-duration` stopped it.
+	fn compile(
+		&self,
+		config :&Config,
+		ws :&Workspace,
+		members :&[&Package],
+		collectors :&[Box<dyn UsageCollector>],
+	) -> Fallible<(Vec<CmdInfo>, bool)> {
+		let mut wrappers = collectors.iter().filter_map(|c| c.rustc_wrapper()).collect::<Vec<_>>();
+		// e.g. `clippy-driver`, which would be replaced by `cargo-udeps` otherwise.
+		wrappers.extend(env::var_os("RUSTC_WORKSPACE_WRAPPER").filter(|w| !w.is_empty()).map(PathBuf::from));
+		if wrappers.len() > 1 {
+			return Err(failure::format_err!("at most one program can replace `rustc`, got {:?}", wrappers));
+		}
+		let mut wrapper_config = WrapperConfig {
+			records_dir : PathBuf::new(),
+			args : collectors.iter().flat_map(|c| c.rustc_args()).collect(),
+			envs : collectors.iter().flat_map(|c| c.rustc_envs()).collect(),
+			wrapper : wrappers.into_iter().next(),
+			claimed_diagnostics : collectors.iter().flat_map(|c| c.claimed_diagnostics()).collect(),
+		};
+		let target_dir = self.target_dir(config, ws);
+		let config_hash = fnv1a(serde_json::to_string(&wrapper_config)?.as_bytes());
+		let records_dir = target_dir.join("udeps").join("records").join(format!("{:016x}", config_hash));
+		fs::create_dir_all(&records_dir)
+			.with_context(|_| format!("could not create `{}`", records_dir.display()))?;
+		wrapper_config.records_dir = records_dir.clone();
+
+		let span = config.trace().start("compile");
+		let started_at = otel::unix_nanos();
+		let deadline = self.max_duration.map(|d| Instant::now() + d);
+		let (mut artifacts, mut timed_out) = self.check(config, ws, members, &wrapper_config, deadline)?;
+		let mut records = Record::read_all(&records_dir)?;
+		tracing::debug!(records_dir = %records_dir.display(), "{} records", records.len());
+
+		// Units which are up to date may have been built without `cargo-udeps`, or with another
+		// configuration.
+		let unrecorded = artifacts
+			.iter()
+			.filter(|a| a.fresh && !records.iter().any(|r| r.produced(a)))
+			.map(|a| a.package_id)
+			.collect::<BTreeSet<_>>();
+		if !unrecorded.is_empty() && !timed_out {
+			let mut clean = config.cargo("clean");
+			clean.arg("--quiet").args(self.cargo_args(config)).args(self.profile_args(config));
+			for id in &unrecorded {
+				clean.arg("-p").arg(format!("{}@{}", id.name(), id.version()));
+			}
+			clean.exec_with_output()?;
+			let (rebuilt, rebuilt_timed_out) = self.check(config, ws, members, &wrapper_config, deadline)?;
+			artifacts = rebuilt;
+			timed_out = rebuilt_timed_out;
+			records = Record::read_all(&records_dir)?;
+		}
+
+		// The file names of the `.rlib`s and `.rmeta`s contain `-C extra-filename`, which differs
+		// between the versions of a crate.
+		let packages_by_file_stem = artifacts
+			.iter()
+			.flat_map(|a| a.filenames.iter().filter_map(move |f| Some((f.file_stem()?.to_owned(), a.package_id))))
+			.collect::<HashMap<_, _>>();
+		let mut cmd_infos = vec![];
+		for artifact in &artifacts {
+			let member = members.iter().find(|m| m.package_id() == artifact.package_id);
+			let record = records.iter().find(|r| r.produced(artifact));
+			if let (Some(member), Some(record)) = (member, record) {
+				let cmd_info = cmd_info(member, record, &packages_by_file_stem, &target_dir)?;
+				// The records of the fresh units are of the previous runs.
+				if record.started_at >= started_at {
+					config.trace().add(
+						format!("rustc {}", cmd_info.crate_name),
+						record.started_at,
+						record.finished_at,
+						vec![
+							("cargo.package", format!("{} {}", cmd_info.pkg.name(), cmd_info.pkg.version())),
+							("cargo.target", cmd_info.target_description.clone()),
+						],
+					);
+				}
+				cmd_infos.push(cmd_info);
+			}
+		}
+		config.trace().end(span);
+		Ok((cmd_infos, timed_out))
+	}
+
+	/// Compiles the doctests of the libraries in `members` with `cargo test --doc`, and returns the
+	/// crates they use.
+	fn check_doctests(
+		&self,
+		config :&Config,
+		ws :&Workspace,
+		members :&[&Package],
+		cmd_infos :&[CmdInfo],
+	) -> Fallible<Vec<DoctestRecord>> {
+		let libs = members
+			.iter()
+			.filter(|m| m.targets().iter().any(Target::is_lib))
+			.collect::<Vec<_>>();
+		if libs.is_empty() {
+			return Ok(vec![]);
+		}
+		// Cargo always runs `rustdoc --test` again, so the records are not reused.
+		let records_dir = self.target_dir(config, ws).join("udeps").join("doctests");
+		if records_dir.exists() {
+			fs::remove_dir_all(&records_dir)
+				.with_context(|_| format!("could not remove `{}`", records_dir.display()))?;
+		}
+		fs::create_dir_all(&records_dir)
+			.with_context(|_| format!("could not create `{}`", records_dir.display()))?;
+
+		let rustc = match cmd_infos.first() {
+			Some(cmd_info) => cmd_info.rustc().get_program().clone(),
+			None => env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()),
+		};
+		let rustdoc = env::var_os("RUSTDOC").unwrap_or_else(|| usage::rustdoc_next_to(&rustc));
+		let wrapper_config = RustdocWrapperConfig { rustdoc : rustdoc.into(), records_dir : records_dir.clone() };
+
+		let mut test = config.cargo("test");
+		test.arg("--doc")
+			.args(self.cargo_args(config))
+			.args(self.profile_args(config))
+			.args(self.feature_args())
+			.env("RUSTDOC", config.rustc_wrapper())
+			.env(wrapper::RUSTDOC_CONFIG_ENV, serde_json::to_string(&wrapper_config)?);
+		for lib in libs {
+			test.arg("-p").arg(format!("{}@{}", lib.name(), lib.package_id().version()));
+		}
+		if let Some(jobs) = &self.jobs {
+			test.arg("--jobs").arg(jobs);
+		}
+		if config.shell().verbosity() == Verbosity::Quiet {
+			test.arg("--quiet");
+		}
+		config.shell().verbose(|shell| shell.status("Running", &test))?;
+		// The output of the test harness is not for the report.
+		let output = test.exec_with_output()?;
+		config.shell().err().write_all(&output.stderr)?;
+		DoctestRecord::read_all(&records_dir)
+	}
+
+	/// `--target-dir`, or the one of the workspace.
+	fn target_dir(&self, config :&Config, ws :&Workspace) -> PathBuf {
+		match &self.target_dir {
+			Some(target_dir) => config.cwd().join(target_dir),
+			None => ws.target_dir().to_owned(),
+		}
+	}
+
+	/// Runs `cargo check` once, and returns the artifacts of `members`, and whether it was stopped
+	/// at `deadline`.
+	fn check(
+		&self,
+		config :&Config,
+		ws :&Workspace,
+		members :&[&Package],
+		wrapper_config :&WrapperConfig,
+		deadline :Option<Instant>,
+	) -> Fallible<(Vec<wrapper::Artifact>, bool)> {
+		let mut check = config.cargo("check");
+		check
+			.args(self.cargo_args(config))
+			.args(self.profile_args(config))
+			.args(self.feature_args())
+			.args(self.target_args(members))
+			.env("RUSTC_WORKSPACE_WRAPPER", config.rustc_wrapper())
+			// e.g. `sccache` would skip `rustc`, and take the workspace wrapper for a compiler.
+			.env("RUSTC_WRAPPER", "")
+			.env(wrapper::CONFIG_ENV, serde_json::to_string(wrapper_config)?);
+		for member in members {
+			check.arg("-p").arg(format!("{}@{}", member.name(), member.package_id().version()));
+		}
+		if let Some(jobs) = &self.jobs {
+			check.arg("--jobs").arg(jobs);
+		}
+		// The messages are parsed, and shown on stderr as well with `--message-format json`, so
+		// that they do not mix with the report.
+		let message_format = self.message_format.last().map_or("human", |f| &**f).to_lowercase();
+		check.arg("--message-format").arg(match &*message_format {
+			"json" => "json",
+			"short" => "json-diagnostic-short,json-render-diagnostics",
+			_ => "json-render-diagnostics",
+		});
+		match config.shell().verbosity() {
+			Verbosity::Verbose => drop(check.args(vec!["--verbose"; self.verbose as usize])),
+			Verbosity::Quiet => drop(check.arg("--quiet")),
+			Verbosity::Normal => {}
+		}
+		tracing::info!("running {}", check);
+		let mut child = check
+			.build_command()
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.with_context(|_| format!("could not execute {}", check))?;
+
+		let (tx, rx) = mpsc::channel();
+		let streams :[(Box<dyn io::Read + Send>, bool); 2] = [
+			(Box::new(child.stdout.take().expect("should be piped")), true),
+			(Box::new(child.stderr.take().expect("should be piped")), false),
+		];
+		for (stream, is_stdout) in streams {
+			let tx = tx.clone();
+			thread::spawn(move || {
+				for line in BufReader::new(stream).lines() {
+					if tx.send((is_stdout, line)).is_err() {
+						break;
+					}
+				}
+			});
+		}
+		drop(tx);
+
+		let mut artifacts = vec![];
+		let mut timed_out = false;
+		// Kept for the error, e.g. `-Z` on stable.
+		let mut nightly_only = None;
+		loop {
+			match rx.recv_timeout(Duration::from_millis(100)) {
+				Ok((true, line)) => {
+					let line = line?;
+					if message_format == "json" {
+						writeln!(config.shell().err(), "{}", line)?;
+					}
+					let artifact = wrapper::Artifact::parse(&line, ws);
+					if let Some(artifact) = &artifact {
+						tracing::debug!(package = %artifact.package_id, fresh = artifact.fresh, "artifact");
+					}
+					artifacts.extend(artifact.filter(|a| members.iter().any(|m| m.package_id() == a.package_id)));
+				}
+				Ok((false, line)) => {
+					let line = line?;
+					if nightly_only.is_none() && is_nightly_only(&line) {
+						nightly_only = Some(line.clone());
+					}
+					writeln!(config.shell().err(), "{}", line)?;
+				}
+				Err(RecvTimeoutError::Timeout) => {}
+				Err(RecvTimeoutError::Disconnected) => break,
+			}
+			if !timed_out && deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+				// Cargo schedules nothing once killed. The units in flight may finish and be
+				// recorded after the records are read, so the outcome is incomplete, and only
+				// the records written in full are read.
+				child.kill()?;
+				timed_out = true;
+			}
+		}
+		// Reaped before the records are read.
+		let status = child.wait()?;
+		if !timed_out && !status.success() {
+			return Err(match nightly_only {
+				Some(line) => failure::format_err!("{} didn't exit successfully ({}): {}", check, status, line),
+				None => failure::format_err!("{} didn't exit successfully ({})", check, status),
+			});
+		}
+		Ok((artifacts, timed_out))
 	}
 }
 
+/// A `rustc` invocation for a target of a package on the local filesystem.
 #[derive(Clone, Debug)]
-struct CmdInfo {
+pub struct CmdInfo {
 	pkg :PackageId,
 	custom_build :bool,
+	src_path :Option<PathBuf>,
+	/// `Target::description_named`, e.g. `bin "foo"`.
+	target_description :String,
 	crate_name :String,
 	crate_type :String,
+	/// Compiled with `--test`, or a target which only tests, benches and examples build. They may
+	/// use the dev-dependencies.
+	test :bool,
 	extra_filename :String,
-	cap_lints_allow :bool,
 	out_dir :String,
 	externs :Vec<(String, String)>,
+	/// The packages of the `--extern`s, by the names.
+	extern_packages :HashMap<String, PackageId>,
+	/// `--remap-path-prefix FROM=TO`s as `(FROM, TO)`.
+	remap_path_prefixes :Vec<(PathBuf, PathBuf)>,
+	/// Diagnostics claimed by `UsageCollector::claimed_diagnostics`, one JSON per line.
+	stderr_lines :Vec<String>,
+	/// The invocation as Cargo prepared it.
+	rustc :ProcessBuilder,
 }
 
 impl CmdInfo {
+	pub fn package_id(&self) -> PackageId {
+		self.pkg
+	}
+	pub fn src_path(&self) -> Option<&Path> {
+		self.src_path.as_deref()
+	}
+	pub fn crate_name(&self) -> &str {
+		&self.crate_name
+	}
+	/// `-C extra-filename`, which Cargo appends to the names of the outputs.
+	pub fn extra_filename(&self) -> &str {
+		&self.extra_filename
+	}
+	pub fn out_dir(&self) -> &Path {
+		Path::new(&self.out_dir)
+	}
+	/// The `--extern`s as `(name, path)`.
+	pub fn externs(&self) -> &[(String, String)] {
+		&self.externs
+	}
+	/// The package of the `--extern` named `name`, found by the hash in the file name.
+	pub fn extern_package(&self, name :&str) -> Option<PackageId> {
+		self.extern_packages.get(name).copied()
+	}
+	/// Undoes `--remap-path-prefix` on a path written by `rustc`, e.g. in the save-analysis.
+	/// As in `rustc`, the last matching one applies.
+	pub fn unremap_path(&self, path :&Path) -> PathBuf {
+		self.remap_path_prefixes
+			.iter()
+			.rev()
+			.find_map(|(from, to)| Some(from.join(path.strip_prefix(to).ok()?)))
+			.unwrap_or_else(|| path.to_owned())
+	}
+	pub fn stderr_lines(&self) -> &[String] {
+		&self.stderr_lines
+	}
+	/// The `rustc` invocation without the changes requested by the `UsageCollector`s.
+	pub fn rustc(&self) -> &ProcessBuilder {
+		&self.rustc
+	}
 	fn get_save_analysis_path(&self) -> PathBuf {
 		let maybe_lib = if self.crate_type.ends_with("lib") ||
 				self.crate_type == "proc-macro" {
@@ -550,8 +2695,9 @@ impl CmdInfo {
 			.join("save-analysis")
 			.join(filename)
 	}
-	fn get_save_analysis(&self, shell :&mut Shell) -> CargoResult<CrateSaveAnalysis> {
+	fn get_save_analysis(&self, shell :&mut Shell) -> Fallible<CrateSaveAnalysis> {
 		let p = self.get_save_analysis_path();
+		tracing::debug!(path = %p.display(), "reading the save-analysis of `{}`", self.target_description);
 		shell.info(format_args!("Loading save analysis from {:?}", p))?;
 		let f = std::fs::read_to_string(p)?;
 		let res = serde_json::from_str(&f)?;
@@ -559,180 +2705,560 @@ impl CmdInfo {
 	}
 }
 
-fn cmd_info(id :PackageId, custom_build :bool, cmd :&ProcessBuilder) -> CargoResult<CmdInfo> {
-	let mut args_iter = cmd.get_args().iter();
+fn cmd_info(
+	member :&Package,
+	record :&Record,
+	packages_by_file_stem :&HashMap<OsString, PackageId>,
+	target_dir :&Path,
+) -> Fallible<CmdInfo> {
+	let Record { rustc, args, cwd, envs, claimed_diagnostics, .. } = record;
+	// `rustc` may have run elsewhere, e.g. in the container of `cross`, which mounts the
+	// workspace at another path.
+	let manifest_dir = member.manifest_path().parent().unwrap_or_else(|| Path::new(""));
+	let local_path = |arg :&str| {
+		let path = normalize_path(&cwd.join(arg));
+		match record.manifest_dir().and_then(|d| path.strip_prefix(d).ok()) {
+			Some(relative) => normalize_path(&manifest_dir.join(relative)),
+			None => path,
+		}
+	};
+	let target = member
+		.targets()
+		.iter()
+		.find(|t| args.iter().any(|a| local_path(a) == normalize_path(t.src_path())))
+		.ok_or_else(|| failure::format_err!("could not find the target of `{}` compiled with {:?}", member.package_id(), args))?;
+
+	let mut args_iter = args.iter();
 	let mut crate_name = None;
 	let mut crate_type = None;
 	let mut extra_filename = None;
-	let mut cap_lints_allow = false;
 	let mut out_dir = None;
+	let mut remap_path_prefixes = vec![];
 	let mut externs = Vec::<(String, String)>::new();
 	while let Some(v) = args_iter.next() {
 		if v == "--extern" {
-			let arg = args_iter.next()
-				.map(|a| a.to_str().expect("non-utf8 paths not supported atm"))
-				.map(|a| {
-					let mut splitter = a.split("=");
-					if let (Some(n), Some(p)) = (splitter.next(), splitter.next()) {
-						(n.to_owned(), p.to_owned())
-					} else {
-						panic!("invalid format for extern arg: {}", a);
-					}
-				});
-			if let Some(e) = arg {
-				externs.push(e);
+			// `[modifiers:]name[=path]`. The path may contain `=` and `:` (e.g. `C:\`).
+			if let Some(a) = args_iter.next() {
+				let mut splitter = a.splitn(2, '=');
+				let n = splitter.next().unwrap_or_default();
+				let n = n.rsplit(':').next().unwrap_or(n);
+				externs.push((n.to_owned(), splitter.next().unwrap_or_default().to_owned()));
 			}
 		} else if v == "--crate-name" {
-			if let Some(name) = args_iter.next() {
-				crate_name = Some(name.to_str()
-					.expect("non-utf8 crate names not supported")
-					.to_owned());
-			}
+			crate_name = args_iter.next().cloned();
 		} else if v == "--crate-type" {
-			if let Some(ty) = args_iter.next() {
-				crate_type = Some(ty.to_str()
-					.expect("non-utf8 crate names not supported")
-					.to_owned());
-			}
-		} else if v == "--cap-lints" {
-			if let Some(c) = args_iter.next() {
-				if c == "allow" {
-					cap_lints_allow = true;
-				}
-			}
+			crate_type = args_iter.next().cloned();
 		} else if v == "--out-dir" {
-			if let Some(d) = args_iter.next() {
-				out_dir = Some(d.to_str()
-					.expect("non-utf8 crate names not supported")
-					.to_owned());
+			out_dir = args_iter.next().map(|d| relocate(&cwd.join(d), target_dir).to_string_lossy().into_owned());
+		} else if v == "--remap-path-prefix" || v.starts_with("--remap-path-prefix=") {
+			let remap = match v.strip_prefix("--remap-path-prefix=") {
+				Some(remap) => Some(remap),
+				None => args_iter.next().map(|a| &**a),
+			};
+			// `FROM=TO`, where `FROM` may contain `=`.
+			if let Some((from, to)) = remap.and_then(|r| r.rsplit_once('=')) {
+				remap_path_prefixes.push((PathBuf::from(from), PathBuf::from(to)));
 			}
 		} else if v == "-C" {
 			if let Some(arg) = args_iter.next() {
-				let arg = arg.to_str().expect("non-utf8 args not supported atm");
-				let mut splitter = arg.split("=");
-				if let (Some(n), Some(p)) = (splitter.next(), splitter.next()) {
-					if n == "extra-filename" {
-						extra_filename = Some(p.to_owned());
-					}
+				if let Some(p) = arg.strip_prefix("extra-filename=") {
+					extra_filename = Some(p.to_owned());
 				}
 			}
 		}
 	}
-	let pkg = id;
 	let crate_name = crate_name.ok_or_else(|| failure::err_msg("crate name needed"))?;
 	let crate_type = crate_type.unwrap_or("bin".to_owned());
 	let extra_filename = extra_filename.ok_or_else(|| failure::err_msg("extra-filename needed"))?;
 	let out_dir = out_dir.ok_or_else(|| failure::err_msg("outdir needed"))?;
+	let extern_packages :HashMap<_, _> = externs
+		.iter()
+		.filter_map(|(name, path)| {
+			let package = packages_by_file_stem.get(Path::new(path).file_stem()?)?;
+			Some((name.clone(), *package))
+		})
+		.collect();
+
+	let mut cmd = ProcessBuilder::new(rustc);
+	cmd.args(args).cwd(cwd);
+	for (key, val) in envs {
+		cmd.env(key, val);
+	}
+
+	for (name, path) in &externs {
+		let package = extern_packages.get(name).map(ToString::to_string);
+		tracing::debug!(crate_name = %crate_name, %name, %path, ?package, "`--extern` of `{}`", target.description_named());
+	}
 
 	Ok(CmdInfo {
-		pkg,
-		custom_build,
+		pkg : member.package_id(),
+		custom_build : target.is_custom_build(),
+		test : args.iter().any(|a| a == "--test") || !(target.is_lib() || target.is_bin() || target.is_custom_build()),
+		src_path : Some(target.src_path().to_owned()),
+		target_description : target.description_named(),
 		crate_name,
 		crate_type,
 		extra_filename,
-		cap_lints_allow,
 		out_dir,
 		externs,
+		extern_packages,
+		remap_path_prefixes,
+		stderr_lines : claimed_diagnostics.clone(),
+		rustc : cmd,
 	})
 }
 
-#[derive(Debug, Default)]
-struct DependencyNames {
-	normal: DependencyNamesValue,
-	development: DependencyNamesValue,
-	build: DependencyNamesValue,
+/// `target` as Cargo takes it, with the path of a custom target spec (`*.json`) made absolute.
+fn target_spec(config :&Config, target :&str) -> OsString {
+	if target.ends_with(".json") {
+		config.cwd().join(target).into()
+	} else {
+		target.into()
+	}
 }
 
-impl DependencyNames {
-	fn new(
-		from :&Package,
-		packages :&HashMap<PackageId, &Package>,
-		resolve :&Resolve,
-		shell :&mut Shell,
-	) -> CargoResult<Self> {
-		let mut this = Self::default();
-
-		let from = from.package_id();
-
-		for (to_pkg, deps) in resolve.deps(from) {
-			let to_pkg = packages.get(&to_pkg).unwrap_or_else(|| panic!("could not find `{}`", to_pkg));
+/// Whether `cargo -Z help` lists `-Z <flag>`. Stable and beta reject `-Z` altogether.
+fn has_unstable_flag(config :&Config, flag :&str) -> bool {
+	config
+		.cargo("-Z")
+		.arg("help")
+		.exec_with_output()
+		.map_or(false, |o| String::from_utf8_lossy(&o.stdout).split_whitespace().any(|w| w == flag))
+}
 
-			// Not all dependencies contain `lib` targets as it is OK to append non-library packages to `Cargo.toml`.
-			// Their `bin` targets can be built with `cargo build --bins -p <SPEC>` and are available in build scripts.
-			if let Some(to_lib) = to_pkg
-				.targets()
-				.iter()
-				.find(|t| t.is_lib())
-			{
-				let extern_crate_name = resolve.extern_crate_name(from, to_pkg.package_id(), to_lib)?;
-				let lib_true_snakecased_name = to_lib.name().replace('-', "_");
+/// The target triple `rustc` compiles for by default, e.g. `x86_64-unknown-linux-gnu`.
+fn host_triple(config :&Config) -> Fallible<String> {
+	let mut rustc = ProcessBuilder::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()));
+	rustc.cwd(config.cwd()).arg("-vV");
+	let output = rustc.exec_with_output()?;
+	String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.find_map(|l| l.strip_prefix("host: "))
+		.map(ToOwned::to_owned)
+		.ok_or_else(|| failure::err_msg("could not find the host triple in `rustc -vV`"))
+}
 
-				for dep in deps {
-					let names = &mut this[dep.kind()];
-					names.by_extern_crate_name.insert(extern_crate_name.clone(), dep.name_in_toml());
+/// `path` in the target directory of `rustc`, as a path in `target_dir` if it does not exist.
+///
+/// The target directory may be mounted elsewhere, e.g. by `cross`. The longest tail of `path`
+/// which exists in `target_dir` is taken, e.g. `debug/deps` of `/target/debug/deps`.
+fn relocate(path :&Path, target_dir :&Path) -> PathBuf {
+	if path.exists() {
+		return path.to_owned();
+	}
+	let components = path.components().collect::<Vec<_>>();
+	(1..components.len())
+		.map(|i| target_dir.join(components[i..].iter().collect::<PathBuf>()))
+		.find(|p| p.exists())
+		.unwrap_or_else(|| path.to_owned())
+}
 
-					// Two `Dependenc`ies with the same name point at the same `Package`.
-					names
-						.by_lib_true_snakecased_name
-						.entry(lib_true_snakecased_name.clone())
-						.or_insert_with(HashSet::new)
-						.insert(dep.name_in_toml());
-				}
-			} else {
-				for dep in deps {
-					this[dep.kind()].non_lib.insert(dep.name_in_toml());
-				}
+/// Resolves `.` and `..` without touching the filesystem.
+fn normalize_path(path :&Path) -> PathBuf {
+	let mut normalized = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				normalized.pop();
 			}
+			component => normalized.push(component),
 		}
+	}
+	normalized
+}
 
-		let ambiguous_names = |kinds: &[dependency::Kind]| -> BTreeMap<_, _> {
-			kinds
-				.iter()
-				.flat_map(|&k| &this[k].by_lib_true_snakecased_name)
-				.filter(|(_, v)| v.len() > 1)
-				.flat_map(|(k, v)| v.iter().map(move |&v| (v, k.deref())))
-				.collect()
-		};
-
-		let ambiguous_normal_dev =
-			ambiguous_names(&[dependency::Kind::Normal, dependency::Kind::Development]);
-		let ambiguous_build = ambiguous_names(&[dependency::Kind::Build]);
-
-		if !(ambiguous_normal_dev.is_empty() && ambiguous_build.is_empty()) {
-			let mut msg = format!(
-				"Currently `cargo-udeps` cannot distinguish multiple crates with the same `lib` name. This may cause false negative\n\
-				 `{}`\n",
-				from,
-			);
-			let (edge, joint) = if ambiguous_build.is_empty() {
-				(' ', '└')
-			} else {
-				('│', '├')
-			};
-			for (ambiguous, edge, joint, prefix) in &[
-				(ambiguous_normal_dev, edge, joint, "(dev-)"),
-				(ambiguous_build, ' ', '└', "build-"),
-			] {
-				if !ambiguous.is_empty() {
-					writeln!(msg, "{}─── {}dependencies", joint, prefix).unwrap();
-					let mut ambiguous = ambiguous.iter().peekable();
-					while let Some((dep, lib)) = ambiguous.next() {
-						let joint = if ambiguous.peek().is_some() {
-							'├'
-						} else {
-							'└'
-						};
-						writeln!(msg, "{}    {}─── {:?} → {:?}", edge, joint, dep, lib).unwrap();
-					}
-				}
-			}
-			shell.warn(msg.trim_end())?;
+/// Whether `package` matches `spec`, which is a package ID specification (e.g. `name`,
+/// `name@1.2` or `file:///path/to/name#1.2.3`) or a glob. Globs with `/` match the directory of
+/// `package` relative to the workspace root.
+fn matches_spec(ws :&Workspace, package :&Package, spec :&str) -> Fallible<bool> {
+	if spec.contains(|c| c == '*' || c == '?' || c == '[') {
+		let pattern = glob::Pattern::new(spec)
+			.with_context(|_| format!("invalid glob pattern `{}`", spec))?;
+		if spec.contains('/') {
+			let dir = package.root().strip_prefix(ws.root()).unwrap_or_else(|_| package.root());
+			return Ok(pattern.matches_path(dir));
 		}
-
-		Ok(this)
+		return Ok(pattern.matches(&package.name()));
 	}
+	let invalid = || failure::format_err!("invalid package ID specification `{}`", spec);
+	let id = package.package_id();
 
-	fn has_non_lib(&self) -> bool {
+	// `url#name@version`, where the name defaults to the last segment of the URL.
+	let (url, fragment) = match spec.find("://") {
+		Some(_) => {
+			let mut split = spec.splitn(2, '#');
+			(split.next(), split.next())
+		}
+		None => (None, Some(spec)),
+	};
+	let mut url_name = None;
+	if let Some(url) = url {
+		// e.g. `path+file:///path/to/name`
+		let (kind, url) = match url.find('+').filter(|&i| i < url.find("://").unwrap_or(0)) {
+			Some(i) => (Some(&url[..i]), &url[i + 1..]),
+			None => (None, url),
+		};
+		let url = Url::parse(url).map_err(|_| invalid())?;
+		url_name = url.path_segments().and_then(|s| s.filter(|s| !s.is_empty()).last()).map(ToOwned::to_owned);
+		let source = &*id.source();
+		let (source_kind, source_url) = match source.find('+') {
+			Some(i) => (&source[..i], &source[i + 1..]),
+			None => ("", source),
+		};
+		let trim = |url :&str| url.trim_end_matches('/').to_owned();
+		if kind.map_or(false, |kind| kind != source_kind)
+			|| Url::parse(source_url).map_or(true, |source_url| trim(source_url.as_str()) != trim(url.as_str()))
+		{
+			return Ok(false);
+		}
+	}
+	let (name, version) = match fragment {
+		None => (url_name, None),
+		Some(fragment) => {
+			let mut split = fragment.splitn(2, |c| c == '@' || c == ':');
+			match (split.next(), split.next()) {
+				(Some(name), Some(version)) => (Some(name.to_owned()), Some(version)),
+				// `url#1.2.3`
+				(Some(version), None) if url.is_some() && version.starts_with(|c :char| c.is_ascii_digit()) => {
+					(url_name, Some(version))
+				}
+				(Some(name), None) => (Some(name.to_owned()), None),
+				_ => return Err(invalid()),
+			}
+		}
+	};
+	let name = name.ok_or_else(invalid)?;
+	if name.is_empty() {
+		return Err(invalid());
+	}
+	let version = match version {
+		Some(version) => matches_partial_version(version, &id.version()).ok_or_else(|| {
+			failure::format_err!("invalid version `{}` in package ID specification `{}`", version, spec)
+		})?,
+		None => true,
+	};
+	Ok(name == *id.name() && version)
+}
+
+/// Whether `version` matches `partial`, which is the version or its prefix, e.g. `1` or `1.2`.
+/// `None` if `partial` is not a version.
+fn matches_partial_version(partial :&str, version :&str) -> Option<bool> {
+	let version = semver::Version::parse(version).ok()?;
+	let parts = partial.split('.').collect::<Vec<_>>();
+	if parts.len() == 3 {
+		return Some(semver::Version::parse(partial).ok()? == version);
+	}
+	if parts.len() > 3 {
+		return None;
+	}
+	let parts = parts.iter().map(|p| p.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+	Some(parts.iter().zip(&[version.major, version.minor]).all(|(p, v)| p == v))
+}
+
+/// Inserts the dependencies of `pkg` which `usage` refers to into `used_dependencies`.
+fn insert_used(
+	pkg :PackageId,
+	usage :&Usage,
+	by_extern_crate_name :&HashMap<String, InternedString>,
+	by_lib_true_snakecased_name :&HashMap<String, HashSet<InternedString>>,
+	used_dependencies :&mut HashSet<(PackageId, InternedString)>,
+) {
+	for name in &usage.extern_crate_names {
+		if let Some(&dependency_name) = by_extern_crate_name.get(name) {
+			used_dependencies.insert((pkg, dependency_name));
+		}
+	}
+	for name in &usage.lib_names {
+		if let Some(dependency_names) = by_lib_true_snakecased_name.get(name) {
+			for &dependency_name in dependency_names {
+				used_dependencies.insert((pkg, dependency_name));
+			}
+		}
+	}
+}
+
+/// Adds the places `usage` refers to the dependencies of `pkg` from to `locations`.
+fn insert_locations(
+	pkg :PackageId,
+	usage :&Usage,
+	by_extern_crate_name :&HashMap<String, InternedString>,
+	by_lib_true_snakecased_name :&HashMap<String, HashSet<InternedString>>,
+	locations :&mut BTreeMap<(PackageId, InternedString), BTreeSet<Location>>,
+) {
+	for (name, referred) in &usage.locations {
+		let dependency_names = by_extern_crate_name
+			.get(name)
+			.into_iter()
+			.chain(by_lib_true_snakecased_name.get(name).into_iter().flatten());
+		for &dependency_name in dependency_names {
+			locations.entry((pkg, dependency_name)).or_default().extend(referred.iter().cloned());
+		}
+	}
+}
+
+/// Returns how the targets of `package` use each of its dependencies which are not unused, from
+/// the syntax. The ones it cannot tell are omitted.
+fn classify_usage(
+	ws :&Workspace,
+	package :&Package,
+	is_unused :impl Fn(dependency::Kind, InternedString) -> bool,
+) -> Fallible<BTreeMap<(dependency::Kind, InternedString), BTreeSet<UsageKind>>> {
+	let id = package.package_id();
+	// By whether they are `[build-dependencies]` and the names given to `--extern`.
+	let mut by_extern_name = HashMap::<(bool, String), Vec<(dependency::Kind, InternedString)>>::new();
+	for dependency in package.dependencies() {
+		let (kind, name) = (dependency.kind(), dependency.name_in_toml());
+		if is_unused(kind, name) {
+			continue;
+		}
+		let resolved = match ws.deps(id).map(|d| ws.package(d)).find(|d| dependency.matches(d)) {
+			Some(resolved) => resolved,
+			None => continue,
+		};
+		if let Some(node_dep) = ws.lib_deps(id).iter().find(|d| d.pkg == resolved.package_id()) {
+			let is_build = kind == dependency::Kind::Build;
+			by_extern_name.entry((is_build, node_dep.name.clone())).or_default().push((kind, name));
+		}
+	}
+
+	let mut usage_kinds = BTreeMap::<_, BTreeSet<_>>::new();
+	for target in package.targets() {
+		// Build scripts are the only targets which can use `[build-dependencies]`.
+		let is_build = target.is_custom_build();
+		let extern_names = by_extern_name
+			.keys()
+			.filter(|(b, _)| *b == is_build)
+			.map(|(_, name)| name.clone())
+			.collect::<HashSet<_>>();
+		if extern_names.is_empty() {
+			continue;
+		}
+		for (extern_name, kinds) in source::usage_kinds(target.src_path(), &extern_names)? {
+			for &dependency in &by_extern_name[&(is_build, extern_name)] {
+				usage_kinds.entry(dependency).or_default().extend(kinds.iter().copied());
+			}
+		}
+	}
+	Ok(usage_kinds)
+}
+
+/// The feature sets `--feature-powerset` checks a package with at most, each of which is a run.
+const MAX_FEATURE_SETS :usize = 1024;
+
+/// The number of subsets of `n` items with at most `depth` items, or `usize::MAX` if it overflows.
+fn num_combinations(n :usize, depth :usize) -> usize {
+	// `by_size[k]` is the number of subsets with `k` items of the items so far.
+	let mut by_size = vec![0usize; depth.min(n) + 1];
+	by_size[0] = 1;
+	for _ in 0..n {
+		for k in (1..by_size.len()).rev() {
+			by_size[k] = by_size[k].saturating_add(by_size[k - 1]);
+		}
+	}
+	by_size.iter().fold(0usize, |sum, &k| sum.saturating_add(k))
+}
+
+/// The subsets of `features` with at most `depth` items, e.g. `[], [a], [b], [a, b]`.
+fn combinations<'a>(features :&[&'a str], depth :usize) -> Vec<Vec<&'a str>> {
+	let mut sets = vec![vec![]];
+	for &feature in features {
+		let extended = sets
+			.iter()
+			.filter(|set :&&Vec<_>| set.len() < depth)
+			.map(|set| set.iter().copied().chain(iter::once(feature)).collect())
+			.collect::<Vec<_>>();
+		sets.extend(extended);
+	}
+	sets
+}
+
+/// Returns the workspace members owning any of `files`, and optionally the members depending on
+/// them.
+fn changed_members(ws :&Workspace, cwd :&Path, files :&[PathBuf], with_dependents :bool) -> HashSet<PackageId> {
+	let root_files = [ws.root().join("Cargo.toml"), ws.root().join("Cargo.lock")];
+	let mut changed = HashSet::new();
+	for file in files {
+		let file = normalize_path(&cwd.join(file));
+		if root_files.contains(&file) {
+			// `[workspace]`, `[patch]` and the lockfile affect every member.
+			return ws.members().map(Package::package_id).collect();
+		}
+		let owner = ws
+			.members()
+			.filter(|m| file.starts_with(m.root()))
+			.max_by_key(|m| m.root().components().count());
+		if let Some(owner) = owner {
+			changed.insert(owner.package_id());
+		}
+	}
+	if with_dependents {
+		loop {
+			let dependents = ws
+				.members()
+				.filter(|m| !changed.contains(&m.package_id()))
+				.filter(|m| ws.deps(m.package_id()).any(|id| changed.contains(&id)))
+				.map(Package::package_id)
+				.collect::<Vec<_>>();
+			if dependents.is_empty() {
+				break;
+			}
+			changed.extend(dependents);
+		}
+	}
+	changed
+}
+
+/// The files which differ from the git `since`, including the uncommitted and untracked ones.
+fn changed_since(config :&Config, since :&str) -> Fallible<Vec<PathBuf>> {
+	let git = |args :&[&str]| -> Fallible<String> {
+		let output = ProcessBuilder::new("git").args(args).cwd(config.cwd()).exec_with_output()?;
+		Ok(String::from_utf8(output.stdout).with_context(|_| "`git` printed non-utf8 paths")?)
+	};
+	let toplevel = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim_end());
+	let changed = git(&["diff", "--name-only", "-z", since, "--"])?;
+	let untracked = git(&["ls-files", "--others", "--exclude-standard", "-z"])?;
+	// `git ls-files` prints the paths relative to the current directory.
+	Ok(changed
+		.split('\0')
+		.filter(|f| !f.is_empty())
+		.map(|f| toplevel.join(f))
+		.chain(untracked.split('\0').filter(|f| !f.is_empty()).map(|f| config.cwd().join(f)))
+		.collect())
+}
+
+/// `package.metadata.cargo-udeps` of `package`.
+fn package_metadata(package :&Package) -> Fallible<PackageMetadataCargoUdeps> {
+	let metadata = package
+		.custom_metadata()
+		.map::<Fallible<_>, _>(|package_metadata| {
+			let PackageMetadata { cargo_udeps } = serde_json::from_value(package_metadata.clone())
+				.with_context(|_| "could not parse `package.metadata.cargo-udeps`")?;
+			Ok(cargo_udeps)
+		})
+		.transpose()?;
+	Ok(metadata.unwrap_or_default())
+}
+
+/// `package.metadata.cargo-udeps.ignore` of `package`, with the dependencies declared with
+/// `# cargo-udeps: ignore`.
+fn package_ignore(package :&Package) -> Fallible<PackageMetadataCargoUdepsIgnore> {
+	let mut ignore = package_metadata(package)?.ignore;
+	// Cargo has read the manifest already. The comments are not worth failing the run if it has
+	// become unreadable since.
+	for (kind, name) in fix::ignored_by_comment(package.manifest_path()).unwrap_or_default() {
+		match kind {
+			dependency::Kind::Normal => &mut ignore.normal,
+			dependency::Kind::Development => &mut ignore.development,
+			dependency::Kind::Build => &mut ignore.build,
+		}
+		.insert(name);
+	}
+	Ok(ignore)
+}
+
+/// `only` of `package.metadata.cargo-udeps`.
+fn package_only(package :&Package) -> Fallible<Vec<DependencySpec>> {
+	package_metadata(package)?
+		.only
+		.iter()
+		.map(|spec| {
+			spec.parse().map_err(|e| {
+				failure::format_err!("could not parse `package.metadata.cargo-udeps.only` of `{}`: {}", package.name(), e)
+			})
+		})
+		.collect()
+}
+
+/// `allow` of `package.metadata.cargo-udeps`.
+fn package_allow(package :&Package) -> Fallible<Vec<dependency::Kind>> {
+	package_metadata(package)?
+		.allow
+		.iter()
+		.map(|kind| {
+			kind.parse().map_err(|e| {
+				failure::format_err!("could not parse `package.metadata.cargo-udeps.allow` of `{}`: {}", package.name(), e)
+			})
+		})
+		.collect()
+}
+
+/// Whether `cargo publish` is allowed for the package (i.e. `publish = false` is not set).
+fn is_published(package :&Package) -> bool {
+	package.publish().map_or(true, |registries| !registries.is_empty())
+}
+
+#[derive(Debug, Default)]
+struct DependencyNames {
+	normal: DependencyNamesValue,
+	development: DependencyNamesValue,
+	build: DependencyNamesValue,
+}
+
+impl DependencyNames {
+	fn new(from :&Package, ws :&Workspace) -> Fallible<Self> {
+		let mut this = Self::default();
+
+		for to_pkg in ws.deps(from.package_id()) {
+			let to_pkg = ws.package(to_pkg);
+			let deps = from
+				.dependencies()
+				.iter()
+				.filter(|d| d.matches(to_pkg) && features::is_enabled(ws, from, d))
+				.collect::<Vec<_>>();
+
+			// Only the immediate dependents via `[dependencies]` receive the `DEP_*` variables.
+			if let Some(links) = to_pkg.links() {
+				for dep in deps.iter().filter(|d| d.kind() == dependency::Kind::Normal) {
+					this.normal
+						.by_links
+						.entry(links.to_uppercase().replace('-', "_"))
+						.or_insert_with(HashSet::new)
+						.insert(dep.name_in_toml());
+				}
+			}
+
+			// Not all dependencies contain `lib` targets as it is OK to append non-library packages to `Cargo.toml`.
+			// Their `bin` targets can be built with `cargo build --bins -p <SPEC>` and are available in build scripts.
+			if let Some(to_lib) = to_pkg
+				.targets()
+				.iter()
+				.find(|t| t.is_lib())
+			{
+				let lib_true_snakecased_name = to_lib.name().replace('-', "_");
+
+				// `cargo metadata` tells the names given to `--extern`.
+				let lib_deps = ws.lib_deps(from.package_id()).iter().filter(|d| d.pkg == to_pkg.package_id());
+				for lib_dep in lib_deps {
+					let extern_crate_name = &lib_dep.name;
+					let deps = deps.iter().filter(|d| {
+						lib_dep.kinds.contains(&d.kind())
+							&& d.explicit_name_in_toml().map_or_else(|| lib_true_snakecased_name.clone(), |r| r.replace('-', "_"))
+								== *extern_crate_name
+					});
+					for dep in deps {
+						let names = &mut this[dep.kind()];
+						names.by_extern_crate_name.insert(extern_crate_name.clone(), dep.name_in_toml());
+
+						// Two `Dependenc`ies with the same name point at the same `Package`.
+						names
+							.by_lib_true_snakecased_name
+							.entry(lib_true_snakecased_name.clone())
+							.or_insert_with(HashSet::new)
+							.insert(dep.name_in_toml());
+					}
+				}
+			} else {
+				for dep in deps {
+					this[dep.kind()].non_lib.insert(dep.name_in_toml());
+				}
+			}
+		}
+
+		Ok(this)
+	}
+
+	fn has_non_lib(&self) -> bool {
 		[dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build]
 			.iter()
 			.any(|&k| !self[k].non_lib.is_empty())
@@ -765,6 +3291,8 @@ impl IndexMut<dependency::Kind> for DependencyNames {
 struct DependencyNamesValue {
 	by_extern_crate_name :HashMap<String, InternedString>,
 	by_lib_true_snakecased_name :HashMap<String, HashSet<InternedString>>,
+	/// Upper-snakecased `links` values as they appear in `DEP_<links>_<key>`.
+	by_links :HashMap<String, HashSet<InternedString>>,
 	non_lib :HashSet<InternedString>,
 }
 
@@ -775,109 +3303,1307 @@ struct PackageMetadata {
 	cargo_udeps: PackageMetadataCargoUdeps,
 }
 
-#[derive(Debug, Default, Deserialize)]
-struct PackageMetadataCargoUdeps {
-	#[serde(default)]
-	ignore: PackageMetadataCargoUdepsIgnore,
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct WorkspaceMetadata {
+	#[serde(default)]
+	cargo_udeps: WorkspaceMetadataCargoUdeps,
+}
+
+/// `udeps.toml` in the workspace root, or `[workspace.metadata.cargo-udeps]`. The flags take
+/// precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct WorkspaceMetadataCargoUdeps {
+	/// Feature sets for `--features-matrix`, e.g. `["", "serde", "std,serde"]`.
+	#[serde(default)]
+	features_matrix: Vec<String>,
+	/// `--output`
+	#[serde(default)]
+	output: Option<String>,
+	/// `--backend`
+	#[serde(default)]
+	backend: Vec<String>,
+	/// `--target`
+	#[serde(default)]
+	target: Vec<String>,
+	/// `--ensure-full-coverage`
+	#[serde(default)]
+	ensure_full_coverage: bool,
+	/// `--allow`
+	#[serde(default)]
+	allow: Vec<String>,
+	#[serde(default)]
+	ignore: WorkspaceMetadataCargoUdepsIgnore,
+}
+
+impl WorkspaceMetadataCargoUdeps {
+	/// `udeps.toml` if there is one, `[workspace.metadata.cargo-udeps]` otherwise.
+	fn load(ws :&Workspace) -> Fallible<Self> {
+		let path = ws.root().join("udeps.toml");
+		if path.exists() {
+			let toml = fs::read_to_string(&path).with_context(|_| format!("could not read `{}`", path.display()))?;
+			let toml = toml
+				.parse::<toml_edit::Document>()
+				.with_context(|_| format!("could not parse `{}`", path.display()))?;
+			let this = serde_json::from_value(toml_to_json(toml.as_table()))
+				.with_context(|_| format!("could not parse `{}`", path.display()))?;
+			return Ok(this);
+		}
+		let WorkspaceMetadata { cargo_udeps } = ws
+			.custom_metadata()
+			.map(|metadata| serde_json::from_value(metadata.clone()))
+			.transpose()
+			.with_context(|_| "could not parse `workspace.metadata.cargo-udeps`")?
+			.unwrap_or_default();
+		Ok(cargo_udeps)
+	}
+}
+
+/// `toml_edit` 0.1 does not support `serde`.
+fn toml_to_json(table :&toml_edit::Table) -> serde_json::Value {
+	fn item_to_json(item :&toml_edit::Item) -> serde_json::Value {
+		match item {
+			toml_edit::Item::Table(table) => toml_to_json(table),
+			toml_edit::Item::ArrayOfTables(tables) => tables.iter().map(toml_to_json).collect::<Vec<_>>().into(),
+			toml_edit::Item::Value(value) => value_to_json(value),
+			toml_edit::Item::None => serde_json::Value::Null,
+		}
+	}
+
+	fn value_to_json(value :&toml_edit::Value) -> serde_json::Value {
+		if let Some(table) = value.as_inline_table() {
+			table.iter().map(|(k, v)| (k.to_owned(), value_to_json(v))).collect::<serde_json::Map<_, _>>().into()
+		} else if let Some(array) = value.as_array() {
+			array.iter().map(value_to_json).collect::<Vec<_>>().into()
+		} else if let Some(s) = value.as_str() {
+			s.into()
+		} else if let Some(i) = value.as_integer() {
+			i.into()
+		} else if let Some(f) = value.as_float() {
+			f.into()
+		} else if let Some(b) = value.as_bool() {
+			b.into()
+		} else {
+			// Dates
+			value.to_string().trim().into()
+		}
+	}
+
+	table.iter().map(|(k, v)| (k.to_owned(), item_to_json(v))).collect::<serde_json::Map<_, _>>().into()
+}
+
+/// Merged with `package.metadata.cargo-udeps.ignore` of the members.
+#[derive(Clone, Debug, Default, Deserialize)]
+struct WorkspaceMetadataCargoUdepsIgnore {
+	/// Ignored in every member.
+	#[serde(flatten)]
+	all: PackageMetadataCargoUdepsIgnore,
+	/// Ignored in the members, by their names.
+	#[serde(default)]
+	members: HashMap<String, PackageMetadataCargoUdepsIgnore>,
+}
+
+impl WorkspaceMetadataCargoUdepsIgnore {
+	fn contains(&self, package: &Package, kind: dependency::Kind, name_in_toml: InternedString) -> bool {
+		self.all.contains(kind, name_in_toml)
+			|| self.members.get(&*package.name()).map_or(false, |m| m.contains(kind, name_in_toml))
+	}
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PackageMetadataCargoUdeps {
+	#[serde(default)]
+	ignore: PackageMetadataCargoUdepsIgnore,
+	/// The dependencies to check, e.g. `["serde", "dev:criterion"]`. Every one if empty.
+	#[serde(default)]
+	only: Vec<String>,
+	/// Never check the package, e.g. generated or vendored ones.
+	#[serde(default)]
+	skip: bool,
+	/// The kinds whose unused dependencies do not fail the check, e.g. `["dev"]`.
+	#[serde(default)]
+	allow: Vec<String>,
+	/// `false` makes every unused dependency of the package a warning.
+	#[serde(default)]
+	deny: Option<bool>,
+	/// The number of unused dependencies (of the kinds not in `allow`) which are warnings. One
+	/// more fails the check.
+	#[serde(default)]
+	max_unused: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct PackageMetadataCargoUdepsIgnore {
+	#[serde(default)]
+	normal: HashSet<String>,
+	#[serde(default)]
+	development: HashSet<String>,
+	#[serde(default)]
+	build: HashSet<String>,
+}
+
+impl PackageMetadataCargoUdepsIgnore {
+	fn contains(&self, kind: dependency::Kind, name_in_toml: InternedString) -> bool {
+		match kind {
+			dependency::Kind::Normal => &self.normal,
+			dependency::Kind::Development => &self.development,
+			dependency::Kind::Build => &self.build,
+		}
+		.contains(&*name_in_toml)
+	}
+
+	fn iter(&self) -> impl Iterator<Item = (dependency::Kind, &str)> {
+		let normal = self.normal.iter().map(|n| (dependency::Kind::Normal, &**n));
+		let development = self.development.iter().map(|n| (dependency::Kind::Development, &**n));
+		let build = self.build.iter().map(|n| (dependency::Kind::Build, &**n));
+		normal.chain(development).chain(build)
+	}
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, JsonSchema)]
+struct Outcome {
+	/// Incremented only when a field is removed or changes its meaning. New fields may be added
+	/// without a new version.
+	#[serde(default)]
+	schema_version: SchemaVersion,
+	success: bool,
+	/// The analysis was stopped by `--max-duration`.
+	#[serde(default)]
+	incomplete: bool,
+	#[serde(with = "package_key::map")]
+	#[schemars(with = "Vec<PackageEntry<OutcomeUnusedDeps>>")]
+	unused_deps: BTreeMap<PackageId, OutcomeUnusedDeps>,
+	/// Targets and dependencies which were not analyzed.
+	#[serde(default)]
+	#[serde(with = "package_key::map")]
+	#[schemars(with = "Vec<PackageEntry<OutcomeCoverage>>")]
+	coverage: BTreeMap<PackageId, OutcomeCoverage>,
+	/// Hints on the findings, shown as `Note:`s in the human output.
+	#[serde(default)]
+	notes: Vec<OutcomeNote>,
+	/// Edits removing the unused dependencies, for editors and bots.
+	#[serde(default)]
+	suggestions: Vec<OutcomeSuggestion>,
+	/// Dependencies used only with some of the feature sets.
+	#[serde(default)]
+	feature_gated: Vec<OutcomeFeatureGated>,
+	/// Optional dependencies which no feature in `[features]` enables.
+	#[serde(default)]
+	#[serde(with = "package_key::map")]
+	#[schemars(with = "Vec<PackageEntry<OutcomeUnusedDeps>>")]
+	never_enabled: BTreeMap<PackageId, OutcomeUnusedDeps>,
+	/// Features of used dependencies whose items are never referred to.
+	#[serde(default)]
+	unused_features: Vec<OutcomeUnusedFeatures>,
+	/// The unused dependencies inherited from `[workspace.dependencies]`.
+	#[serde(default)]
+	inherited: BTreeMap<InternedString, OutcomeInherited>,
+	/// The default features of used dependencies, none of whose items are referred to.
+	#[serde(default)]
+	unused_default_features: Vec<OutcomeUnusedFeatures>,
+	/// Used dependencies declared in the wrong table, e.g. used only by tests.
+	#[serde(default)]
+	misplaced: Vec<OutcomeMisplaced>,
+	/// Dependencies used only behind platform-specific `cfg`s.
+	#[serde(default)]
+	target_specific: Vec<OutcomeTargetSpecific>,
+	/// How the used dependencies are used, with `--classify-usage`.
+	#[serde(default)]
+	usage_kinds: Vec<OutcomeUsageKinds>,
+	/// The dependencies the backends disagree on, with `--compare-backends`.
+	#[serde(default)]
+	backend_disagreements: Vec<OutcomeBackendDisagreement>,
+	/// The unused dependencies on each of the target triples given with `--target`, if several.
+	#[serde(default)]
+	#[serde(with = "package_key::nested_map")]
+	#[schemars(with = "BTreeMap<String, Vec<PackageEntry<OutcomeUnusedDeps>>>")]
+	by_target: BTreeMap<String, BTreeMap<PackageId, OutcomeUnusedDeps>>,
+	/// Ignore entries naming dependencies which the packages do not declare.
+	#[serde(default)]
+	stale_ignores: Vec<OutcomeStaleIgnore>,
+	/// Ignored dependencies which are used.
+	#[serde(default)]
+	unnecessary_ignores: Vec<OutcomeUnnecessaryIgnore>,
+	/// The warnings shown on the shell.
+	#[serde(default)]
+	warnings: Vec<OutcomeWarning>,
+	/// The analyzed edges of the dependency graph, for `--output dot`.
+	#[serde(skip)]
+	edges: Vec<OutcomeEdge>,
+	/// Some dependencies are non-library packages, which are regarded as unused.
+	#[serde(skip)]
+	non_lib: bool,
+	/// Where the dependencies are referred from, for `cargo udeps explain`.
+	#[serde(skip)]
+	locations: BTreeMap<(PackageId, InternedString), BTreeSet<Location>>,
+	/// How the human output lays out `unused_deps` and `never_enabled`.
+	#[serde(skip)]
+	layout: Layout,
+	/// The workspace root, which the paths are relative to with `--paths relative`.
+	#[serde(skip)]
+	root: PathBuf,
+}
+
+/// The version of the format of `--output json`. The reports of the previous versions are read
+/// as the current one.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+struct SchemaVersion(u32);
+
+impl Default for SchemaVersion {
+	fn default() -> Self {
+		Self(4)
+	}
+}
+
+/// A hint on the findings of a run.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "code", rename_all = "kebab-case")]
+enum OutcomeNote {
+	/// Only some of the targets were checked, so the dependencies might be used by the other ones.
+	MissingAllTargets {
+		/// No target was selected either, so `--all-targets` is suggested.
+		suggest_all_targets: bool,
+	},
+	/// Optional dependencies which no feature enables can still be enabled with `--features`.
+	NeverEnabled,
+	/// Dependencies used only with some features can be made optional.
+	FeatureGated,
+	/// The features were judged by the items under `#[cfg(feature = "..")]`.
+	UnusedFeatures,
+	UnusedDefaultFeatures,
+	/// Misplaced dependencies are built for the dependents as well.
+	Misplaced,
+	/// Dependencies used only on some platforms are built on the other ones as well.
+	TargetSpecific,
+	/// Non-library packages are regarded as unused.
+	NonLibDependencies,
+	/// The findings might be false positives.
+	PossibleFalsePositive {
+		/// With `--doc`. Otherwise the usage in doctests is not seen.
+		doctests_analyzed: bool,
+	},
+}
+
+/// The text of the human output, one `Note:` with the continuation lines indented.
+impl fmt::Display for OutcomeNote {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingAllTargets { suggest_all_targets } => {
+				writeln!(f, "Note: These dependencies might be used by other targets.")?;
+				if *suggest_all_targets {
+					writeln!(f, "      To find dependencies that are not used by any target, enable `--all-targets`.")?;
+				}
+				Ok(())
+			}
+			Self::NeverEnabled => {
+				writeln!(f, "Note: Optional dependencies which no feature enables can still be enabled with `--features <name>`.")?;
+				writeln!(f, "      Enable them from a feature with `dep:<name>`, or remove them.")
+			}
+			Self::FeatureGated => {
+				writeln!(f, "Note: Dependencies used only with some features can be made `optional = true`,")?;
+				writeln!(f, "      and enabled by those features with `dep:<name>`.")
+			}
+			Self::UnusedFeatures => {
+				writeln!(f, "Note: Features are regarded as unused if their `#[cfg(feature = \"..\")]` items are never referred to.")?;
+				writeln!(f, "      Their `impl`s and the features of other crates they enable may still be used.")
+			}
+			Self::UnusedDefaultFeatures => {
+				writeln!(f, "Note: The default features of some dependencies seem unneeded.")?;
+				writeln!(f, "      Set `default-features = false` on them.")
+			}
+			Self::Misplaced => {
+				writeln!(f, "Note: Misplaced dependencies are built for the dependents of the package as well.")?;
+				writeln!(f, "      Move them to the suggested table.")
+			}
+			Self::TargetSpecific => {
+				writeln!(f, "Note: Dependencies used only on some platforms are built on the other ones as well.")?;
+				writeln!(f, "      Move them to the suggested `[target.'cfg(..)'.*]` table.")
+			}
+			Self::NonLibDependencies => {
+				writeln!(f, "Note: Some dependencies are non-library packages.")?;
+				writeln!(f, "      `cargo-udeps` regards them as unused.")
+			}
+			Self::PossibleFalsePositive { doctests_analyzed } => {
+				writeln!(f, "Note: They might be false-positive.")?;
+				if !doctests_analyzed {
+					writeln!(f, "      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.")?;
+				}
+				writeln!(f, "      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.")
+			}
+		}
+	}
+}
+
+/// Replaces `byte_start..byte_end` of the manifest (the whole lines `line_start..=line_end`)
+/// with `replacement`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeSuggestion {
+	manifest_path: String,
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	/// `normal`, `development` or `build`.
+	kind: String,
+	dependency: InternedString,
+	line_start: usize,
+	line_end: usize,
+	byte_start: usize,
+	byte_end: usize,
+	replacement: String,
+}
+
+impl OutcomeSuggestion {
+	fn removal(
+		package :PackageId,
+		unused :&OutcomeUnusedDeps,
+		kind :dependency::Kind,
+		dependency :InternedString,
+		declaration :manifest::Declaration,
+	) -> Self {
+		let manifest_path = unused.manifest_path.clone();
+		Self::new(package, manifest_path, kind, dependency, declaration, "".to_owned())
+	}
+
+	fn new(
+		package :PackageId,
+		manifest_path :String,
+		kind :dependency::Kind,
+		dependency :InternedString,
+		declaration :manifest::Declaration,
+		replacement :String,
+	) -> Self {
+		let kind = match kind {
+			dependency::Kind::Normal => "normal",
+			dependency::Kind::Development => "development",
+			dependency::Kind::Build => "build",
+		};
+		Self {
+			manifest_path,
+			package,
+			kind : kind.to_owned(),
+			dependency,
+			line_start : declaration.line,
+			line_end : declaration.end_line,
+			byte_start : declaration.span.start,
+			byte_end : declaration.span.end,
+			replacement,
+		}
+	}
+}
+
+/// A dependency which may be `optional`, enabled by the features it is used with.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeFeatureGated {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+	/// The smallest feature sets it is used with, e.g. `["serde"]`.
+	features: Vec<String>,
+}
+
+/// An entry of `[workspace.dependencies]` which some members inherit but do not use.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+struct OutcomeInherited {
+	#[serde(with = "package_key::set")]
+	#[schemars(with = "Vec<PackageKey>")]
+	unused_by: BTreeSet<PackageId>,
+	/// The members which still inherit it. The entry can be removed if this is empty.
+	#[serde(with = "package_key::set")]
+	#[schemars(with = "Vec<PackageKey>")]
+	used_by: BTreeSet<PackageId>,
+}
+
+/// Features enabled on a dependency which may be dropped.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeUnusedFeatures {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+	features: Vec<String>,
+}
+
+/// A dependency which only targets with access to another kind of dependencies use.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeMisplaced {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+	/// The kind it can be declared as instead.
+	suggested: dependency::Kind,
+}
+
+/// An entry of `package.metadata.cargo-udeps.ignore` (or of the members in the workspace
+/// metadata) which the package does not declare.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeStaleIgnore {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: String,
+}
+
+/// A warning about the analysis or the configuration, e.g. an ambiguous library name.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeWarning {
+	/// `allowed`, `ambiguous-lib-name`, `non-lib`, `incomplete`, `stale-ignore` or `unnecessary-ignore`.
+	code: String,
+	#[serde(with = "package_key::option")]
+	#[schemars(with = "Option<PackageKey>")]
+	package: Option<PackageId>,
+	message: String,
+}
+
+/// An ignored dependency which the analysis finds used, so that the entry can be removed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeUnnecessaryIgnore {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+}
+
+/// A dependency which can be declared in `[target.'cfg(..)'.*]`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeTargetSpecific {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+	/// e.g. `unix`
+	cfg: String,
+}
+
+/// A dependency which some of the backends find unused and the others used.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeBackendDisagreement {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+	/// The backends which find it unused.
+	unused_by: Vec<String>,
+	/// The backends which find it used, with the lines they see it referred from (e.g.
+	/// `src/lib.rs:3`, none if the backend does not tell).
+	used_by: BTreeMap<String, Vec<String>>,
+}
+
+/// How a used dependency is used.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeUsageKinds {
+	#[serde(with = "package_key::id")]
+	#[schemars(with = "PackageKey")]
+	package: PackageId,
+	kind: dependency::Kind,
+	dependency: InternedString,
+	kinds: BTreeSet<UsageKind>,
+}
+
+/// A way of using a dependency, told from the syntax.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum UsageKind {
+	/// A macro invocation, or an attribute other than `#[derive(..)]`.
+	Macro,
+	/// A derive macro in `#[derive(..)]`.
+	Derive,
+	/// A trait implemented with `impl Trait for ..`.
+	TraitImpl,
+	/// `pub use` or `pub extern crate`.
+	ReExport,
+	/// A type or a trait in the signature of a `pub` item.
+	PublicApi,
+	/// Anything else, e.g. a function call.
+	Other,
+}
+
+#[derive(Clone, Debug)]
+struct OutcomeEdge {
+	from: PackageId,
+	/// `None` if the dependency is not resolved.
+	to: Option<PackageId>,
+	name: InternedString,
+	kind: dependency::Kind,
+	unused: bool,
+}
+
+impl Outcome {
+	/// Reads a report written with `--output json`.
+	fn read(path :&Path) -> Fallible<Self> {
+		let report = fs::read_to_string(path).with_context(|_| format!("could not read `{}`", path.display()))?;
+		let report = serde_json::from_str(&report).with_context(|_| format!("could not parse `{}`", path.display()))?;
+		Ok(report)
+	}
+
+	/// Keeps `warning` unless it is already there, e.g. from another feature set.
+	fn add_warning(&mut self, warning :OutcomeWarning) {
+		if !self.warnings.contains(&warning) {
+			self.warnings.push(warning);
+		}
+	}
+
+	/// Shows a warning on the shell, and keeps it for the machine-readable outputs.
+	fn warn(&mut self, shell :&mut Shell, code :&str, package :Option<PackageId>, message :String) -> Fallible<()> {
+		shell.warn(&message)?;
+		self.add_warning(OutcomeWarning { code : code.to_owned(), package, message });
+		Ok(())
+	}
+
+	fn merge(&mut self, other :Self) {
+		for (id, deps) in other.unused_deps {
+			match self.unused_deps.entry(id) {
+				btree_map::Entry::Vacant(entry) => {
+					entry.insert(deps);
+				}
+				btree_map::Entry::Occupied(entry) => {
+					let entry = entry.into_mut();
+					entry.normal.extend(deps.normal);
+					entry.development.extend(deps.development);
+					entry.build.extend(deps.build);
+					entry.renamed.extend(deps.renamed);
+					entry.platforms.extend(deps.platforms);
+					entry.blame.extend(deps.blame);
+					entry.ignored.extend(deps.ignored);
+				}
+			}
+		}
+		self.coverage.extend(other.coverage);
+		self.suggestions.extend(other.suggestions);
+		self.feature_gated.extend(other.feature_gated);
+		self.never_enabled.extend(other.never_enabled);
+		self.unused_features.extend(other.unused_features);
+		for (name, inherited) in other.inherited {
+			let entry = self.inherited.entry(name).or_default();
+			entry.unused_by.extend(inherited.unused_by);
+			entry.used_by.extend(inherited.used_by);
+		}
+		self.unused_default_features.extend(other.unused_default_features);
+		self.misplaced.extend(other.misplaced);
+		self.target_specific.extend(other.target_specific);
+		self.usage_kinds.extend(other.usage_kinds);
+		self.backend_disagreements.extend(other.backend_disagreements);
+		self.by_target.extend(other.by_target);
+		self.stale_ignores.extend(other.stale_ignores);
+		self.unnecessary_ignores.extend(other.unnecessary_ignores);
+		for warning in other.warnings {
+			self.add_warning(warning);
+		}
+		for note in other.notes {
+			if !self.notes.contains(&note) {
+				self.notes.push(note);
+			}
+		}
+		self.incomplete |= other.incomplete;
+	}
+
+	/// Combines the outcomes of several feature sets (e.g. `--features-matrix`). A dependency is
+	/// unused if no feature set uses it, and a target is skipped if every one skips it.
+	fn intersect(outcomes :Vec<(String, Self)>) -> Self {
+		let mut used_with = BTreeMap::<_, Vec<&str>>::new();
+		let mut unused_somewhere = HashSet::new();
+		for (features, outcome) in &outcomes {
+			for edge in &outcome.edges {
+				if edge.unused {
+					unused_somewhere.insert((edge.from, edge.kind, edge.name));
+				} else {
+					used_with.entry((edge.from, edge.kind, edge.name)).or_default().push(features);
+				}
+			}
+		}
+		let feature_gated = used_with
+			.iter()
+			.filter(|(key, _)| unused_somewhere.contains(key))
+			.map(|(&(package, kind, dependency), features)| OutcomeFeatureGated {
+				package,
+				kind,
+				dependency,
+				features : minimal_feature_sets(features),
+			})
+			.collect();
+		let used = used_with.into_iter().map(|(key, features)| (key, features.len())).collect::<HashMap<_, _>>();
+		let outcomes = outcomes.into_iter().map(|(_, outcome)| outcome).collect::<Vec<_>>();
+		let num_outcomes = outcomes.len();
+		let mut skipped_targets = HashMap::<_, usize>::new();
+		let mut unevaluated_platforms = HashMap::<_, usize>::new();
+		let mut weakly_enabled = HashMap::<_, (usize, BTreeSet<String>)>::new();
+		let mut misplaced = BTreeMap::<_, usize>::new();
+		let mut target_specific = BTreeMap::<_, usize>::new();
+		let mut unnecessary_ignores = BTreeMap::<_, usize>::new();
+		let mut intersection = Self::default();
+		for outcome in outcomes {
+			for m in outcome.misplaced {
+				*misplaced.entry((m.package, m.kind, m.dependency, m.suggested)).or_default() += 1;
+			}
+			for t in outcome.target_specific {
+				*target_specific.entry((t.package, t.kind, t.dependency, t.cfg)).or_default() += 1;
+			}
+			for u in outcome.unnecessary_ignores {
+				*unnecessary_ignores.entry((u.package, u.kind, u.dependency)).or_default() += 1;
+			}
+			for (id, mut deps) in outcome.unused_deps {
+				for &kind in &[dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build] {
+					deps.unused_deps_mut(kind).retain(|&name| !used.contains_key(&(id, kind, name)));
+				}
+				intersection.merge(Self { unused_deps : vec![(id, deps)].into_iter().collect(), ..Self::default() });
+			}
+			for (id, coverage) in outcome.coverage {
+				for target in coverage.skipped_targets {
+					*skipped_targets.entry((id, target.target.clone())).or_default() += 1;
+					let entry = intersection.coverage.entry(id).or_default();
+					if !entry.skipped_targets.iter().any(|t| t.target == target.target) {
+						entry.skipped_targets.push(target);
+					}
+				}
+				for (platform, deps) in coverage.unevaluated_platforms {
+					for dep in deps {
+						*unevaluated_platforms.entry((id, platform.clone(), dep)).or_default() += 1;
+					}
+				}
+				for (dep, features) in coverage.weakly_enabled {
+					let entry = weakly_enabled.entry((id, dep)).or_default();
+					entry.0 += 1;
+					entry.1.extend(features);
+				}
+			}
+			for edge in outcome.edges {
+				match intersection.edges.iter_mut().find(|e| (e.from, e.to, e.name, e.kind) == (edge.from, edge.to, edge.name, edge.kind)) {
+					Some(e) => e.unused &= edge.unused,
+					None => intersection.edges.push(edge),
+				}
+			}
+			intersection.incomplete |= outcome.incomplete;
+			intersection.non_lib |= outcome.non_lib;
+			for (key, locations) in outcome.locations {
+				intersection.locations.entry(key).or_default().extend(locations);
+			}
+			for warning in outcome.warnings {
+				intersection.add_warning(warning);
+			}
+		}
+		for (&id, coverage) in &mut intersection.coverage {
+			coverage
+				.skipped_targets
+				.retain(|t| skipped_targets.get(&(id, t.target.clone())) == Some(&num_outcomes));
+		}
+		for ((id, platform, dep), n) in unevaluated_platforms {
+			if n == num_outcomes {
+				intersection
+					.coverage
+					.entry(id)
+					.or_default()
+					.unevaluated_platforms
+					.entry(platform)
+					.or_default()
+					.insert(dep);
+			}
+		}
+		for ((id, dep), (n, features)) in weakly_enabled {
+			if n == num_outcomes {
+				intersection.coverage.entry(id).or_default().weakly_enabled.insert(dep, features);
+			}
+		}
+		intersection.coverage.retain(|_, c| !c.is_empty());
+		intersection.feature_gated = feature_gated;
+		// Misplaced with every feature set it is used with.
+		intersection.misplaced = misplaced
+			.into_iter()
+			.filter(|&((package, kind, dependency, _), n)| {
+				used.get(&(package, kind, dependency)) == Some(&n)
+			})
+			.map(|((package, kind, dependency, suggested), _)| OutcomeMisplaced { package, kind, dependency, suggested })
+			.collect();
+		intersection.target_specific = target_specific
+			.into_iter()
+			.filter(|((package, kind, dependency, _), n)| used.get(&(*package, *kind, *dependency)) == Some(n))
+			.map(|((package, kind, dependency, cfg), _)| OutcomeTargetSpecific { package, kind, dependency, cfg })
+			.collect();
+		// Unnecessary only if every feature set uses it.
+		intersection.unnecessary_ignores = unnecessary_ignores
+			.into_iter()
+			.filter(|&(_, n)| n == num_outcomes)
+			.map(|((package, kind, dependency), _)| OutcomeUnnecessaryIgnore { package, kind, dependency })
+			.collect();
+		intersection
+	}
+
+	/// For `--report-only`: drops the results of the packages which are not `reported`.
+	fn retain_packages(&mut self, reported :impl Fn(PackageId) -> bool) {
+		self.unused_deps.retain(|&id, _| reported(id));
+		self.never_enabled.retain(|&id, _| reported(id));
+		for map in self.by_target.values_mut() {
+			map.retain(|&id, _| reported(id));
+		}
+		self.coverage.retain(|&id, _| reported(id));
+		self.suggestions.retain(|s| reported(s.package));
+		for inherited in self.inherited.values_mut() {
+			inherited.unused_by.retain(|&id| reported(id));
+		}
+		self.inherited.retain(|_, inherited| !inherited.unused_by.is_empty());
+		self.feature_gated.retain(|g| reported(g.package));
+		self.unused_features.retain(|u| reported(u.package));
+		self.unused_default_features.retain(|u| reported(u.package));
+		self.misplaced.retain(|m| reported(m.package));
+		self.target_specific.retain(|t| reported(t.package));
+		self.usage_kinds.retain(|u| reported(u.package));
+		self.backend_disagreements.retain(|d| reported(d.package));
+		self.stale_ignores.retain(|s| reported(s.package));
+		self.unnecessary_ignores.retain(|u| reported(u.package));
+		self.warnings.retain(|w| w.package.map_or(true, &reported));
+		self.edges.retain(|e| reported(e.from));
+	}
+
+	/// The manifest at `path`, one of the `manifest_path`s of the report, which may be relative
+	/// to the workspace root.
+	fn manifest_path(&self, path :&str) -> PathBuf {
+		self.root.join(path)
+	}
+
+	/// For `--paths relative`: makes the paths of the packages and their manifests under `root`
+	/// relative to it.
+	fn relativize_paths(&mut self, root :&Path) {
+		self.root = root.to_owned();
+		let id = |id :PackageId| id.relative_to(root);
+		let manifest_path = |path :&mut String| {
+			if let Ok(relative) = Path::new(&*path).strip_prefix(root) {
+				*path = relative.to_string_lossy().into_owned();
+			}
+		};
+		let unused_deps = |map :BTreeMap<PackageId, OutcomeUnusedDeps>| {
+			map.into_iter()
+				.map(|(package, mut unused)| {
+					manifest_path(&mut unused.manifest_path);
+					(id(package), unused)
+				})
+				.collect::<BTreeMap<_, _>>()
+		};
+
+		self.unused_deps = unused_deps(mem::take(&mut self.unused_deps));
+		self.never_enabled = unused_deps(mem::take(&mut self.never_enabled));
+		for map in self.by_target.values_mut() {
+			*map = unused_deps(mem::take(map));
+		}
+		self.coverage = mem::take(&mut self.coverage).into_iter().map(|(package, c)| (id(package), c)).collect();
+		for suggestion in &mut self.suggestions {
+			suggestion.package = id(suggestion.package);
+			manifest_path(&mut suggestion.manifest_path);
+		}
+		for inherited in self.inherited.values_mut() {
+			inherited.unused_by = inherited.unused_by.iter().map(|&p| id(p)).collect();
+			inherited.used_by = inherited.used_by.iter().map(|&p| id(p)).collect();
+		}
+		for gated in &mut self.feature_gated {
+			gated.package = id(gated.package);
+		}
+		for unused in self.unused_features.iter_mut().chain(&mut self.unused_default_features) {
+			unused.package = id(unused.package);
+		}
+		for misplaced in &mut self.misplaced {
+			misplaced.package = id(misplaced.package);
+		}
+		for target_specific in &mut self.target_specific {
+			target_specific.package = id(target_specific.package);
+		}
+		for usage in &mut self.usage_kinds {
+			usage.package = id(usage.package);
+		}
+		for disagreement in &mut self.backend_disagreements {
+			disagreement.package = id(disagreement.package);
+		}
+		for stale in &mut self.stale_ignores {
+			stale.package = id(stale.package);
+		}
+		for unnecessary in &mut self.unnecessary_ignores {
+			unnecessary.package = id(unnecessary.package);
+		}
+		for warning in &mut self.warnings {
+			warning.package = warning.package.map(id);
+		}
+		for edge in &mut self.edges {
+			edge.from = id(edge.from);
+			edge.to = edge.to.map(id);
+		}
+	}
+
+	fn exit_code(&self) -> i32 {
+		if self.incomplete {
+			EXIT_INCOMPLETE
+		} else if self.success {
+			0
+		} else {
+			EXIT_UNUSED
+		}
+	}
+
+	/// `cwd` is the base of the relative paths in annotations for CI systems.
+	fn print(&self, output: OutputKind, cwd: &Path, stdout: impl Write) -> io::Result<()> {
+		match output {
+			OutputKind::Human => self.print_human(stdout),
+			OutputKind::Json => self.print_json(stdout),
+			OutputKind::Sarif => output::print_sarif(self, cwd, stdout),
+			OutputKind::Github => output::print_github(self, cwd, stdout),
+			OutputKind::Junit => output::print_junit(self, stdout),
+			OutputKind::Codeclimate => output::print_codeclimate(self, cwd, stdout),
+			OutputKind::Teamcity => output::print_teamcity(self, cwd, stdout),
+			OutputKind::Azure => output::print_azure(self, cwd, stdout),
+			OutputKind::CiAuto => self.print(OutputKind::detect_ci(), cwd, stdout),
+			OutputKind::Markdown => output::print_markdown(self, cwd, stdout),
+			OutputKind::Html => output::print_html(self, stdout),
+			OutputKind::Dot => output::print_dot(self, stdout),
+			OutputKind::Patch => output::print_patch(self, cwd, stdout),
+			OutputKind::Short => output::print_short(self, stdout),
+		}
+	}
+
+	fn print_human(&self, mut stdout: impl Write) -> io::Result<()> {
+		// With `--allow`, the run can succeed with unused dependencies. Advice such as
+		// `target_specific` is shown after the success line.
+		let all_used = self.unused_deps.values().all(OutcomeUnusedDeps::is_empty);
+		if self.success && all_used {
+			writeln!(stdout, "{}", self.layout.paint(Colour::Green.bold(), "All deps seem to have been used."))?;
+		}
+		if !all_used {
+			writeln!(stdout, "unused dependencies:")?;
+
+			write_unused_deps(&mut stdout, &self.unused_deps, self.layout)?;
+		}
+
+		if !self.inherited.is_empty() {
+			writeln!(stdout, "inherited from `[workspace.dependencies]`:")?;
+
+			let items = self
+				.inherited
+				.iter()
+				.map(|(name, inherited)| {
+					if inherited.used_by.is_empty() {
+						format!("{:?} (unused by every member, the workspace entry can be removed too)", name)
+					} else {
+						let used_by = inherited.used_by.iter().map(|id| format!("`{}`", id.name())).collect::<Vec<_>>();
+						format!("{:?} (still inherited by {})", name, used_by.join(", "))
+					}
+				})
+				.collect::<Vec<_>>();
+			write_tree(&mut stdout, self.layout.charset, &[("workspace".to_owned(), items)])?;
+		}
+
+		if !self.never_enabled.is_empty() {
+			writeln!(stdout, "optional dependencies not enabled by any feature:")?;
+
+			write_unused_deps(&mut stdout, &self.never_enabled, self.layout)?;
+		}
+
+		if !self.feature_gated.is_empty() {
+			writeln!(stdout, "used only with some features:")?;
+
+			write_by_package(&mut stdout, self.layout, self.feature_gated.iter().map(|gated| {
+				let features = gated.features.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>();
+				(gated.package, gated.kind, format!("{:?} (with {})", gated.dependency, features.join(" or ")))
+			}))?;
+		}
+
+		if !self.unused_features.is_empty() {
+			writeln!(stdout, "unused features of dependencies:")?;
+
+			write_by_package(&mut stdout, self.layout, self.unused_features.iter().map(|unused| {
+				let features = unused.features.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>();
+				(unused.package, unused.kind, format!("{:?} ({})", unused.dependency, features.join(", ")))
+			}))?;
+		}
+
+		if !self.unused_default_features.is_empty() {
+			writeln!(stdout, "unneeded default features:")?;
+
+			write_by_package(&mut stdout, self.layout, self.unused_default_features.iter().map(|unused| {
+				let features = unused.features.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>();
+				(unused.package, unused.kind, format!("{:?} ({})", unused.dependency, features.join(", ")))
+			}))?;
+		}
+
+		for &(suggested, header, table) in &[
+			(dependency::Kind::Development, "used only by tests, benches or examples:", "dev-dependencies"),
+			(dependency::Kind::Build, "used only by build scripts:", "build-dependencies"),
+		] {
+			let mut misplaced = self.misplaced.iter().filter(|m| m.suggested == suggested).peekable();
+			if misplaced.peek().is_some() {
+				writeln!(stdout, "{}", header)?;
+
+				write_by_package(&mut stdout, self.layout, misplaced.map(|misplaced| {
+					let item = format!("{:?} (move it to `[{}]`)", misplaced.dependency, table);
+					(misplaced.package, misplaced.kind, item)
+				}))?;
+			}
+		}
+
+		if !self.target_specific.is_empty() {
+			writeln!(stdout, "used only on some platforms:")?;
+
+			write_by_package(&mut stdout, self.layout, self.target_specific.iter().map(|t| {
+				let table = match t.kind {
+					dependency::Kind::Normal => "dependencies",
+					dependency::Kind::Development => "dev-dependencies",
+					dependency::Kind::Build => "build-dependencies",
+				};
+				let item = format!("{:?} (move it to `[target.'cfg({})'.{}]`)", t.dependency, t.cfg, table);
+				(t.package, t.kind, item)
+			}))?;
+		}
+
+		if !self.backend_disagreements.is_empty() {
+			writeln!(stdout, "backends disagree:")?;
+
+			write_by_package(&mut stdout, self.layout, self.backend_disagreements.iter().map(|d| {
+				let unused_by = d.unused_by.iter().map(|b| format!("`{}`", b)).collect::<Vec<_>>();
+				let used_by = d
+					.used_by
+					.iter()
+					.map(|(b, locations)| match &**locations {
+						[] => format!("`{}`", b),
+						locations => format!("`{}` at {}", b, locations.join(", ")),
+					})
+					.collect::<Vec<_>>();
+				let item = format!("{:?} (unused by {}, used by {})", d.dependency, unused_by.join(", "), used_by.join(", "));
+				(d.package, d.kind, item)
+			}))?;
+		}
+
+		if !self.unnecessary_ignores.is_empty() {
+			writeln!(stdout, "unnecessary ignores:")?;
+
+			write_by_package(&mut stdout, self.layout, self.unnecessary_ignores.iter().map(|u| {
+				(u.package, u.kind, format!("{:?} (used, remove it from the ignored ones)", u.dependency))
+			}))?;
+		}
+
+		if !self.coverage.is_empty() {
+			writeln!(stdout, "not analyzed:")?;
+
+			for (member, OutcomeCoverage { skipped_targets, unevaluated_platforms, weakly_enabled }) in &self.coverage {
+				writeln!(stdout, "`{}`", member)?;
+				let mut sections = vec![(
+					"targets".to_owned(),
+					skipped_targets.iter().map(|t| format!("{} ({})", t.target, t.reason)).collect::<Vec<_>>(),
+				)];
+				for (platform, deps) in unevaluated_platforms {
+					sections.push((
+						format!("dependencies for `{}`", platform),
+						deps.iter().map(|d| format!("{:?}", d)).collect::<Vec<_>>(),
+					));
+				}
+				sections.push((
+					"optional dependencies not enabled".to_owned(),
+					weakly_enabled
+						.iter()
+						.map(|(dep, features)| {
+							let features = features.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>();
+							format!("{:?} (`{}?/..` in {} does not enable it)", dep, dep, features.join(", "))
+						})
+						.collect(),
+				));
+				write_tree(&mut stdout, self.layout.charset, &sections)?;
+			}
+		}
+
+		self.print_summary(&mut stdout)?;
+
+		for note in &self.notes {
+			write!(stdout, "{}", note)?;
+		}
+		if self.incomplete {
+			writeln!(stdout, "Note: The time budget (`--max-duration`) was exhausted. These results are incomplete.")?;
+		}
+		stdout.flush()
+	}
+
+	/// `--summary`: the numbers of the unused dependencies of each package by kind, and of the
+	/// ignored ones, with the totals.
+	fn print_summary(&self, mut stdout: impl Write) -> io::Result<()> {
+		if !self.layout.summary {
+			return Ok(());
+		}
+		let header = ["package", "normal", "dev", "build", "ignored"];
+		let mut rows = self
+			.unused_deps
+			.iter()
+			.map(|(package, deps)| {
+				let counts = [deps.normal.len(), deps.development.len(), deps.build.len(), deps.ignored.len()];
+				(format!("{} v{}", package.name(), package.version()), counts)
+			})
+			.collect::<Vec<_>>();
+		let mut total = [0; 4];
+		for (_, counts) in &rows {
+			for (total, count) in total.iter_mut().zip(counts) {
+				*total += count;
+			}
+		}
+		rows.push(("total".to_owned(), total));
+
+		let width = rows.iter().map(|(package, _)| package.len()).chain(iter::once(header[0].len())).max().unwrap_or(0);
+		writeln!(stdout, "summary:")?;
+		write!(stdout, "{:<width$}", header[0], width = width)?;
+		for title in &header[1..] {
+			write!(stdout, "  {:>7}", title)?;
+		}
+		writeln!(stdout)?;
+		for (package, counts) in rows {
+			write!(stdout, "{:<width$}", package, width = width)?;
+			for count in &counts {
+				write!(stdout, "  {:>7}", count)?;
+			}
+			writeln!(stdout)?;
+		}
+		Ok(())
+	}
+
+	fn print_json(&self, mut stdout: impl Write) -> io::Result<()> {
+		let json = serde_json::to_string(self).expect("should not fail");
+		writeln!(stdout, "{}", json)?;
+		stdout.flush()
+	}
+
+	/// The JSON Schema of [`print_json`](Self::print_json), also in `schema/outcome.json`.
+	fn print_schema(mut stdout: impl Write) -> io::Result<()> {
+		let schema = serde_json::to_string_pretty(&schemars::schema_for!(Outcome)).expect("should not fail");
+		writeln!(stdout, "{}", schema)?;
+		stdout.flush()
+	}
+}
+
+/// Writes `(package, kind, item)`s as trees like the ones of the unused dependencies.
+fn write_by_package<P: Ord + fmt::Display>(
+	mut stdout: impl Write,
+	layout: Layout,
+	items: impl Iterator<Item = (P, dependency::Kind, String)>,
+) -> io::Result<()> {
+	let mut by_package = BTreeMap::<_, BTreeMap<_, Vec<_>>>::new();
+	for (package, kind, item) in items {
+		by_package.entry(package).or_default().entry(kind).or_default().push(item);
+	}
+	for (package, by_kind) in by_package {
+		writeln!(stdout, "{}", layout.package(package))?;
+		let sections = by_kind.into_iter().map(|(kind, items)| (layout.table_name(kind), items)).collect::<Vec<_>>();
+		write_tree(&mut stdout, layout.charset, &sections)?;
+	}
+	Ok(())
 }
 
-#[derive(Debug, Default, Deserialize)]
-struct PackageMetadataCargoUdepsIgnore {
-	#[serde(default)]
-	normal: HashSet<String>,
-	#[serde(default)]
-	development: HashSet<String>,
-	#[serde(default)]
-	build: HashSet<String>,
+fn table_name(kind: dependency::Kind) -> &'static str {
+	match kind {
+		dependency::Kind::Normal => "dependencies",
+		dependency::Kind::Development => "dev-dependencies",
+		dependency::Kind::Build => "build-dependencies",
+	}
 }
 
-impl PackageMetadataCargoUdepsIgnore {
-	fn contains(&self, kind: dependency::Kind, name_in_toml: InternedString) -> bool {
-		match kind {
-			dependency::Kind::Normal => &self.normal,
-			dependency::Kind::Development => &self.development,
-			dependency::Kind::Build => &self.build,
+/// Writes the unused dependencies of each package as `--group-by` and `--sort` lay them out.
+fn write_unused_deps(
+	mut stdout: impl Write,
+	unused_deps: &BTreeMap<PackageId, OutcomeUnusedDeps>,
+	layout: Layout,
+) -> io::Result<()> {
+	if layout.aggregate {
+		return write_aggregated_deps(stdout, unused_deps, layout);
+	}
+	let mut groups = match layout.group_by {
+		GroupBy::Package => unused_deps
+			.iter()
+			.map(|(package, deps)| {
+				let sections = deps.sections().into_iter().map(|(kind, items)| (layout.table_name(kind), items)).collect();
+				(layout.package(package), sections)
+			})
+			.collect(),
+		GroupBy::Dependency => {
+			let mut by_dependency = BTreeMap::<_, BTreeMap<_, Vec<_>>>::new();
+			for (package, deps) in unused_deps {
+				for (kind, dependency) in deps.iter() {
+					by_dependency
+						.entry(dependency)
+						.or_default()
+						.entry(kind)
+						.or_default()
+						.push(deps.describe(dependency, &layout.package(package)));
+				}
+			}
+			by_dependency
+				.into_iter()
+				.map(|(dependency, by_kind)| {
+					let sections = by_kind.into_iter().map(|(kind, items)| (layout.table_name(kind), items)).collect();
+					(format!("{:?}", dependency), sections)
+				})
+				.collect()
 		}
-		.contains(&*name_in_toml)
+		GroupBy::Kind => [dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build]
+			.iter()
+			.map(|&kind| {
+				let sections = unused_deps
+					.iter()
+					.map(|(package, deps)| {
+						let items = deps.iter().filter(|&(k, _)| k == kind).map(|(_, d)| deps.describe(d, &format!("{:?}", d)));
+						(layout.package(package), items.collect::<Vec<_>>())
+					})
+					.collect::<Vec<_>>();
+				(format!("`[{}]`", layout.table_name(kind)), sections)
+			})
+			.filter(|(_, sections)| sections.iter().any(|(_, items)| !items.is_empty()))
+			.collect::<Vec<_>>(),
+	};
+	if layout.sort == SortBy::Count {
+		// Stable, so that the ties stay sorted by name.
+		groups.sort_by_key(|(_, sections)| cmp::Reverse(sections.iter().map(|(_, items)| items.len()).sum::<usize>()));
 	}
+	for (header, sections) in groups {
+		writeln!(stdout, "{}", header)?;
+		write_tree(&mut stdout, layout.charset, &sections)?;
+	}
+	Ok(())
 }
 
-#[derive(Default, Debug, Serialize)]
-struct Outcome {
-	success: bool,
-	unused_deps: BTreeMap<PackageId, OutcomeUnusedDeps>,
-	note: Option<String>,
+/// Writes a line for each unused dependency with the packages declaring it, e.g.
+/// ``"log" is unused in 2 packages: `a`, `b` (dev)``. The ones unused in the most packages come
+/// first.
+fn write_aggregated_deps(
+	mut stdout: impl Write,
+	unused_deps: &BTreeMap<PackageId, OutcomeUnusedDeps>,
+	layout: Layout,
+) -> io::Result<()> {
+	let mut by_dependency = BTreeMap::<_, BTreeMap<_, Vec<_>>>::new();
+	for (&package, deps) in unused_deps {
+		for (kind, dependency) in deps.iter() {
+			by_dependency.entry(dependency).or_default().entry(package).or_default().push(kind);
+		}
+	}
+	let mut by_dependency = by_dependency.into_iter().collect::<Vec<_>>();
+	by_dependency.sort_by_key(|(_, packages)| cmp::Reverse(packages.len()));
+	for (dependency, packages) in by_dependency {
+		let names = packages
+			.iter()
+			.map(|(package, kinds)| {
+				let name = layout.package(package.name());
+				if *kinds == [dependency::Kind::Normal] {
+					return name;
+				}
+				let kinds = kinds
+					.iter()
+					.map(|kind| match kind {
+						dependency::Kind::Normal => "normal",
+						dependency::Kind::Development => "dev",
+						dependency::Kind::Build => "build",
+					})
+					.collect::<Vec<_>>();
+				format!("{} ({})", name, kinds.join(", "))
+			})
+			.collect::<Vec<_>>();
+		let noun = if names.len() == 1 { "package" } else { "packages" };
+		writeln!(stdout, "{:?} is unused in {} {}: {}", dependency, names.len(), noun, names.join(", "))?;
+	}
+	Ok(())
 }
 
-impl Outcome {
-	fn print(&self, output: OutputKind, stdout: impl Write) -> io::Result<()> {
-		match output {
-			OutputKind::Human => self.print_human(stdout),
-			OutputKind::Json => self.print_json(stdout),
+/// Writes `sections` as a tree below a header line written by the caller. Empty sections are
+/// omitted.
+fn write_tree(mut stdout: impl Write, charset: Charset, sections: &[(String, Vec<String>)]) -> io::Result<()> {
+	let (vertical, branch, last_branch) = match charset {
+		Charset::Ascii => ("|   ", "|-- ", "`-- "),
+		_ => ("│    ", "├─── ", "└─── "),
+	};
+	let blank = " ".repeat(vertical.chars().count());
+	let mut sections = sections.iter().filter(|(_, items)| !items.is_empty()).peekable();
+	while let Some((title, items)) = sections.next() {
+		let (edge, joint) = if sections.peek().is_some() {
+			(vertical, branch)
+		} else {
+			(&*blank, last_branch)
+		};
+		writeln!(stdout, "{}{}", joint, title)?;
+		let mut items = items.iter().peekable();
+		while let Some(item) = items.next() {
+			let joint = if items.peek().is_some() {
+				branch
+			} else {
+				last_branch
+			};
+			writeln!(stdout, "{}{}{}", edge, joint, item)?;
 		}
 	}
+	Ok(())
+}
 
-	fn print_human(&self, mut stdout: impl Write) -> io::Result<()> {
-		if self.success {
-			writeln!(stdout, "All deps seem to have been used.")?;
-		} else {
-			writeln!(stdout, "unused dependencies:")?;
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+struct OutcomeCoverage {
+	skipped_targets: Vec<OutcomeSkippedTarget>,
+	/// Platform-specific dependencies that never reached rustc, by `target.<platform>` table.
+	unevaluated_platforms: BTreeMap<String, BTreeSet<InternedString>>,
+	/// Optional dependencies which are not enabled, but whose features the enabled features
+	/// refer to with `dep?/feature`. The values are the enabled features.
+	#[serde(default)]
+	weakly_enabled: BTreeMap<InternedString, BTreeSet<String>>,
+}
 
-			for (member, OutcomeUnusedDeps { normal, development, build, .. }) in &self.unused_deps {
-				fn edge_and_joint(p: bool) -> (char, char) {
-					if p {
-						(' ', '└')
-					} else {
-						('│', '├')
-					}
-				}
+impl OutcomeCoverage {
+	fn is_empty(&self) -> bool {
+		self.skipped_targets.is_empty() && self.unevaluated_platforms.is_empty() && self.weakly_enabled.is_empty()
+	}
+}
 
-				writeln!(stdout, "`{}`", member)?;
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct OutcomeSkippedTarget {
+	target: String,
+	reason: SkipReason,
+}
 
-				for (deps, (edge, joint), prefix) in &[
-					(normal, edge_and_joint(development.is_empty() && build.is_empty()), ""),
-					(development, edge_and_joint(build.is_empty()), "dev-"),
-					(build, (' ', '└'), "build-"),
-				] {
-					if !deps.is_empty() {
-						writeln!(stdout, "{}─── {}dependencies", joint, prefix)?;
-						let mut deps = deps.iter().peekable();
-						while let Some(dep) = deps.next() {
-							let joint = if deps.peek().is_some() {
-								'├'
-							} else {
-								'└'
-							};
-							writeln!(stdout, "{}    {}─── {:?}", edge, joint, dep)?;
-						}
-					}
-				}
-			}
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum SkipReason {
+	RequiredFeatures { missing: Vec<String> },
+	NotSelected,
+	Unfinished,
+}
 
-			if let Some(note) = &self.note {
-				write!(stdout, "{}", note)?;
+impl fmt::Display for SkipReason {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SkipReason::RequiredFeatures { missing } => {
+				write!(f, "required features not enabled: {}", missing.join(", "))
 			}
+			SkipReason::NotSelected => write!(f, "not selected"),
+			SkipReason::Unfinished => write!(f, "not finished in time"),
 		}
-		stdout.flush()
-	}
-
-	fn print_json(&self, mut stdout: impl Write) -> io::Result<()> {
-		let json = serde_json::to_string(self).expect("should not fail");
-		writeln!(stdout, "{}", json)?;
-		stdout.flush()
 	}
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 struct OutcomeUnusedDeps {
 	manifest_path: String,
 	normal: BTreeSet<InternedString>,
 	development: BTreeSet<InternedString>,
 	build: BTreeSet<InternedString>,
+	/// The packages of the renamed dependencies (`name = { package = ".." }`), by the names in
+	/// `Cargo.toml`.
+	#[serde(default)]
+	renamed: BTreeMap<InternedString, String>,
+	/// The `target.<platform>` tables of the dependencies declared only in them, by the names in
+	/// `Cargo.toml`.
+	#[serde(default)]
+	platforms: BTreeMap<InternedString, String>,
+	/// The commits which added the declarations (`--blame`), by the names in `Cargo.toml`.
+	#[serde(default)]
+	blame: BTreeMap<InternedString, Blame>,
+	/// The unused dependencies which are ignored, and not in `normal`, `development` or `build`.
+	#[serde(default)]
+	ignored: BTreeSet<InternedString>,
 }
 
 impl OutcomeUnusedDeps {
-	fn new(manifest_path: &Path) -> CargoResult<Self> {
+	fn new(manifest_path: &Path) -> Fallible<Self> {
 		let manifest_path = manifest_path
 			.to_str()
 			.ok_or_else(|| failure::format_err!("{:?} is not valid utf-8", manifest_path))?
@@ -888,9 +4614,48 @@ impl OutcomeUnusedDeps {
 			normal: BTreeSet::new(),
 			development: BTreeSet::new(),
 			build: BTreeSet::new(),
+			renamed: BTreeMap::new(),
+			platforms: BTreeMap::new(),
+			blame: BTreeMap::new(),
+			ignored: BTreeSet::new(),
 		})
 	}
 
+	fn is_empty(&self) -> bool {
+		self.normal.is_empty() && self.development.is_empty() && self.build.is_empty()
+	}
+
+	fn iter(&self) -> impl Iterator<Item = (dependency::Kind, InternedString)> + '_ {
+		let normal = self.normal.iter().map(|&d| (dependency::Kind::Normal, d));
+		let development = self.development.iter().map(|&d| (dependency::Kind::Development, d));
+		let build = self.build.iter().map(|&d| (dependency::Kind::Build, d));
+		normal.chain(development).chain(build)
+	}
+
+	/// The sections of [`write_tree`], one for each kind.
+	fn sections(&self) -> Vec<(dependency::Kind, Vec<String>)> {
+		[
+			(&self.normal, dependency::Kind::Normal),
+			(&self.development, dependency::Kind::Development),
+			(&self.build, dependency::Kind::Build),
+		]
+		.iter()
+		.map(|&(deps, kind)| (kind, deps.iter().map(|&d| self.describe(d, &format!("{:?}", d))).collect()))
+		.collect()
+	}
+
+	/// `item` followed by the notes on `dependency`, e.g. the package it renames.
+	fn describe(&self, dependency: InternedString, item: &str) -> String {
+		let platform = self.platforms.get(&dependency).map(|p| format!("for `{}`", p));
+		let blame = self.blame.get(&dependency).map(Blame::describe);
+		let notes = self.renamed.get(&dependency).cloned().into_iter().chain(platform).chain(blame).collect::<Vec<_>>();
+		if notes.is_empty() {
+			item.to_owned()
+		} else {
+			format!("{} ({})", item, notes.join(", "))
+		}
+	}
+
 	fn unused_deps_mut(&mut self, kind: dependency::Kind) -> &mut BTreeSet<InternedString> {
 		match kind {
 			dependency::Kind::Normal => &mut self.normal,
@@ -904,10 +4669,38 @@ impl OutcomeUnusedDeps {
 enum OutputKind {
 	Human,
 	Json,
+	Sarif,
+	Github,
+	Junit,
+	Codeclimate,
+	Teamcity,
+	Azure,
+	CiAuto,
+	Markdown,
+	Html,
+	Dot,
+	Patch,
+	/// `{package} {kind} {dependency}` lines.
+	Short,
 }
 
 impl OutputKind {
-	const VARIANTS: &'static [&'static str] = &["human", "json"];
+	const VARIANTS: &'static [&'static str] = &["human", "json", "sarif", "github", "junit", "codeclimate", "teamcity", "azure", "ci-auto", "markdown", "html", "dot", "patch", "short"];
+
+	/// Picks the annotation format of the CI system this process runs on.
+	fn detect_ci() -> Self {
+		let ci_systems = [
+			("GITHUB_ACTIONS", Self::Github),
+			("GITLAB_CI", Self::Codeclimate),
+			("TF_BUILD", Self::Azure),
+			("TEAMCITY_VERSION", Self::Teamcity),
+			("JENKINS_URL", Self::Junit),
+		];
+		ci_systems
+			.iter()
+			.find(|(var, _)| env::var_os(var).is_some())
+			.map_or(Self::Human, |&(_, kind)| kind)
+	}
 }
 
 impl FromStr for OutputKind {
@@ -917,28 +4710,437 @@ impl FromStr for OutputKind {
 		match s {
 			"human" => Ok(Self::Human),
 			"json" => Ok(Self::Json),
-			_ => Err(r#"expected "human" or "json" (you should not see this message)"#),
+			"sarif" => Ok(Self::Sarif),
+			"github" => Ok(Self::Github),
+			"junit" => Ok(Self::Junit),
+			"codeclimate" => Ok(Self::Codeclimate),
+			"teamcity" => Ok(Self::Teamcity),
+			"azure" => Ok(Self::Azure),
+			"ci-auto" => Ok(Self::CiAuto),
+			"markdown" => Ok(Self::Markdown),
+			"html" => Ok(Self::Html),
+			"dot" => Ok(Self::Dot),
+			"patch" => Ok(Self::Patch),
+			"short" => Ok(Self::Short),
+			_ => Err(r#"expected one of `OutputKind::VARIANTS` (you should not see this message)"#),
 		}
 	}
 }
 
-trait ShellExt {
-    fn info<T: fmt::Display>(&mut self, message: T) -> CargoResult<()>;
+/// The findings `--deny` makes fail.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Lint {
+	NeverEnabled,
+	FeatureGated,
+	/// Including the unneeded default features.
+	UnusedFeatures,
+	Misplaced,
+	TargetSpecific,
+	UnnecessaryIgnore,
 }
 
-impl ShellExt for Shell {
-	fn info<T: fmt::Display>(&mut self, message: T) -> CargoResult<()> {
-		self.print_ansi(
-			format!(
-				"{} {}\n",
-				if self.supports_color() {
-					Colour::Cyan.bold().paint("info:").to_string()
-				} else {
-					"info:".to_owned()
-				},
-				message,
-			)
-			.as_ref(),
-		)
-    }
+impl Lint {
+	const VARIANTS: &'static [&'static str] =
+		&["never-enabled", "feature-gated", "unused-features", "misplaced", "target-specific", "unnecessary-ignore"];
+}
+
+impl FromStr for Lint {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"never-enabled" => Ok(Self::NeverEnabled),
+			"feature-gated" => Ok(Self::FeatureGated),
+			"unused-features" => Ok(Self::UnusedFeatures),
+			"misplaced" => Ok(Self::Misplaced),
+			"target-specific" => Ok(Self::TargetSpecific),
+			"unnecessary-ignore" => Ok(Self::UnnecessaryIgnore),
+			_ => Err(r#"expected one of `Lint::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+/// Where `--timings` shows or writes the timings.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TimingsFormat {
+	Human,
+	Json,
+	Html,
+}
+
+impl TimingsFormat {
+	const VARIANTS: &'static [&'static str] = &["human", "json", "html"];
+}
+
+impl FromStr for TimingsFormat {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"human" => Ok(Self::Human),
+			"json" => Ok(Self::Json),
+			"html" => Ok(Self::Html),
+			_ => Err(r#"expected one of `TimingsFormat::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+/// How the human output lays out the unused dependencies.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+	group_by: GroupBy,
+	sort: SortBy,
+	/// `--aggregate`, which overrides `group_by` and `sort`.
+	aggregate: bool,
+	summary: bool,
+	/// Either `Utf8` or `Ascii`.
+	charset: Charset,
+	/// `--color`, on stdout only.
+	color: bool,
+}
+
+impl Layout {
+	fn paint(self, style: Style, s: &str) -> String {
+		if self.color {
+			style.paint(s).to_string()
+		} else {
+			s.to_owned()
+		}
+	}
+
+	/// e.g. `` `a v0.1.0 (/path/to/a)` `` in bold.
+	fn package(self, package: impl fmt::Display) -> String {
+		self.paint(Style::new().bold(), &format!("`{}`", package))
+	}
+
+	/// e.g. `dev-dependencies`, in the color of the kind.
+	fn table_name(self, kind: dependency::Kind) -> String {
+		let colour = match kind {
+			dependency::Kind::Normal => Colour::Yellow,
+			dependency::Kind::Development => Colour::Cyan,
+			dependency::Kind::Build => Colour::Purple,
+		};
+		self.paint(colour.normal(), table_name(kind))
+	}
+}
+
+impl Default for Layout {
+	fn default() -> Self {
+		Self {
+			group_by : GroupBy::Package,
+			sort : SortBy::Name,
+			aggregate : false,
+			summary : false,
+			charset : Charset::Auto.resolve(),
+			color : false,
+		}
+	}
+}
+
+/// The headers of `--group-by`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum GroupBy {
+	Package,
+	Dependency,
+	/// The table, e.g. `[dev-dependencies]`.
+	Kind,
+}
+
+impl GroupBy {
+	const VARIANTS: &'static [&'static str] = &["package", "dependency", "kind"];
+}
+
+impl FromStr for GroupBy {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"package" => Ok(Self::Package),
+			"dependency" => Ok(Self::Dependency),
+			"kind" => Ok(Self::Kind),
+			_ => Err(r#"expected one of `GroupBy::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SortBy {
+	Name,
+	/// The number of unused dependencies in the group, most first.
+	Count,
+}
+
+impl SortBy {
+	const VARIANTS: &'static [&'static str] = &["name", "count"];
+}
+
+impl FromStr for SortBy {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"name" => Ok(Self::Name),
+			"count" => Ok(Self::Count),
+			_ => Err(r#"expected one of `SortBy::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+/// The characters `--charset` draws the trees with.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Charset {
+	Auto,
+	Utf8,
+	Ascii,
+}
+
+impl Charset {
+	const VARIANTS: &'static [&'static str] = &["auto", "utf8", "ascii"];
+
+	/// Resolves `auto`. ASCII is chosen on the Windows consoles other than Windows Terminal, and
+	/// when the locale (`LC_ALL`, `LC_CTYPE` or `LANG`) is set to a non-UTF-8 one such as `C`.
+	fn resolve(self) -> Self {
+		if self != Self::Auto {
+			return self;
+		}
+		if cfg!(windows) && env::var_os("WT_SESSION").is_none() {
+			return Self::Ascii;
+		}
+		let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+			.iter()
+			.filter_map(|name| env::var(name).ok())
+			.find(|value| !value.is_empty());
+		match locale {
+			Some(locale) if !locale.to_lowercase().replace('-', "").contains("utf8") => Self::Ascii,
+			_ => Self::Utf8,
+		}
+	}
+}
+
+impl FromStr for Charset {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"auto" => Ok(Self::Auto),
+			"utf8" => Ok(Self::Utf8),
+			"ascii" => Ok(Self::Ascii),
+			_ => Err(r#"expected one of `Charset::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+/// How `--paths` writes the paths of the packages and their manifests in the report.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PathsStyle {
+	Absolute,
+	/// Relative to the workspace root. The packages outside it keep their absolute paths.
+	Relative,
+}
+
+impl PathsStyle {
+	const VARIANTS: &'static [&'static str] = &["absolute", "relative"];
+}
+
+impl FromStr for PathsStyle {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"absolute" => Ok(Self::Absolute),
+			"relative" => Ok(Self::Relative),
+			_ => Err(r#"expected one of `PathsStyle::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Backend {
+	Auto,
+	SaveAnalysis,
+	Lint,
+	Source,
+	RustdocJson,
+	Driver,
+}
+
+impl Backend {
+	const VARIANTS: &'static [&'static str] = &["auto", "save-analysis", "lint", "source", "rustdoc-json", "driver"];
+
+	/// `save-analysis`, which recent nightlies no longer have, if `rustc` supports it. `lint`
+	/// otherwise.
+	fn detect(config :&Config) -> Fallible<Self> {
+		let mut rustc = ProcessBuilder::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()));
+		rustc.cwd(config.cwd());
+		// `-Z` is rejected by stable and beta.
+		let supported = rustc
+			.args(&["-Z", "help"])
+			.exec_with_output()
+			.map_or(false, |o| String::from_utf8_lossy(&o.stdout).contains("save-analysis"));
+		let backend = if supported { Self::SaveAnalysis } else { Self::Lint };
+		config.shell().verbose(|shell| shell.info(format_args!("Using `--backend {}`", backend)))?;
+		Ok(backend)
+	}
+
+	fn collector(self) -> Box<dyn UsageCollector> {
+		match self {
+			Self::Auto => unreachable!("should be detected"),
+			Self::SaveAnalysis => Box::new(usage::SaveAnalysis),
+			Self::Lint => Box::new(usage::Lint),
+			Self::Source => Box::new(usage::Source),
+			Self::RustdocJson => Box::new(usage::RustdocJson),
+			Self::Driver => Box::new(usage::Driver::new()),
+		}
+	}
+}
+
+impl fmt::Display for Backend {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Auto => "auto",
+			Self::SaveAnalysis => "save-analysis",
+			Self::Lint => "lint",
+			Self::Source => "source",
+			Self::RustdocJson => "rustdoc-json",
+			Self::Driver => "driver",
+		})
+	}
+}
+
+impl FromStr for Backend {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
+		match s {
+			"auto" => Ok(Self::Auto),
+			"save-analysis" => Ok(Self::SaveAnalysis),
+			"lint" => Ok(Self::Lint),
+			"source" => Ok(Self::Source),
+			"rustdoc-json" => Ok(Self::RustdocJson),
+			"driver" => Ok(Self::Driver),
+			_ => Err(r#"expected one of `Backend::VARIANTS` (you should not see this message)"#),
+		}
+	}
+}
+
+/// Opens `path` in the same way as `cargo doc --open`.
+fn open_report(path :&Path, shell :&mut Shell) -> Fallible<()> {
+	match env::var_os("BROWSER") {
+		Some(browser) => {
+			if let Err(e) = std::process::Command::new(&browser).arg(path).status() {
+				shell.warn(format!("Couldn't open the report with {}: {}", browser.to_string_lossy(), e))?;
+			}
+		}
+		None => {
+			if let Err(e) = opener::open(path) {
+				shell.warn(format!("Couldn't open the report: {}", e))?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Parses `90`, `90s`, `15m` or `1h`.
+fn parse_duration(s :&str) -> std::result::Result<Duration, String> {
+	let (value, unit) = match s.find(|c :char| !c.is_ascii_digit()) {
+		Some(i) => s.split_at(i),
+		None => (s, "s"),
+	};
+	let value = value.parse::<u64>().map_err(|_| format!("invalid duration: {:?}", s))?;
+	match unit {
+		"s" => Ok(Duration::from_secs(value)),
+		"m" => Ok(Duration::from_secs(value * 60)),
+		"h" => Ok(Duration::from_secs(value * 60 * 60)),
+		_ => Err(format!("invalid duration: {:?} (expected a unit of `s`, `m` or `h`)", s)),
+	}
+}
+
+/// `K/N`: the `K`-th of `N` partitions of the workspace members.
+///
+/// Members are assigned by a hash of their name, so adding a member does not move the others.
+#[derive(Clone, Copy, Debug)]
+struct Shard {
+	index: u64,
+	count: u64,
+}
+
+impl Shard {
+	fn contains(self, package: &Package) -> bool {
+		fnv1a(package.name().as_bytes()) % self.count == self.index - 1
+	}
+}
+
+/// FNV-1a. Unlike `DefaultHasher`, it is guaranteed to be stable across Rust versions.
+/// Drops the comma-separated feature sets which contain another one.
+fn minimal_feature_sets(sets :&[&str]) -> Vec<String> {
+	let parse = |set :&str| set.split(',').filter(|f| !f.is_empty()).map(ToOwned::to_owned).collect::<BTreeSet<_>>();
+	let sets = sets.iter().map(|s| parse(s)).collect::<Vec<_>>();
+	let mut minimal = sets
+		.iter()
+		.filter(|set| !sets.iter().any(|other| other.len() < set.len() && other.is_subset(set)))
+		.map(|set| set.iter().cloned().collect::<Vec<_>>().join(","))
+		.collect::<Vec<_>>();
+	minimal.sort();
+	minimal.dedup();
+	minimal
+}
+
+fn fnv1a(bytes :&[u8]) -> u64 {
+	bytes
+		.iter()
+		.fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(0x100_0000_01b3))
+}
+
+impl FromStr for Shard {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, String> {
+		let err = || format!("expected `K/N` with 1 <= K <= N, got {:?}", s);
+		let mut split = s.splitn(2, '/');
+		let (index, count) = match (split.next(), split.next()) {
+			(Some(index), Some(count)) => (index, count),
+			_ => return Err(err()),
+		};
+		let index = index.trim().parse::<u64>().map_err(|_| err())?;
+		let count = count.trim().parse::<u64>().map_err(|_| err())?;
+		if index == 0 || index > count {
+			return Err(err());
+		}
+		Ok(Self { index, count })
+	}
+}
+
+impl fmt::Display for Shard {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}/{}", self.index, self.count)
+	}
+}
+
+/// `[KIND:]NAME` of `--ignore` and `--only`.
+#[derive(Clone, Debug)]
+struct DependencySpec {
+	/// Every kind if `None`.
+	kind: Option<dependency::Kind>,
+	name: String,
+}
+
+impl DependencySpec {
+	fn matches(&self, kind: dependency::Kind, name_in_toml: InternedString) -> bool {
+		self.kind.map_or(true, |k| k == kind) && self.name == *name_in_toml
+	}
+}
+
+impl FromStr for DependencySpec {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, String> {
+		let (kind, name) = match s.split_once(':') {
+			Some((kind, name)) => (Some(kind.parse()?), name),
+			None => (None, s),
+		};
+		if name.is_empty() {
+			return Err(format!("expected a dependency name in {:?}", s));
+		}
+		Ok(Self { kind, name : name.to_owned() })
+	}
 }
@@ -1,11 +1,15 @@
-use std::{env, io};
+use std::{env, io, process};
 
-use cargo::core::shell::Shell;
+use cargo_udeps::{Config, Shell};
 
 fn main() {
-	let mut config = cargo::Config::default()
-		.unwrap_or_else(|e| cargo::exit_with_error(e.into(), &mut Shell::new()));
-	if let Err(err) = cargo_udeps::run(env::args_os(), &mut config, io::stdout()) {
-		cargo::exit_with_error(err, &mut config.shell());
+	if let Some(code) = cargo_udeps::run_as_rustc_wrapper() {
+		process::exit(code);
+	}
+	let mut config = Config::default()
+		.unwrap_or_else(|e| cargo_udeps::exit_with_error(e, &mut Shell::new()));
+	match cargo_udeps::run(env::args_os(), &mut config, io::stdout()) {
+		Ok(code) => process::exit(code),
+		Err(err) => cargo_udeps::exit_with_error(err, &mut config.shell()),
 	}
 }
@@ -0,0 +1,178 @@
+//! Locating dependency declarations in `Cargo.toml`.
+//!
+//! This scans the lines instead of parsing TOML since no parser available to us reports positions.
+//! Inline tables such as `dependencies = { .. }` are not supported.
+
+use std::ops::Range;
+
+use crate::dependency;
+
+/// The names of the tables declaring dependencies of `kind`, with or without a `target.<platform>`
+/// prefix.
+pub(crate) fn tables(kind :dependency::Kind) -> &'static [&'static str] {
+	match kind {
+		dependency::Kind::Normal => &["dependencies"],
+		dependency::Kind::Development => &["dev-dependencies", "dev_dependencies"],
+		dependency::Kind::Build => &["build-dependencies", "build_dependencies"],
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct Declaration {
+	/// The 1-based first line.
+	pub(crate) line :usize,
+	/// The 1-based last line.
+	pub(crate) end_line :usize,
+	/// The byte range of the lines, including the last line break.
+	pub(crate) span :Range<usize>,
+}
+
+/// Finds the 1-based line declaring `name` as a dependency of `kind` in `manifest`.
+pub(crate) fn dependency_line(manifest :&str, kind :dependency::Kind, name :&str) -> Option<usize> {
+	find_dependency(manifest, kind, name).first().map(|d| d.line)
+}
+
+/// Finds every declaration of `name` as a dependency of `kind` in `manifest`, e.g. in both
+/// `[dependencies]` and `[target.'cfg(unix)'.dependencies]`.
+pub(crate) fn find_dependency(manifest :&str, kind :dependency::Kind, name :&str) -> Vec<Declaration> {
+	let tables = tables(kind);
+	let lines = lines(manifest);
+	let is_header = |i :usize| lines[i].1.trim_start().starts_with('[');
+	let declaration = |start :usize, end :usize| Declaration {
+		line : start + 1,
+		end_line : end,
+		span : lines[start].0..lines[end - 1].0 + lines[end - 1].1.len(),
+	};
+
+	let mut declarations = vec![];
+	let mut in_table = false;
+	let mut i = 0;
+	while i < lines.len() {
+		let line = lines[i].1.trim();
+		if is_header(i) {
+			let header = line.split('#').next().unwrap_or("").trim().trim_matches(|c| c == '[' || c == ']').trim();
+			let matches = |suffix :&str| header == suffix || header.ends_with(&format!(".{}", suffix));
+			if tables.iter().any(|t| matches(&format!("{}.{}", t, name))) {
+				// `[dependencies.name]` lasts until the next header. Trailing blank lines and
+				// comments are left for the next table.
+				let mut end = (i + 1..lines.len()).find(|&j| is_header(j)).unwrap_or(lines.len());
+				while end > i + 1 && is_blank_or_comment(lines[end - 1].1) {
+					end -= 1;
+				}
+				declarations.push(declaration(i, end));
+				in_table = false;
+				i = end;
+				continue;
+			}
+			in_table = tables.iter().any(|t| matches(t));
+		} else if in_table {
+			let key = line.split(|c| c == '=' || c == '.').next().unwrap_or("").trim();
+			if key.trim_matches('"') == name {
+				// Arrays in the value may span multiple lines.
+				let mut depth = bracket_depth(line);
+				let mut end = i + 1;
+				while depth > 0 && end < lines.len() {
+					depth += bracket_depth(lines[end].1);
+					end += 1;
+				}
+				declarations.push(declaration(i, end));
+				i = end;
+				continue;
+			}
+		}
+		i += 1;
+	}
+	declarations
+}
+
+/// Rewrites `declaration` (the lines of a [`Declaration`]) with `default-features = false`, or
+/// returns `None` if it cannot be done by editing the declaration alone.
+pub(crate) fn without_default_features(declaration :&str) -> Option<String> {
+	if declaration.contains("default-features") || declaration.contains("default_features") {
+		return None;
+	}
+	let first = declaration.lines().next()?;
+	let key = first.split('=').next().unwrap_or("").trim();
+	if first.trim_start().starts_with('[') || key.contains('.') {
+		// `[dependencies.name]` or `name.version = ".."`
+		let prefix = if key.contains('.') && !first.trim_start().starts_with('[') {
+			format!("{}.", key.split('.').next().unwrap_or("").trim())
+		} else {
+			"".to_owned()
+		};
+		let separator = if declaration.ends_with('\n') { "" } else { "\n" };
+		return Some(format!("{}{}{}default-features = false\n", declaration, separator, prefix));
+	}
+	// `workspace = true` cannot turn off the default features of `[workspace.dependencies]`.
+	if declaration.contains("workspace") {
+		return None;
+	}
+	let value_start = declaration.find('=')? + 1;
+	let value = declaration[value_start..].trim_start();
+	let value_start = declaration.len() - value.len();
+	match value.chars().next()? {
+		quote @ '"' | quote @ '\'' => {
+			let value_end = value_start + 1 + value[1..].find(quote)? + 1;
+			Some(format!(
+				"{}{{ version = {}, default-features = false }}{}",
+				&declaration[..value_start],
+				&declaration[value_start..value_end],
+				&declaration[value_end..],
+			))
+		}
+		'{' => {
+			let brace = declaration.rfind('}')?;
+			let inner = declaration[value_start + 1..brace].trim_end();
+			let inner_end = value_start + 1 + inner.len();
+			let separator = if inner.trim().is_empty() || inner.ends_with(',') { " " } else { ", " };
+			Some(format!(
+				"{}{}default-features = false{}",
+				&declaration[..inner_end],
+				separator,
+				&declaration[inner_end..],
+			))
+		}
+		_ => None,
+	}
+}
+
+/// Splits `s` into `(offset, line)`s, keeping the line breaks.
+pub(crate) fn lines(s :&str) -> Vec<(usize, &str)> {
+	let mut lines = vec![];
+	let mut start = 0;
+	while start < s.len() {
+		let end = s[start..].find('\n').map_or(s.len(), |i| start + i + 1);
+		lines.push((start, &s[start..end]));
+		start = end;
+	}
+	lines
+}
+
+fn is_blank_or_comment(line :&str) -> bool {
+	let line = line.trim();
+	line.is_empty() || line.starts_with('#')
+}
+
+/// The number of brackets and braces opened but not closed in `line`.
+fn bracket_depth(line :&str) -> isize {
+	let mut depth = 0;
+	let mut quote = None;
+	let mut escaped = false;
+	for c in line.chars() {
+		match (quote, c) {
+			(Some('"'), '\\') if !escaped => {
+				escaped = true;
+				continue;
+			}
+			(Some(q), c) if c == q && !escaped => quote = None,
+			(Some(_), _) => {}
+			(None, '"') | (None, '\'') => quote = Some(c),
+			(None, '#') => break,
+			(None, '[') | (None, '{') => depth += 1,
+			(None, ']') | (None, '}') => depth -= 1,
+			(None, _) => {}
+		}
+		escaped = false;
+	}
+	depth
+}
@@ -0,0 +1,466 @@
+//! The workspace and its dependency graph as `cargo metadata` describes them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{Fallible, ResultExt as _};
+use serde::Deserialize;
+use toml_edit::{Document, Item};
+
+use crate::dependency;
+use crate::interning::InternedString;
+use crate::package_id::PackageId;
+use crate::Config;
+
+pub(crate) struct Workspace {
+	root :PathBuf,
+	target_dir :PathBuf,
+	/// The IDs in the output of `cargo metadata`.
+	ids :HashMap<String, PackageId>,
+	members :Vec<PackageId>,
+	default_members :Vec<PackageId>,
+	/// The package of the manifest, unless it is virtual.
+	current :Option<PackageId>,
+	packages :HashMap<PackageId, Package>,
+	resolve :HashMap<PackageId, Node>,
+	/// `[workspace.metadata]`
+	metadata :Option<serde_json::Value>,
+	/// The version of the feature resolver, e.g. `2`.
+	resolver :u32,
+}
+
+impl Workspace {
+	/// Runs `cargo metadata` with `args` (e.g. `--features`).
+	pub(crate) fn load(config :&Config, args :&[OsString]) -> Fallible<Self> {
+		let mut cargo = config.cargo("metadata");
+		cargo.args(&["--format-version", "1"]).args(args);
+		let output = cargo.exec_with_output()?;
+		config.shell().err().write_all(&output.stderr)?;
+		let metadata = serde_json::from_slice::<Metadata>(&output.stdout)
+			.with_context(|_| "could not parse the output of `cargo metadata`")?;
+
+		let ids = metadata
+			.packages
+			.iter()
+			.map(|p| (p.id.clone(), p.package_id()))
+			.collect::<HashMap<_, _>>();
+		let id = |id :&String| {
+			ids.get(id).copied().ok_or_else(|| failure::format_err!("unknown package in `cargo metadata`: {}", id))
+		};
+		let members = metadata.workspace_members.iter().map(id).collect::<Fallible<Vec<_>>>()?;
+		let default_members = metadata
+			.workspace_default_members
+			.as_ref()
+			.map(|default_members| default_members.iter().map(id).collect::<Fallible<Vec<_>>>())
+			.transpose()?;
+		let resolve = metadata.resolve.ok_or_else(|| failure::err_msg("`cargo metadata` did not resolve the dependencies"))?;
+		let current = resolve.root.as_ref().map(id).transpose()?;
+		let resolve = resolve
+			.nodes
+			.into_iter()
+			.map(|node| {
+				let dependencies = node.dependencies.iter().map(id).collect::<Fallible<_>>()?;
+				let deps = node
+					.deps
+					.iter()
+					.map(|dep| {
+						let kinds = dep.dep_kinds.iter().map(|k| parse_kind(k.kind.as_deref())).collect();
+						Ok(NodeDep { name : dep.name.clone(), pkg : id(&dep.pkg)?, kinds })
+					})
+					.collect::<Fallible<_>>()?;
+				Ok((id(&node.id)?, Node { dependencies, deps, features : node.features }))
+			})
+			.collect::<Fallible<_>>()?;
+		let packages = metadata.packages.into_iter().map(|p| (p.package_id(), p)).collect::<HashMap<_, _>>();
+		let root_manifest_path = metadata.workspace_root.join("Cargo.toml");
+		let root_package = packages.values().find(|p| p.manifest_path == root_manifest_path);
+		let resolver = resolver(&root_manifest_path, root_package)?;
+		let default_members = match default_members {
+			Some(default_members) => default_members,
+			None => {
+				let dirs = declared_default_members(&root_manifest_path)?;
+				let members = members.iter().copied();
+				match dirs {
+					Some(dirs) => members.filter(|m| dirs.iter().any(|d| packages[m].root() == d)).collect(),
+					None => members.collect(),
+				}
+			}
+		};
+
+		Ok(Self {
+			root : metadata.workspace_root,
+			target_dir : metadata.target_directory,
+			ids,
+			members,
+			default_members,
+			current,
+			packages,
+			resolve,
+			metadata : metadata.metadata,
+			resolver,
+		})
+	}
+
+	pub(crate) fn root(&self) -> &Path {
+		&self.root
+	}
+
+	pub(crate) fn target_dir(&self) -> &Path {
+		&self.target_dir
+	}
+
+	pub(crate) fn members(&self) -> impl Iterator<Item = &Package> {
+		self.members.iter().map(move |id| self.package(*id))
+	}
+
+	/// `workspace.default-members`, or all the members.
+	pub(crate) fn default_members(&self) -> impl Iterator<Item = &Package> {
+		self.default_members.iter().map(move |id| self.package(*id))
+	}
+
+	pub(crate) fn current(&self) -> Option<&Package> {
+		self.current.map(|id| self.package(id))
+	}
+
+	/// Translates an ID in the messages of Cargo, e.g. `compiler-artifact`.
+	pub(crate) fn package_id(&self, id :&str) -> Option<PackageId> {
+		self.ids.get(id).copied()
+	}
+
+	pub(crate) fn package(&self, id :PackageId) -> &Package {
+		self.packages.get(&id).unwrap_or_else(|| panic!("could not find `{}`", id))
+	}
+
+	/// The resolved dependencies of `id`, for every kind and platform.
+	pub(crate) fn deps(&self, id :PackageId) -> impl Iterator<Item = PackageId> + '_ {
+		self.resolve.get(&id).into_iter().flat_map(|node| node.dependencies.iter().copied())
+	}
+
+	/// The dependencies of `id` which have a `lib`, by the names given to `--extern`.
+	pub(crate) fn lib_deps(&self, id :PackageId) -> &[NodeDep] {
+		self.resolve.get(&id).map_or(&[], |node| &node.deps)
+	}
+
+	/// `[workspace.metadata]`
+	pub(crate) fn custom_metadata(&self) -> Option<&serde_json::Value> {
+		self.metadata.as_ref()
+	}
+
+	/// The version of the feature resolver, e.g. `2`.
+	///
+	/// Since version 2, the features of build-dependencies are not unified with the ones of the
+	/// other dependencies.
+	pub(crate) fn resolver(&self) -> u32 {
+		self.resolver
+	}
+
+	/// The enabled features of `id`.
+	pub(crate) fn features(&self, id :PackageId) -> &[String] {
+		self.resolve.get(&id).map_or(&[], |node| &node.features)
+	}
+}
+
+/// `resolver` in `[workspace]` or `[package]` of the root manifest, or the default of the
+/// edition of the root package.
+fn resolver(root_manifest_path :&Path, root_package :Option<&Package>) -> Fallible<u32> {
+	let manifest = fs::read_to_string(root_manifest_path)
+		.with_context(|_| format!("could not read `{}`", root_manifest_path.display()))?;
+	let manifest = manifest
+		.parse::<Document>()
+		.with_context(|_| format!("could not parse `{}`", root_manifest_path.display()))?;
+	let explicit = ["workspace", "package"].iter().find_map(|&table| {
+		let table = manifest.as_table().iter().find(|(k, _)| *k == table)?.1.as_table()?;
+		let resolver = table.iter().find(|(k, _)| *k == "resolver")?.1;
+		match resolver {
+			Item::Value(value) => value.as_str()?.parse().ok(),
+			_ => None,
+		}
+	});
+	Ok(explicit.unwrap_or_else(|| match root_package.map(|p| &*p.edition) {
+		Some("2015") | Some("2018") | None => 1,
+		Some("2021") => 2,
+		Some(_) => 3,
+	}))
+}
+
+/// The directories of `default-members` in `[workspace]` of the root manifest, for Cargo older
+/// than 1.71 which does not tell them.
+fn declared_default_members(root_manifest_path :&Path) -> Fallible<Option<Vec<PathBuf>>> {
+	let manifest = fs::read_to_string(root_manifest_path)
+		.with_context(|_| format!("could not read `{}`", root_manifest_path.display()))?;
+	let manifest = manifest
+		.parse::<Document>()
+		.with_context(|_| format!("could not parse `{}`", root_manifest_path.display()))?;
+	let root = root_manifest_path.parent().expect("should not be empty");
+	let default_members = manifest["workspace"]["default-members"].as_array().map(|default_members| {
+		default_members.iter().filter_map(|m| m.as_str()).map(|m| root.join(m)).collect()
+	});
+	Ok(default_members)
+}
+
+struct Node {
+	dependencies :Vec<PackageId>,
+	deps :Vec<NodeDep>,
+	features :Vec<String>,
+}
+
+pub(crate) struct NodeDep {
+	/// The name given to `--extern`, i.e. the one the source code refers to.
+	pub(crate) name :String,
+	pub(crate) pkg :PackageId,
+	pub(crate) kinds :Vec<dependency::Kind>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+	packages :Vec<Package>,
+	workspace_members :Vec<String>,
+	/// Since Rust 1.71.
+	#[serde(default)]
+	workspace_default_members :Option<Vec<String>>,
+	resolve :Option<MetadataResolve>,
+	workspace_root :PathBuf,
+	target_directory :PathBuf,
+	metadata :Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct MetadataResolve {
+	nodes :Vec<MetadataNode>,
+	root :Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MetadataNode {
+	id :String,
+	dependencies :Vec<String>,
+	/// Since Rust 1.41 (`dep_kinds`).
+	#[serde(default)]
+	deps :Vec<MetadataNodeDep>,
+	#[serde(default)]
+	features :Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MetadataNodeDep {
+	name :String,
+	pkg :String,
+	#[serde(default)]
+	dep_kinds :Vec<MetadataDepKind>,
+}
+
+#[derive(Deserialize)]
+struct MetadataDepKind {
+	kind :Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Package {
+	/// Opaque, and formatted differently by each version of Cargo.
+	id :String,
+	name :InternedString,
+	version :String,
+	source :Option<String>,
+	manifest_path :PathBuf,
+	#[serde(default = "default_edition")]
+	edition :String,
+	targets :Vec<Target>,
+	dependencies :Vec<Dependency>,
+	#[serde(default)]
+	features :BTreeMap<String, Vec<String>>,
+	publish :Option<Vec<String>>,
+	links :Option<String>,
+	/// `[package.metadata]`
+	metadata :Option<serde_json::Value>,
+}
+
+impl Package {
+	pub fn package_id(&self) -> PackageId {
+		match &self.source {
+			Some(source) => PackageId::new(&self.name, &self.version, source),
+			None => PackageId::with_path(&self.name, &self.version, self.root()),
+		}
+	}
+
+	pub fn name(&self) -> InternedString {
+		self.name
+	}
+
+	pub fn manifest_path(&self) -> &Path {
+		&self.manifest_path
+	}
+
+	/// The directory of `Cargo.toml`.
+	pub fn root(&self) -> &Path {
+		self.manifest_path.parent().unwrap_or_else(|| Path::new(""))
+	}
+
+	pub fn targets(&self) -> &[Target] {
+		&self.targets
+	}
+
+	pub fn dependencies(&self) -> &[Dependency] {
+		&self.dependencies
+	}
+
+	/// The features in `[features]`, and the ones Cargo creates for optional dependencies.
+	pub fn features(&self) -> &BTreeMap<String, Vec<String>> {
+		&self.features
+	}
+
+	/// The features in `[features]`, except `default`. The ones Cargo creates for optional
+	/// dependencies are excluded as well.
+	pub fn optional_features(&self) -> impl Iterator<Item = &str> {
+		self.features
+			.iter()
+			.filter(|(name, enables)| *name != "default" && **enables != [format!("dep:{}", name)])
+			.map(|(name, _)| &**name)
+	}
+
+	/// `None` if the package may be published to any registry.
+	pub fn publish(&self) -> Option<&[String]> {
+		self.publish.as_deref()
+	}
+
+	pub fn links(&self) -> Option<&str> {
+		self.links.as_deref()
+	}
+
+	/// `[package.metadata]`
+	pub fn custom_metadata(&self) -> Option<&serde_json::Value> {
+		self.metadata.as_ref()
+	}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Target {
+	kind :Vec<String>,
+	name :String,
+	src_path :PathBuf,
+	#[serde(rename = "required-features")]
+	required_features :Option<Vec<String>>,
+}
+
+impl Target {
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn src_path(&self) -> &Path {
+		&self.src_path
+	}
+
+	pub fn is_lib(&self) -> bool {
+		self.kind
+			.iter()
+			.any(|k| ["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"].contains(&&**k))
+	}
+
+	pub fn is_bin(&self) -> bool {
+		self.is_kind("bin")
+	}
+
+	pub fn is_custom_build(&self) -> bool {
+		self.is_kind("custom-build")
+	}
+
+	pub fn required_features(&self) -> Option<&[String]> {
+		self.required_features.as_deref()
+	}
+
+	/// e.g. `lib`, `bin "foo"` or `custom-build`, as Cargo describes the target.
+	pub fn description_named(&self) -> String {
+		if self.is_lib() {
+			"lib".to_owned()
+		} else if self.is_custom_build() {
+			"custom-build".to_owned()
+		} else {
+			let kind = self.kind.first().map_or("", |k| &**k);
+			format!("{} {:?}", kind, self.name)
+		}
+	}
+
+	fn is_kind(&self, kind :&str) -> bool {
+		self.kind.iter().any(|k| k == kind)
+	}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Dependency {
+	/// The name of the package, even if it is renamed.
+	name :String,
+	req :String,
+	kind :Option<String>,
+	rename :Option<InternedString>,
+	#[serde(default)]
+	optional :bool,
+	/// The features enabled by the dependent.
+	#[serde(default)]
+	features :Vec<String>,
+	uses_default_features :bool,
+	/// e.g. `cfg(windows)`
+	target :Option<String>,
+}
+
+impl Dependency {
+	/// The key in `Cargo.toml`.
+	pub fn name_in_toml(&self) -> InternedString {
+		self.rename.unwrap_or_else(|| InternedString::new(&self.name))
+	}
+
+	/// The name of the package, even if it is renamed.
+	pub fn package_name(&self) -> &str {
+		&self.name
+	}
+
+	/// The key in `Cargo.toml` if it differs from the name of the package.
+	pub fn explicit_name_in_toml(&self) -> Option<InternedString> {
+		self.rename
+	}
+
+	pub fn kind(&self) -> dependency::Kind {
+		parse_kind(self.kind.as_deref())
+	}
+
+	pub fn is_optional(&self) -> bool {
+		self.optional
+	}
+
+	pub fn features(&self) -> &[String] {
+		&self.features
+	}
+
+	/// `false` with `default-features = false`.
+	pub fn uses_default_features(&self) -> bool {
+		self.uses_default_features
+	}
+
+	pub fn platform(&self) -> Option<&str> {
+		self.target.as_deref()
+	}
+
+	/// Whether `package` may be what this dependency resolved to.
+	pub fn matches(&self, package :&Package) -> bool {
+		self.name == *package.name
+			&& match (semver::VersionReq::parse(&self.req), semver::Version::parse(&package.version)) {
+				(Ok(req), Ok(version)) => req.matches(&version) || !version.pre.is_empty(),
+				_ => true,
+			}
+	}
+}
+
+/// Before Rust 1.30, `cargo metadata` did not tell the edition.
+fn default_edition() -> String {
+	"2015".to_owned()
+}
+
+/// `null`, `"dev"` or `"build"`.
+fn parse_kind(kind :Option<&str>) -> dependency::Kind {
+	match kind {
+		Some("dev") => dependency::Kind::Development,
+		Some("build") => dependency::Kind::Build,
+		_ => dependency::Kind::Normal,
+	}
+}
@@ -0,0 +1,219 @@
+//! `--otel-endpoint`: the spans of the compilation and the analysis, exported with OTLP over HTTP
+//! in the JSON encoding.
+
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::net::TcpStream;
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use failure::{Fallible, ResultExt as _};
+use serde::Serialize;
+use url::Url;
+
+use crate::fnv1a;
+
+/// The spans recorded so far. They are always recorded, exported with `--otel-endpoint`, and
+/// summarized with `--timings`.
+#[derive(Default, Debug)]
+pub(crate) struct Trace {
+	spans :Vec<Span>,
+	/// Indices of the spans which have started but not ended, innermost last.
+	open :Vec<usize>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Span {
+	pub(crate) name :String,
+	parent :Option<usize>,
+	start :u64,
+	end :u64,
+	attributes :Vec<(&'static str, String)>,
+}
+
+impl Span {
+	/// In nanoseconds, or 0 if it has not ended.
+	pub(crate) fn duration(&self) -> u64 {
+		self.end.saturating_sub(self.start)
+	}
+
+	pub(crate) fn attribute(&self, key :&str) -> Option<&str> {
+		self.attributes.iter().find(|(k, _)| *k == key).map(|(_, v)| &**v)
+	}
+}
+
+impl Trace {
+	pub(crate) fn spans(&self) -> &[Span] {
+		&self.spans
+	}
+
+	/// Starts a span in the innermost open one.
+	pub(crate) fn start(&mut self, name :impl Into<String>) -> usize {
+		let parent = self.open.last().copied();
+		self.spans.push(Span { name : name.into(), parent, start : unix_nanos(), end : 0, attributes : vec![] });
+		self.open.push(self.spans.len() - 1);
+		self.spans.len() - 1
+	}
+
+	/// Ends `span`, and the ones started in it which are still open.
+	pub(crate) fn end(&mut self, span :usize) {
+		let now = unix_nanos();
+		while let Some(open) = self.open.pop() {
+			self.spans[open].end = now;
+			if open == span {
+				break;
+			}
+		}
+	}
+
+	/// Adds a span which has already ended, e.g. of a `rustc` process, in the innermost open one.
+	pub(crate) fn add(&mut self, name :impl Into<String>, start :u64, end :u64, attributes :Vec<(&'static str, String)>) {
+		let parent = self.open.last().copied();
+		self.spans.push(Span { name : name.into(), parent, start, end, attributes });
+	}
+
+	/// Sets an attribute of `span`.
+	pub(crate) fn set_attribute(&mut self, span :usize, key :&'static str, value :impl ToString) {
+		self.spans[span].attributes.push((key, value.to_string()));
+	}
+
+	/// POSTs the spans to `{endpoint}/v1/traces`.
+	pub(crate) fn export(&self, endpoint :&Url) -> Fallible<()> {
+		let url = if endpoint.path().ends_with("/v1/traces") {
+			endpoint.clone()
+		} else {
+			let path = format!("{}/v1/traces", endpoint.path().trim_end_matches('/'));
+			let mut url = endpoint.clone();
+			url.set_path(&path);
+			url
+		};
+		if url.scheme() != "http" {
+			return Err(failure::format_err!("only `http` endpoints are supported, got `{}`", endpoint));
+		}
+		let host = url.host_str().ok_or_else(|| failure::format_err!("no host in `{}`", endpoint))?;
+		let port = url.port_or_known_default().unwrap_or(80);
+		let body = serde_json::to_string(&self.to_otlp()).expect("should not fail");
+
+		let mut stream = TcpStream::connect((host, port)).with_context(|_| format!("could not connect to `{}`", url))?;
+		stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+		stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+		let query = url.query().map(|q| format!("?{}", q)).unwrap_or_default();
+		write!(
+			stream,
+			"POST {}{} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			url.path(),
+			query,
+			host,
+			port,
+			body.len(),
+			body,
+		)?;
+		stream.flush()?;
+
+		let mut status_line = String::new();
+		BufReader::new(stream).read_line(&mut status_line)?;
+		match status_line.split(' ').nth(1) {
+			Some(status) if status.starts_with('2') => Ok(()),
+			_ => Err(failure::format_err!("`{}` responded with `{}`", url, status_line.trim_end())),
+		}
+	}
+
+	fn to_otlp(&self) -> OtlpTraces {
+		let seed = format!("{}:{}", process::id(), unix_nanos());
+		let trace_id = format!("{:016x}{:016x}", fnv1a(seed.as_bytes()), fnv1a(format!("{}:trace", seed).as_bytes()));
+		let span_id = |i :usize| format!("{:016x}", fnv1a(format!("{}:{}", seed, i).as_bytes()));
+		let spans = self
+			.spans
+			.iter()
+			.enumerate()
+			.map(|(i, span)| OtlpSpan {
+				trace_id : trace_id.clone(),
+				span_id : span_id(i),
+				parent_span_id : span.parent.map(span_id).unwrap_or_default(),
+				name : span.name.clone(),
+				kind : SPAN_KIND_INTERNAL,
+				start_time_unix_nano : span.start.to_string(),
+				end_time_unix_nano : span.end.max(span.start).to_string(),
+				attributes : span.attributes.iter().map(|(k, v)| OtlpAttribute::new(k, v)).collect(),
+			})
+			.collect();
+		OtlpTraces {
+			resource_spans : vec![OtlpResourceSpans {
+				resource : OtlpResource { attributes : vec![OtlpAttribute::new("service.name", "cargo-udeps")] },
+				scope_spans : vec![OtlpScopeSpans {
+					scope : OtlpScope { name : "cargo-udeps", version : env!("CARGO_PKG_VERSION") },
+					spans,
+				}],
+			}],
+		}
+	}
+}
+
+/// Nanoseconds since the Unix epoch, as OTLP counts them.
+pub(crate) fn unix_nanos() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+const SPAN_KIND_INTERNAL :u8 = 1;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpTraces {
+	resource_spans :Vec<OtlpResourceSpans>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpResourceSpans {
+	resource :OtlpResource,
+	scope_spans :Vec<OtlpScopeSpans>,
+}
+
+#[derive(Serialize)]
+struct OtlpResource {
+	attributes :Vec<OtlpAttribute>,
+}
+
+#[derive(Serialize)]
+struct OtlpScopeSpans {
+	scope :OtlpScope,
+	spans :Vec<OtlpSpan>,
+}
+
+#[derive(Serialize)]
+struct OtlpScope {
+	name :&'static str,
+	version :&'static str,
+}
+
+/// 64-bit integers are strings in the JSON encoding.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan {
+	trace_id :String,
+	span_id :String,
+	#[serde(skip_serializing_if = "String::is_empty")]
+	parent_span_id :String,
+	name :String,
+	kind :u8,
+	start_time_unix_nano :String,
+	end_time_unix_nano :String,
+	attributes :Vec<OtlpAttribute>,
+}
+
+#[derive(Serialize)]
+struct OtlpAttribute {
+	key :String,
+	value :OtlpValue,
+}
+
+impl OtlpAttribute {
+	fn new(key :&str, value :&str) -> Self {
+		Self { key : key.to_owned(), value : OtlpValue { string_value : value.to_owned() } }
+	}
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpValue {
+	string_value :String,
+}
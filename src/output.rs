@@ -0,0 +1,574 @@
+//! Machine-readable report formats for CI systems.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use serde_json::json;
+use url::Url;
+
+use crate::dependency;
+use crate::interning::InternedString;
+use crate::manifest::{self, dependency_line, tables};
+use crate::package_id::PackageId;
+use crate::{fnv1a, Outcome, OutcomeUnusedDeps};
+
+static KINDS :[dependency::Kind; 3] =
+	[dependency::Kind::Normal, dependency::Kind::Development, dependency::Kind::Build];
+
+fn noun(kind :dependency::Kind) -> &'static str {
+	match kind {
+		dependency::Kind::Normal => "dependency",
+		dependency::Kind::Development => "dev-dependency",
+		dependency::Kind::Build => "build-dependency",
+	}
+}
+
+/// `(id, description)` of the rule reported for each dependency kind.
+fn rule(kind :dependency::Kind) -> (&'static str, &'static str) {
+	match kind {
+		dependency::Kind::Normal => ("unused-dependency", "Unused dependency"),
+		dependency::Kind::Development => ("unused-dev-dependency", "Unused dev-dependency"),
+		dependency::Kind::Build => ("unused-build-dependency", "Unused build-dependency"),
+	}
+}
+
+/// Writes a [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) log.
+///
+/// The manifests are relative to `%SRCROOT%`, the checkout on GitHub Actions or `cwd`, so that
+/// code scanning can anchor the results to their lines.
+pub(crate) fn print_sarif(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	let base = checkout_dir("GITHUB_WORKSPACE", cwd);
+	let rules = KINDS
+		.iter()
+		.map(|&kind| {
+			let (id, description) = rule(kind);
+			json!({
+				"id": id,
+				"shortDescription": { "text": description },
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let mut results = vec![];
+	for (member, unused) in &outcome.unused_deps {
+		let manifest_path = outcome.manifest_path(&unused.manifest_path);
+		let manifest = fs::read_to_string(&manifest_path).unwrap_or_default();
+		let uri = relative_path(&manifest_path, &base);
+		for (kind, name) in unused.iter() {
+			let mut physical_location = json!({
+				"artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" },
+			});
+			if let Some(line) = dependency_line(&manifest, kind, &name) {
+				physical_location["region"] = json!({ "startLine": line });
+			}
+			results.push(json!({
+				"ruleId": rule(kind).0,
+				"level": "warning",
+				"message": {
+					"text": format!("`{}` is not used by `{}`", name, member),
+				},
+				"locations": [{ "physicalLocation": physical_location }],
+			}));
+		}
+	}
+
+	let sarif = json!({
+		"$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+		"version": "2.1.0",
+		"runs": [{
+			"tool": {
+				"driver": {
+					"name": "cargo-udeps",
+					"informationUri": env!("CARGO_PKG_REPOSITORY"),
+					"version": env!("CARGO_PKG_VERSION"),
+					"rules": rules,
+				},
+			},
+			"originalUriBaseIds": {
+				"%SRCROOT%": { "uri": Url::from_directory_path(&base).map(|url| url.to_string()).unwrap_or_default() },
+			},
+			"results": results,
+		}],
+	});
+	writeln!(stdout, "{}", sarif)?;
+	stdout.flush()
+}
+
+/// Writes [workflow commands](https://help.github.com/en/actions/reference/workflow-commands-for-github-actions)
+/// which GitHub Actions shows as annotations.
+pub(crate) fn print_github(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	let base = checkout_dir("GITHUB_WORKSPACE", cwd);
+	for unused in outcome.unused_deps.values() {
+		let manifest_path = outcome.manifest_path(&unused.manifest_path);
+		let manifest = fs::read_to_string(&manifest_path).unwrap_or_default();
+		let file = relative_path(&manifest_path, &base);
+		for (kind, name) in unused.iter() {
+			let mut properties = format!("file={}", escape_github_property(&file));
+			if let Some(line) = dependency_line(&manifest, kind, &name) {
+				properties += &format!(",line={}", line);
+			}
+			let message = format!("unused {} '{}'", noun(kind), name);
+			writeln!(stdout, "::warning {}::{}", properties, escape_github_data(&message))?;
+		}
+	}
+	stdout.flush()
+}
+
+/// Identifies an unused dependency across runs.
+fn fingerprint(member :PackageId, kind :dependency::Kind, name :InternedString) -> String {
+	// `PackageId`s of path packages contain absolute paths which differ between runners.
+	let key = format!("{} {} {} {}", member.name(), member.version(), tables(kind)[0], name);
+	format!("{:016x}", fnv1a(key.as_bytes()))
+}
+
+/// The directory which annotations are resolved against, given by `var` on the CI system.
+fn checkout_dir(var :&str, cwd :&Path) -> PathBuf {
+	env::var_os(var).map_or_else(|| cwd.to_owned(), |dir| cwd.join(dir))
+}
+
+/// `path` relative to `base` if possible, with `/` as the separator.
+fn relative_path(path :impl AsRef<Path>, base :&Path) -> String {
+	let path = path.as_ref();
+	path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+fn escape_github_data(s :&str) -> String {
+	s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_github_property(s :&str) -> String {
+	escape_github_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Writes a JUnit XML report with a test case per package and dependency kind.
+pub(crate) fn print_junit(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	fn num_failures(unused :&OutcomeUnusedDeps) -> usize {
+		KINDS.iter().filter(|&&kind| unused.iter().any(|(k, _)| k == kind)).count()
+	}
+
+	writeln!(stdout, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+	writeln!(
+		stdout,
+		r#"<testsuites name="cargo-udeps" tests="{}" failures="{}">"#,
+		KINDS.len() * outcome.unused_deps.len(),
+		outcome.unused_deps.values().map(num_failures).sum::<usize>(),
+	)?;
+	for (member, unused) in &outcome.unused_deps {
+		let member = escape_xml(&member.to_string());
+		writeln!(
+			stdout,
+			r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+			member,
+			KINDS.len(),
+			num_failures(unused),
+		)?;
+		for &kind in &KINDS {
+			let names = unused.iter().filter(|&(k, _)| k == kind).map(|(_, n)| n).collect::<Vec<_>>();
+			write!(stdout, r#"    <testcase classname="{}" name="{}""#, member, tables(kind)[0])?;
+			if names.is_empty() {
+				writeln!(stdout, "/>")?;
+				continue;
+			}
+			writeln!(stdout, ">")?;
+			for name in names {
+				writeln!(
+					stdout,
+					r#"      <failure type="{}" message="{}">{}</failure>"#,
+					rule(kind).0,
+					escape_xml(&format!("unused {} `{}`", noun(kind), name)),
+					escape_xml(&unused.manifest_path.replace('\\', "/")),
+				)?;
+			}
+			writeln!(stdout, "    </testcase>")?;
+		}
+		writeln!(stdout, "  </testsuite>")?;
+	}
+	writeln!(stdout, "</testsuites>")?;
+	stdout.flush()
+}
+
+fn escape_xml(s :&str) -> String {
+	s.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+		.replace('\'', "&apos;")
+}
+
+/// Writes a [Code Quality](https://docs.gitlab.com/ee/user/project/merge_requests/code_quality.html)
+/// report for GitLab.
+pub(crate) fn print_codeclimate(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	let mut issues = vec![];
+	for (member, unused) in &outcome.unused_deps {
+		let manifest_path = outcome.manifest_path(&unused.manifest_path);
+		let manifest = fs::read_to_string(&manifest_path).unwrap_or_default();
+		let path = relative_path(&manifest_path, cwd);
+		for (kind, name) in unused.iter() {
+			issues.push(json!({
+				"type": "issue",
+				"check_name": rule(kind).0,
+				"description": format!("unused {} `{}` in `{} v{}`", noun(kind), name, member.name(), member.version()),
+				"categories": ["Clarity"],
+				"fingerprint": fingerprint(*member, kind, name),
+				"severity": "minor",
+				"location": {
+					"path": path,
+					"lines": { "begin": dependency_line(&manifest, kind, &name).unwrap_or(1) },
+				},
+			}));
+		}
+	}
+	writeln!(stdout, "{}", serde_json::Value::from(issues))?;
+	stdout.flush()
+}
+
+/// Writes [service messages](https://www.jetbrains.com/help/teamcity/service-messages.html) reporting
+/// each unused dependency as an inspection and a build problem.
+pub(crate) fn print_teamcity(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	for &kind in &KINDS {
+		if outcome.unused_deps.values().any(|u| u.iter().any(|(k, _)| k == kind)) {
+			let (id, description) = rule(kind);
+			writeln!(
+				stdout,
+				"##teamcity[inspectionType id='{}' name='{}' category='cargo-udeps' description='{}']",
+				id,
+				description,
+				description,
+			)?;
+		}
+	}
+	for (member, unused) in &outcome.unused_deps {
+		let manifest_path = outcome.manifest_path(&unused.manifest_path);
+		let manifest = fs::read_to_string(&manifest_path).unwrap_or_default();
+		let file = escape_teamcity(&relative_path(&manifest_path, cwd));
+		for (kind, name) in unused.iter() {
+			let message = escape_teamcity(&format!("unused {} `{}` in `{}`", noun(kind), name, member));
+			write!(
+				stdout,
+				"##teamcity[inspection typeId='{}' message='{}' file='{}'",
+				rule(kind).0,
+				message,
+				file,
+			)?;
+			if let Some(line) = dependency_line(&manifest, kind, &name) {
+				write!(stdout, " line='{}'", line)?;
+			}
+			writeln!(stdout, " SEVERITY='WARNING']")?;
+			writeln!(
+				stdout,
+				"##teamcity[buildProblem description='{}' identity='{}']",
+				message,
+				fingerprint(*member, kind, name),
+			)?;
+		}
+	}
+	stdout.flush()
+}
+
+fn escape_teamcity(s :&str) -> String {
+	s.replace('|', "||")
+		.replace('\'', "|'")
+		.replace('\n', "|n")
+		.replace('\r', "|r")
+		.replace('[', "|[")
+		.replace(']', "|]")
+}
+
+/// Writes [logging commands](https://docs.microsoft.com/en-us/azure/devops/pipelines/scripts/logging-commands)
+/// which Azure Pipelines shows in the run summary.
+pub(crate) fn print_azure(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	let base = checkout_dir("BUILD_SOURCESDIRECTORY", cwd);
+	for unused in outcome.unused_deps.values() {
+		let manifest_path = outcome.manifest_path(&unused.manifest_path);
+		let manifest = fs::read_to_string(&manifest_path).unwrap_or_default();
+		let file = relative_path(&manifest_path, &base);
+		for (kind, name) in unused.iter() {
+			let mut properties = format!(
+				"type=warning;sourcepath={};code={}",
+				escape_azure_property(&file),
+				rule(kind).0,
+			);
+			if let Some(line) = dependency_line(&manifest, kind, &name) {
+				properties += &format!(";linenumber={}", line);
+			}
+			let message = format!("unused {} '{}'", noun(kind), name);
+			writeln!(stdout, "##vso[task.logissue {}]{}", properties, escape_azure_data(&message))?;
+		}
+	}
+	stdout.flush()
+}
+
+fn escape_azure_data(s :&str) -> String {
+	s.replace('%', "%AZP25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_azure_property(s :&str) -> String {
+	escape_azure_data(s).replace(';', "%3B").replace(']', "%5D")
+}
+
+/// Writes a report to be posted as a pull request comment.
+pub(crate) fn print_markdown(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	writeln!(stdout, "## Unused dependencies")?;
+	if outcome.unused_deps.values().all(OutcomeUnusedDeps::is_empty) {
+		writeln!(stdout)?;
+		writeln!(stdout, "All deps seem to have been used.")?;
+		return stdout.flush();
+	}
+	for (member, unused) in outcome.unused_deps.iter().filter(|(_, u)| !u.is_empty()) {
+		writeln!(stdout)?;
+		writeln!(
+			stdout,
+			"### `{} v{}` (`{}`)",
+			member.name(),
+			member.version(),
+			relative_path(&unused.manifest_path, cwd),
+		)?;
+		writeln!(stdout)?;
+		writeln!(stdout, "| Kind | Dependency |")?;
+		writeln!(stdout, "| ---- | ---------- |")?;
+		for (kind, name) in unused.iter() {
+			writeln!(stdout, "| {} | `{}` |", tables(kind)[0], name)?;
+		}
+		writeln!(stdout)?;
+		writeln!(stdout, "<details><summary>Ignoring false positives</summary>")?;
+		writeln!(stdout)?;
+		writeln!(stdout, "```toml")?;
+		writeln!(stdout, "[package.metadata.cargo-udeps.ignore]")?;
+		for (deps, key) in &[(&unused.normal, "normal"), (&unused.development, "development"), (&unused.build, "build")] {
+			if !deps.is_empty() {
+				let deps = deps.iter().map(|d| format!("{:?}", d)).collect::<Vec<_>>();
+				writeln!(stdout, "{} = [{}]", key, deps.join(", "))?;
+			}
+		}
+		writeln!(stdout, "```")?;
+		writeln!(stdout)?;
+		writeln!(stdout, "</details>")?;
+	}
+	stdout.flush()
+}
+
+static HTML_HEAD :&str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>cargo-udeps report</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+th { cursor: pointer; background: #f0f0f0; }
+</style>
+</head>
+<body>
+<h1>Unused dependencies</h1>
+"#;
+
+static HTML_TAIL :&str = r#"<script>
+document.querySelectorAll("table.sortable th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var tbody = th.closest("table").tBodies[0];
+    var index = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var ascending = th.dataset.order !== "asc";
+    th.dataset.order = ascending ? "asc" : "desc";
+    Array.from(tbody.rows)
+      .sort(function (a, b) {
+        var x = a.cells[index].textContent, y = b.cells[index].textContent;
+        return ascending ? x.localeCompare(y) : y.localeCompare(x);
+      })
+      .forEach(function (row) { tbody.appendChild(row); });
+  });
+});
+</script>
+</body>
+</html>
+"#;
+
+/// Writes a standalone HTML page with a sortable table per package.
+pub(crate) fn print_html(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	write!(stdout, "{}", HTML_HEAD)?;
+	if outcome.unused_deps.values().all(OutcomeUnusedDeps::is_empty) {
+		writeln!(stdout, "<p>All deps seem to have been used.</p>")?;
+	}
+	for (member, unused) in outcome.unused_deps.iter().filter(|(_, u)| !u.is_empty()) {
+		let manifest_path = unused.manifest_path.replace('\\', "/");
+		let url = if manifest_path.starts_with('/') {
+			format!("file://{}", manifest_path)
+		} else {
+			format!("file:///{}", manifest_path)
+		};
+		writeln!(
+			stdout,
+			r#"<h2><code>{}</code> (<a href="{}">Cargo.toml</a>)</h2>"#,
+			escape_xml(&member.to_string()),
+			escape_xml(&url),
+		)?;
+		writeln!(stdout, r#"<table class="sortable">"#)?;
+		writeln!(stdout, "<thead><tr><th>Kind</th><th>Dependency</th></tr></thead>")?;
+		writeln!(stdout, "<tbody>")?;
+		for (kind, name) in unused.iter() {
+			writeln!(stdout, "<tr><td>{}</td><td><code>{}</code></td></tr>", tables(kind)[0], name)?;
+		}
+		writeln!(stdout, "</tbody>")?;
+		writeln!(stdout, "</table>")?;
+	}
+	write!(stdout, "{}", HTML_TAIL)?;
+	stdout.flush()
+}
+
+/// Writes the analyzed dependency graph in the DOT language of Graphviz, with the unused edges in
+/// red.
+pub(crate) fn print_dot(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	fn node(id :PackageId) -> String {
+		format!("{:?}", id.to_string())
+	}
+
+	let mut edges = outcome.edges.iter().collect::<Vec<_>>();
+	edges.sort_by_key(|e| (e.from, e.name, tables(e.kind)[0]));
+	let mut nodes = edges.iter().flat_map(|e| once(e.from).chain(e.to)).collect::<Vec<_>>();
+	nodes.sort();
+	nodes.dedup();
+
+	writeln!(stdout, "digraph udeps {{")?;
+	writeln!(stdout, "\tnode [shape=box];")?;
+	for &id in &nodes {
+		writeln!(stdout, "\t{} [label={:?}];", node(id), format!("{} v{}", id.name(), id.version()))?;
+	}
+	for edge in edges {
+		let to = match edge.to {
+			Some(to) => node(to),
+			None => format!("{:?}", edge.name),
+		};
+		let mut attrs = vec![if edge.unused { "color=red" } else { "color=gray" }.to_owned()];
+		match edge.kind {
+			dependency::Kind::Normal => {}
+			dependency::Kind::Development => attrs.push("label=\"dev\"".to_owned()),
+			dependency::Kind::Build => attrs.push("label=\"build\"".to_owned()),
+		}
+		writeln!(stdout, "\t{} -> {} [{}];", node(edge.from), to, attrs.join(", "))?;
+	}
+	writeln!(stdout, "}}")?;
+	stdout.flush()
+}
+
+/// Writes a line per unused dependency, e.g. `a dev maplit`, for `grep` and `awk`. The kinds are
+/// the ones of `--kinds`.
+pub(crate) fn print_short(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	for (id, unused) in &outcome.unused_deps {
+		for (kind, dependency) in unused.iter() {
+			let kind = match kind {
+				dependency::Kind::Normal => "normal",
+				dependency::Kind::Development => "dev",
+				dependency::Kind::Build => "build",
+			};
+			writeln!(stdout, "{} {} {}", id.name(), kind, dependency)?;
+		}
+	}
+	stdout.flush()
+}
+
+/// Writes a unified diff applying the suggestions, i.e. removing the unused dependencies and
+/// turning off the unneeded default features, to be applied with `git apply`.
+pub(crate) fn print_patch(outcome :&Outcome, cwd :&Path, mut stdout :impl Write) -> io::Result<()> {
+	const CONTEXT :usize = 3;
+
+	// The replacements of the lines `line_start..=line_end` (1-based), by `line_start`.
+	let mut edits = BTreeMap::<_, BTreeMap<_, _>>::new();
+	for suggestion in &outcome.suggestions {
+		edits
+			.entry(&suggestion.manifest_path)
+			.or_default()
+			.entry(suggestion.line_start)
+			.or_insert((suggestion.line_end, &*suggestion.replacement));
+	}
+
+	for (manifest_path, edits) in edits {
+		let manifest_path = outcome.manifest_path(manifest_path);
+		let manifest = fs::read_to_string(&manifest_path)?;
+		let lines = manifest::lines(&manifest).into_iter().map(|(_, l)| l).collect::<Vec<_>>();
+		let path = relative_path(&manifest_path, cwd);
+		writeln!(stdout, "--- a/{}", path)?;
+		writeln!(stdout, "+++ b/{}", path)?;
+
+		// Group the edits into hunks whose contexts do not overlap.
+		let mut hunks = Vec::<Vec<(usize, usize, &str)>>::new();
+		for (&line_start, &(line_end, replacement)) in &edits {
+			match hunks.last_mut() {
+				Some(hunk) if line_start <= hunk[hunk.len() - 1].1 + 2 * CONTEXT + 1 => {
+					hunk.push((line_start, line_end, replacement))
+				}
+				_ => hunks.push(vec![(line_start, line_end, replacement)]),
+			}
+		}
+
+		// The number of lines the previous hunks added, less the ones they removed.
+		let mut offset = 0isize;
+		for hunk in hunks {
+			let start = hunk[0].0.saturating_sub(CONTEXT).max(1);
+			let end = (hunk[hunk.len() - 1].1 + CONTEXT).min(lines.len());
+			let mut body = vec![];
+			let mut next = start;
+			for (line_start, line_end, replacement) in hunk {
+				if line_start < next {
+					continue;
+				}
+				body.extend(lines[next - 1..line_start - 1].iter().map(|&l| (' ', l)));
+				body.extend(lines[line_start - 1..line_end].iter().map(|&l| ('-', l)));
+				body.extend(manifest::lines(replacement).into_iter().map(|(_, l)| ('+', l)));
+				next = line_end + 1;
+			}
+			body.extend(lines[next - 1..end].iter().map(|&l| (' ', l)));
+
+			let old_len = end - start + 1;
+			let new_len = body.iter().filter(|&&(sign, _)| sign != '-').count();
+			writeln!(stdout, "@@ -{},{} +{},{} @@", start, old_len, start as isize + offset, new_len)?;
+			for (sign, line) in body {
+				write!(stdout, "{}{}", sign, line)?;
+				if !line.ends_with('\n') {
+					writeln!(stdout)?;
+					writeln!(stdout, "\\ No newline at end of file")?;
+				}
+			}
+			offset += new_len as isize - old_len as isize;
+		}
+	}
+	stdout.flush()
+}
+
+/// Writes the numbers of the unused dependencies of the `checked` members as gauges in the text
+/// format of Prometheus, for `--metrics-file`.
+pub(crate) fn print_metrics(outcome :&Outcome, checked :&[PackageId], mut stdout :impl Write) -> io::Result<()> {
+	fn escape(s :&str) -> String {
+		s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+	}
+
+	let mut checked = checked.to_vec();
+	checked.sort();
+	checked.dedup();
+	writeln!(stdout, "# HELP cargo_udeps_unused_total The number of the unused dependencies.")?;
+	writeln!(stdout, "# TYPE cargo_udeps_unused_total gauge")?;
+	for &id in &checked {
+		let unused = outcome.unused_deps.get(&id);
+		for &kind in &KINDS {
+			let count = unused.map_or(0, |u| u.iter().filter(|&(k, _)| k == kind).count());
+			let kind = match kind {
+				dependency::Kind::Normal => "normal",
+				dependency::Kind::Development => "development",
+				dependency::Kind::Build => "build",
+			};
+			writeln!(
+				stdout,
+				"cargo_udeps_unused_total{{package=\"{}\",kind=\"{}\"}} {}",
+				escape(&id.name()),
+				kind,
+				count,
+			)?;
+		}
+	}
+	writeln!(stdout, "# HELP cargo_udeps_success Whether no problem was found.")?;
+	writeln!(stdout, "# TYPE cargo_udeps_success gauge")?;
+	writeln!(stdout, "cargo_udeps_success {}", if outcome.success { 1 } else { 0 })?;
+	stdout.flush()
+}
@@ -0,0 +1,158 @@
+//! Identifiers of the packages in the dependency graph.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::interning::InternedString;
+
+static CRATES_IO :&str = "registry+https://github.com/rust-lang/crates.io-index";
+
+/// A package, as `name version (source)`. The source is the URL Cargo gives it, e.g.
+/// `registry+https://github.com/rust-lang/crates.io-index` or `path+file:///path/to/package`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackageId {
+	name :InternedString,
+	version :InternedString,
+	source :InternedString,
+}
+
+impl PackageId {
+	pub(crate) fn new(name :&str, version :&str, source :&str) -> Self {
+		Self {
+			name : InternedString::new(name),
+			version : InternedString::new(version),
+			source : InternedString::new(source),
+		}
+	}
+
+	/// The ID of the package at `root` on the local filesystem.
+	/// `root` may be relative, e.g. with `--paths relative`.
+	pub(crate) fn with_path(name :&str, version :&str, root :&Path) -> Self {
+		if root.is_relative() {
+			return Self::new(name, version, &format!("path+{}", root.display()));
+		}
+		let source = match url::Url::from_file_path(root) {
+			Ok(url) => format!("path+{}", url),
+			Err(()) => format!("path+file://{}", root.display()),
+		};
+		Self::new(name, version, &source)
+	}
+
+	pub fn name(self) -> InternedString {
+		self.name
+	}
+
+	pub fn version(self) -> InternedString {
+		self.version
+	}
+
+	pub fn source(self) -> InternedString {
+		self.source
+	}
+
+	/// Whether the package is on the local filesystem, e.g. a workspace member.
+	pub fn is_path(self) -> bool {
+		self.source.starts_with("path+")
+	}
+
+	/// The directory of the package, if it is on the local filesystem.
+	pub(crate) fn path(self) -> Option<PathBuf> {
+		if !self.is_path() {
+			return None;
+		}
+		match url::Url::parse(&self.source["path+".len()..]) {
+			Ok(url) => url.to_file_path().ok(),
+			Err(_) => Some(PathBuf::from(&self.source["path+".len()..])),
+		}
+	}
+
+	/// The package with its path relative to `root`, if it is in `root`.
+	pub(crate) fn relative_to(self, root :&Path) -> Self {
+		match self.path().as_ref().and_then(|p| p.strip_prefix(root).ok()) {
+			Some(relative) => Self::with_path(&self.name, &self.version, relative),
+			None => self,
+		}
+	}
+}
+
+impl Ord for PackageId {
+	fn cmp(&self, other :&Self) -> Ordering {
+		let version = |id :&Self| semver::Version::parse(&id.version).ok();
+		self.name
+			.cmp(&other.name)
+			.then_with(|| version(self).cmp(&version(other)))
+			.then_with(|| self.version.cmp(&other.version))
+			.then_with(|| self.source.cmp(&other.source))
+	}
+}
+
+impl PartialOrd for PackageId {
+	fn partial_cmp(&self, other :&Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+/// `name vX.Y.Z (source)` as Cargo shows it. The source is omitted for crates.io.
+impl fmt::Display for PackageId {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} v{}", self.name, self.version)?;
+		if let Some(path) = self.path() {
+			let path = if path.as_os_str().is_empty() { Path::new(".") } else { &path };
+			write!(f, " ({})", path.display())
+		} else if *self.source != *CRATES_IO {
+			write!(f, " ({})", self.source)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+impl fmt::Debug for PackageId {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "PackageId({} {} ({}))", self.name, self.version, self.source)
+	}
+}
+
+impl Serialize for PackageId {
+	fn serialize<S: Serializer>(&self, serializer :S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(&format_args!("{} {} ({})", self.name, self.version, self.source))
+	}
+}
+
+impl JsonSchema for PackageId {
+	fn schema_name() -> String {
+		"PackageId".to_owned()
+	}
+
+	fn json_schema(_ :&mut SchemaGenerator) -> Schema {
+		let metadata = Metadata {
+			description : Some("`name version (source)`, e.g. `foo 0.1.0 (path+file:///path/to/foo)`".to_owned()),
+			..Metadata::default()
+		};
+		let schema = SchemaObject {
+			instance_type : Some(InstanceType::String.into()),
+			metadata : Some(Box::new(metadata)),
+			..SchemaObject::default()
+		};
+		schema.into()
+	}
+}
+
+impl<'de> Deserialize<'de> for PackageId {
+	fn deserialize<D: Deserializer<'de>>(deserializer :D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		let mut split = s.splitn(3, ' ');
+		match (split.next(), split.next(), split.next()) {
+			(Some(name), Some(version), Some(source)) if source.starts_with('(') && source.ends_with(')') => {
+				Ok(Self::new(name, version, &source[1..source.len() - 1]))
+			}
+			_ => Err(de::Error::custom(format!("invalid package ID: {:?}", s))),
+		}
+	}
+}
@@ -0,0 +1,153 @@
+//! The packages in `--output json`, as `{name, version, manifest_path}` objects.
+//!
+//! The [`PackageId`]s contain the source URLs, whose format differs between the versions of Cargo.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::package_id::PackageId;
+
+/// A workspace member.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct PackageKey {
+	name :String,
+	version :String,
+	manifest_path :String,
+}
+
+impl PackageKey {
+	fn new(id :PackageId) -> Self {
+		let manifest_path = id.path().map(|p| p.join("Cargo.toml").display().to_string()).unwrap_or_default();
+		Self { name : id.name().to_string(), version : id.version().to_string(), manifest_path }
+	}
+
+	fn id(&self) -> PackageId {
+		let root = Path::new(&self.manifest_path).parent().unwrap_or_else(|| Path::new(""));
+		PackageId::with_path(&self.name, &self.version, root)
+	}
+}
+
+/// An entry of a map keyed by the packages, with the fields of the value next to `package`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct PackageEntry<T> {
+	package :PackageKey,
+	#[serde(flatten)]
+	value :T,
+}
+
+/// The reports of `schema_version` 1 keyed the maps by the [`PackageId`]s.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Compat<T> {
+	Entries(Vec<PackageEntry<T>>),
+	ById(BTreeMap<PackageId, T>),
+}
+
+/// For `#[serde(with = "package_key::map")]` on `BTreeMap<PackageId, T>`.
+pub(crate) mod map {
+	use super::*;
+
+	pub(crate) fn serialize<T :Serialize, S :Serializer>(map :&BTreeMap<PackageId, T>, serializer :S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(map.iter().map(|(&id, value)| PackageEntry { package : PackageKey::new(id), value }))
+	}
+
+	pub(crate) fn deserialize<'de, T :Deserialize<'de>, D :Deserializer<'de>>(
+		deserializer :D,
+	) -> Result<BTreeMap<PackageId, T>, D::Error> {
+		Ok(match Compat::deserialize(deserializer)? {
+			Compat::Entries(entries) => entries.into_iter().map(|e| (e.package.id(), e.value)).collect(),
+			Compat::ById(map) => map,
+		})
+	}
+}
+
+/// For `#[serde(with = "package_key::nested_map")]` on `BTreeMap<K, BTreeMap<PackageId, T>>`.
+pub(crate) mod nested_map {
+	use super::*;
+
+	struct Entries<'a, T>(&'a BTreeMap<PackageId, T>);
+
+	impl<T :Serialize> Serialize for Entries<'_, T> {
+		fn serialize<S :Serializer>(&self, serializer :S) -> Result<S::Ok, S::Error> {
+			map::serialize(self.0, serializer)
+		}
+	}
+
+	#[derive(Deserialize)]
+	#[serde(bound = "T: Deserialize<'de>")]
+	struct Owned<T>(#[serde(with = "map")] BTreeMap<PackageId, T>);
+
+	pub(crate) fn serialize<K :Serialize, T :Serialize, S :Serializer>(
+		map :&BTreeMap<K, BTreeMap<PackageId, T>>,
+		serializer :S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.collect_map(map.iter().map(|(k, v)| (k, Entries(v))))
+	}
+
+	pub(crate) fn deserialize<'de, K :Deserialize<'de> + Ord, T :Deserialize<'de>, D :Deserializer<'de>>(
+		deserializer :D,
+	) -> Result<BTreeMap<K, BTreeMap<PackageId, T>>, D::Error> {
+		let map = BTreeMap::<K, Owned<T>>::deserialize(deserializer)?;
+		Ok(map.into_iter().map(|(k, v)| (k, v.0)).collect())
+	}
+}
+
+/// A package, or the [`PackageId`] string the reports before `schema_version` 4 gave outside the
+/// maps.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CompatKey {
+	Key(PackageKey),
+	ById(PackageId),
+}
+
+impl CompatKey {
+	fn id(self) -> PackageId {
+		match self {
+			Self::Key(key) => key.id(),
+			Self::ById(id) => id,
+		}
+	}
+}
+
+/// For `#[serde(with = "package_key::id")]` on `PackageId`.
+pub(crate) mod id {
+	use super::*;
+
+	pub(crate) fn serialize<S :Serializer>(id :&PackageId, serializer :S) -> Result<S::Ok, S::Error> {
+		PackageKey::new(*id).serialize(serializer)
+	}
+
+	pub(crate) fn deserialize<'de, D :Deserializer<'de>>(deserializer :D) -> Result<PackageId, D::Error> {
+		Ok(CompatKey::deserialize(deserializer)?.id())
+	}
+}
+
+/// For `#[serde(with = "package_key::option")]` on `Option<PackageId>`.
+pub(crate) mod option {
+	use super::*;
+
+	pub(crate) fn serialize<S :Serializer>(id :&Option<PackageId>, serializer :S) -> Result<S::Ok, S::Error> {
+		id.map(PackageKey::new).serialize(serializer)
+	}
+
+	pub(crate) fn deserialize<'de, D :Deserializer<'de>>(deserializer :D) -> Result<Option<PackageId>, D::Error> {
+		Ok(Option::<CompatKey>::deserialize(deserializer)?.map(CompatKey::id))
+	}
+}
+
+/// For `#[serde(with = "package_key::set")]` on `BTreeSet<PackageId>`.
+pub(crate) mod set {
+	use super::*;
+
+	pub(crate) fn serialize<S :Serializer>(set :&BTreeSet<PackageId>, serializer :S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(set.iter().map(|&id| PackageKey::new(id)))
+	}
+
+	pub(crate) fn deserialize<'de, D :Deserializer<'de>>(deserializer :D) -> Result<BTreeSet<PackageId>, D::Error> {
+		Ok(Vec::<CompatKey>::deserialize(deserializer)?.into_iter().map(CompatKey::id).collect())
+	}
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use failure::{Fallible, ResultExt as _};
+
+/// A command which can be inspected and modified before it is run.
+#[derive(Clone, Debug)]
+pub struct ProcessBuilder {
+	program :OsString,
+	args :Vec<OsString>,
+	cwd :Option<PathBuf>,
+	env :BTreeMap<String, OsString>,
+}
+
+impl ProcessBuilder {
+	pub fn new(program :impl AsRef<OsStr>) -> Self {
+		Self { program : program.as_ref().to_owned(), args : vec![], cwd : None, env : BTreeMap::new() }
+	}
+
+	pub fn program(&mut self, program :impl AsRef<OsStr>) -> &mut Self {
+		self.program = program.as_ref().to_owned();
+		self
+	}
+
+	pub fn arg(&mut self, arg :impl AsRef<OsStr>) -> &mut Self {
+		self.args.push(arg.as_ref().to_owned());
+		self
+	}
+
+	pub fn args(&mut self, args :impl IntoIterator<Item = impl AsRef<OsStr>>) -> &mut Self {
+		self.args.extend(args.into_iter().map(|a| a.as_ref().to_owned()));
+		self
+	}
+
+	pub fn args_replace(&mut self, args :&[OsString]) -> &mut Self {
+		self.args = args.to_vec();
+		self
+	}
+
+	pub fn cwd(&mut self, path :impl AsRef<Path>) -> &mut Self {
+		self.cwd = Some(path.as_ref().to_owned());
+		self
+	}
+
+	pub fn env(&mut self, key :&str, val :impl AsRef<OsStr>) -> &mut Self {
+		self.env.insert(key.to_owned(), val.as_ref().to_owned());
+		self
+	}
+
+	pub fn get_program(&self) -> &OsString {
+		&self.program
+	}
+
+	pub fn get_args(&self) -> &[OsString] {
+		&self.args
+	}
+
+	pub fn get_cwd(&self) -> Option<&Path> {
+		self.cwd.as_deref()
+	}
+
+	pub fn build_command(&self) -> Command {
+		let mut command = Command::new(&self.program);
+		command.args(&self.args).envs(&self.env);
+		if let Some(cwd) = &self.cwd {
+			command.current_dir(cwd);
+		}
+		command
+	}
+
+	/// Runs the command, failing with its stderr unless it succeeds.
+	pub fn exec_with_output(&self) -> Fallible<Output> {
+		tracing::info!("running {}", self);
+		let output = self
+			.build_command()
+			.output()
+			.with_context(|_| format!("could not execute process {}", self))?;
+		if !output.status.success() {
+			return Err(failure::format_err!(
+				"process didn't exit successfully: {} ({})\n--- stderr\n{}",
+				self,
+				output.status,
+				String::from_utf8_lossy(&output.stderr),
+			));
+		}
+		Ok(output)
+	}
+}
+
+impl fmt::Display for ProcessBuilder {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "`{}", self.program.to_string_lossy())?;
+		for arg in &self.args {
+			write!(f, " {}", arg.to_string_lossy())?;
+		}
+		write!(f, "`")
+	}
+}
@@ -0,0 +1,213 @@
+//! Status messages on stderr, in the same style as Cargo's.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use ansi_term::{Colour, Style};
+use failure::Fallible;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Verbosity {
+	Verbose,
+	Normal,
+	Quiet,
+}
+
+pub struct Shell {
+	err :Box<dyn Write>,
+	/// Whether `err` is a terminal.
+	tty :bool,
+	color :bool,
+	/// Whether stdout is a terminal.
+	out_tty :bool,
+	/// Whether the human report on stdout is colored.
+	out_color :bool,
+	/// Whether to show progress bars.
+	progress :bool,
+	/// Whether a progress bar is on the last line of `err`.
+	progress_shown :bool,
+	verbosity :Verbosity,
+}
+
+impl Shell {
+	/// Writes to stderr, with colors if it is a terminal. The report on stdout is colored if stdout
+	/// is one.
+	pub fn new() -> Self {
+		let tty = atty::is(atty::Stream::Stderr);
+		let out_tty = atty::is(atty::Stream::Stdout);
+		Self {
+			err : Box::new(io::stderr()),
+			tty,
+			color : tty,
+			out_tty,
+			out_color : out_tty,
+			progress : tty,
+			progress_shown : false,
+			verbosity : Verbosity::Normal,
+		}
+	}
+
+	/// Writes to `out` without colors.
+	pub fn from_write(out :Box<dyn Write>) -> Self {
+		Self {
+			err : out,
+			tty : false,
+			color : false,
+			out_tty : false,
+			out_color : false,
+			progress : false,
+			progress_shown : false,
+			verbosity : Verbosity::Normal,
+		}
+	}
+
+	pub fn verbosity(&self) -> Verbosity {
+		self.verbosity
+	}
+
+	pub fn set_verbosity(&mut self, verbosity :Verbosity) {
+		self.verbosity = verbosity;
+	}
+
+	/// `--color`: `auto`, `always` or `never`.
+	pub fn set_color_choice(&mut self, color :Option<&str>) -> Fallible<()> {
+		let (color, out_color) = match color {
+			None | Some("auto") => (self.tty, self.out_tty),
+			Some("always") => (true, true),
+			Some("never") => (false, false),
+			Some(color) => return Err(failure::format_err!(
+				"argument for --color must be auto, always, or never, but found `{}`",
+				color,
+			)),
+		};
+		self.color = color;
+		self.out_color = out_color;
+		Ok(())
+	}
+
+	pub fn supports_color(&self) -> bool {
+		self.color
+	}
+
+	/// Whether the human report on stdout is colored.
+	pub fn out_supports_color(&self) -> bool {
+		self.out_color
+	}
+
+	/// `term.progress.when`: `auto`, `always` or `never`.
+	pub fn set_progress_choice(&mut self, when :Option<&str>) -> Fallible<()> {
+		self.progress = match when {
+			None | Some("auto") => self.tty,
+			Some("always") => true,
+			Some("never") => false,
+			Some(when) => return Err(failure::format_err!(
+				"`term.progress.when` must be auto, always, or never, but found `{}`",
+				when,
+			)),
+		};
+		Ok(())
+	}
+
+	/// Whether progress bars are shown. Never with `--quiet`.
+	pub fn supports_progress(&self) -> bool {
+		self.progress && self.verbosity != Verbosity::Quiet
+	}
+
+	/// e.g. `   Analyzing [=====>      ] 3/10: foo`, which replaces the previous one. Shown only if
+	/// [`supports_progress`](Self::supports_progress).
+	pub fn progress(
+		&mut self,
+		status :impl fmt::Display,
+		current :usize,
+		total :usize,
+		message :impl fmt::Display,
+	) -> Fallible<()> {
+		const WIDTH :usize = 25;
+		if !self.supports_progress() || total == 0 {
+			return Ok(());
+		}
+		let filled = WIDTH * current.min(total) / total;
+		let bar = if filled < WIDTH {
+			format!("{}>{}", "=".repeat(filled), " ".repeat(WIDTH - filled - 1))
+		} else {
+			"=".repeat(WIDTH)
+		};
+		let status = self.paint(Colour::Cyan.bold(), &format!("{:>12}", status));
+		write!(self.err, "\r{} [{}] {}/{}: {}\x1b[K", status, bar, current, total, message)?;
+		self.err.flush()?;
+		self.progress_shown = true;
+		Ok(())
+	}
+
+	/// Erases the progress bar, if any.
+	pub fn clear_progress(&mut self) -> Fallible<()> {
+		if self.progress_shown {
+			write!(self.err, "\r\x1b[K")?;
+			self.err.flush()?;
+			self.progress_shown = false;
+		}
+		Ok(())
+	}
+
+	pub fn err(&mut self) -> &mut dyn Write {
+		let _ = self.clear_progress();
+		&mut self.err
+	}
+
+	/// e.g. `   Compiling foo`. Hidden with `--quiet`.
+	pub fn status(&mut self, status :impl fmt::Display, message :impl fmt::Display) -> Fallible<()> {
+		if self.verbosity == Verbosity::Quiet {
+			return Ok(());
+		}
+		self.clear_progress()?;
+		let status = format!("{:>12}", status);
+		let status = self.paint(Colour::Green.bold(), &status);
+		writeln!(self.err, "{} {}", status, message)?;
+		Ok(())
+	}
+
+	/// Hidden with `--quiet`.
+	pub fn warn(&mut self, message :impl fmt::Display) -> Fallible<()> {
+		if self.verbosity == Verbosity::Quiet {
+			return Ok(());
+		}
+		self.print(Colour::Yellow.bold(), "warning:", message)
+	}
+
+	pub fn error(&mut self, message :impl fmt::Display) -> Fallible<()> {
+		self.print(Colour::Red.bold(), "error:", message)
+	}
+
+	pub fn info(&mut self, message :impl fmt::Display) -> Fallible<()> {
+		self.print(Colour::Cyan.bold(), "info:", message)
+	}
+
+	/// Runs `callback` only with `--verbose`.
+	pub fn verbose(&mut self, callback :impl FnOnce(&mut Self) -> Fallible<()>) -> Fallible<()> {
+		match self.verbosity {
+			Verbosity::Verbose => callback(self),
+			_ => Ok(()),
+		}
+	}
+
+	fn print(&mut self, style :Style, label :&str, message :impl fmt::Display) -> Fallible<()> {
+		self.clear_progress()?;
+		let label = self.paint(style, label);
+		writeln!(self.err, "{} {}", label, message)?;
+		Ok(())
+	}
+
+	fn paint(&self, style :Style, s :&str) -> String {
+		if self.color {
+			style.paint(s).to_string()
+		} else {
+			s.to_owned()
+		}
+	}
+}
+
+impl Default for Shell {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,838 @@
+//! `--backend source`: finds the crates referred from the sources without compiling them.
+//!
+//! This is faster but less precise than the other backends. `cfg`s are ignored, and a local
+//! item named the same as a dependency counts as a usage of it.
+//!
+//! `--unused-features` compares the items of the dependencies behind `#[cfg(feature = "..")]`
+//! with the ones referred from the sources in the same way, and the dependencies referred only
+//! behind `#[cfg(unix)]` and so on are suggested to be platform-specific.
+//!
+//! `--classify-usage` tells from the syntax how the used dependencies are used, e.g. only by
+//! `#[derive(..)]`s.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{Fallible, ResultExt as _};
+use proc_macro2::{TokenStream, TokenTree};
+use syn::visit::{self, Visit};
+
+use crate::usage::Location;
+use crate::UsageKind;
+
+/// Returns the first segments of the paths (e.g. `foo` of `foo::bar()`) in the module tree of
+/// the crate root `root`, with the lines they are on.
+pub(crate) fn referred_locations(root :&Path) -> Fallible<HashMap<String, BTreeSet<Location>>> {
+	Ok(references(root)?.into_iter().map(|(name, r)| (name, r.locations)).collect())
+}
+
+/// Returns the names [`referred_locations`] returns, with the platform-specific `cfg`s (e.g.
+/// `unix` or `target_os = "linux"`) of the places they are referred from. `None` stands for
+/// the places behind no such `cfg`s.
+pub(crate) fn referred_names_by_platform(root :&Path) -> Fallible<HashMap<String, BTreeSet<Option<String>>>> {
+	Ok(references(root)?.into_iter().map(|(name, r)| (name, r.platforms)).collect())
+}
+
+/// The places a name is referred from.
+#[derive(Default)]
+struct References {
+	platforms :BTreeSet<Option<String>>,
+	locations :BTreeSet<Location>,
+}
+
+fn references(root :&Path) -> Fallible<HashMap<String, References>> {
+	let mut names = HashMap::new();
+	walk(root, vec![], |file, path, parent, dir, platforms| {
+		let mut visitor = Visitor { names : &mut names, platforms, path, parent, dir, mods : vec![] };
+		for attr in &file.attrs {
+			visitor.platforms.extend(cfg_platform(attr));
+		}
+		visitor.visit_file(file);
+		visitor.mods
+	})?;
+	Ok(names)
+}
+
+/// Returns the names (e.g. `Serialize` of `serde::Serialize` or `use serde::{Serialize};`) in
+/// the paths starting with `crate_name`, or `None` if a glob import hides them.
+pub(crate) fn referred_items(root :&Path, crate_name :&str) -> Fallible<Option<HashSet<String>>> {
+	let mut visitor = ItemsVisitor { crate_name, names : HashSet::new(), glob : false, mods : vec![] };
+	walk(root, (), |file, _, parent, dir, ()| {
+		visitor.visit_file(file);
+		visitor
+			.mods
+			.drain(..)
+			.filter_map(|item| mod_file(&item, parent, &dir))
+			.map(|(path, is_mod_rs)| (path, is_mod_rs, ()))
+			.collect()
+	})?;
+	Ok(if visitor.glob { None } else { Some(visitor.names) })
+}
+
+/// Returns how the module tree of the crate root `root` uses each of `crates`, by the names given
+/// to `--extern`. The crates it does not refer to are omitted.
+pub(crate) fn usage_kinds(root :&Path, crates :&HashSet<String>) -> Fallible<HashMap<String, BTreeSet<UsageKind>>> {
+	let mut visitor = KindsVisitor {
+		crates,
+		imports : HashMap::new(),
+		imported : HashSet::new(),
+		kinds : HashMap::new(),
+		public_api : false,
+		mods : vec![],
+	};
+	walk(root, (), |file, _, parent, dir, ()| {
+		visitor.imports.clear();
+		visitor.insert_imports(&file.items);
+		visitor.visit_file(file);
+		visitor
+			.mods
+			.drain(..)
+			.filter_map(|item| mod_file(&item, parent, &dir))
+			.map(|(path, is_mod_rs)| (path, is_mod_rs, ()))
+			.collect()
+	})?;
+	// e.g. traits imported for their methods.
+	for krate in visitor.imported {
+		visitor.kinds.entry(krate).or_insert_with(|| vec![UsageKind::Other].into_iter().collect());
+	}
+	Ok(visitor.kinds)
+}
+
+/// Returns the names of the items behind `#[cfg(feature = "..")]` in the crate `root`, by
+/// feature.
+pub(crate) fn feature_gated_items(root :&Path) -> Fallible<HashMap<String, HashSet<String>>> {
+	let mut items = HashMap::new();
+	walk(root, vec![], |file, _, parent, dir, features| {
+		let mut visitor = GatedVisitor { items : &mut items, features, parent, dir, mods : vec![] };
+		for attr in &file.attrs {
+			visitor.features.extend(cfg_features(attr));
+		}
+		for item in &file.items {
+			visitor.visit_item(item);
+		}
+		visitor.mods
+	})?;
+	Ok(items)
+}
+
+/// Parses the files in the module tree of the crate root `root`.
+///
+/// `visit` is given a file, its path, the directory of the file, the directory of its submodules
+/// and a value inherited from its parent module. It returns the files of the submodules.
+/// Submodules which cannot be read or parsed are skipped.
+fn walk<T>(
+	root :&Path,
+	init :T,
+	mut visit :impl FnMut(&syn::File, &Path, &Path, PathBuf, T) -> Vec<(PathBuf, bool, T)>,
+) -> Fallible<()> {
+	let mut files = vec![(root.to_owned(), true, init)];
+	let mut visited = HashSet::new();
+	while let Some((path, is_mod_rs, inherited)) = files.pop() {
+		if !visited.insert(path.clone()) {
+			continue;
+		}
+		let file = fs::read_to_string(&path)
+			.with_context(|_| format!("could not read `{}`", path.display()))
+			.and_then(|content| {
+				syn::parse_file(&content).with_context(|_| format!("could not parse `{}`", path.display()))
+			});
+		// A module file may be missing or use a syntax `syn` does not know if `cfg`s exclude it,
+		// e.g. `#[cfg(any())] mod foo;`.
+		let file = match file {
+			Ok(file) => file,
+			Err(_) if path != root => continue,
+			Err(err) => return Err(err.into()),
+		};
+
+		let parent = path.parent().unwrap_or_else(|| Path::new(""));
+		// `mod foo;` in `src/lib.rs` or `src/bar/mod.rs` refers to `foo.rs` next to them, and
+		// in `src/bar.rs` to `src/bar/foo.rs`.
+		let dir = if is_mod_rs {
+			parent.to_owned()
+		} else {
+			parent.join(path.file_stem().unwrap_or_default())
+		};
+		files.extend(visit(&file, &path, parent, dir, inherited));
+	}
+	Ok(())
+}
+
+/// The file of `mod foo;` and whether it is `mod.rs`-like, or `None` if the module is inline.
+fn mod_file(item :&syn::ItemMod, parent :&Path, dir :&Path) -> Option<(PathBuf, bool)> {
+	if item.content.is_some() {
+		return None;
+	}
+	let name = item.ident.to_string();
+	Some(match path_attr(item) {
+		Some(path) => (parent.join(path), true),
+		None if dir.join(format!("{}.rs", name)).exists() => (dir.join(format!("{}.rs", name)), false),
+		None => (dir.join(&name).join("mod.rs"), true),
+	})
+}
+
+/// The directory of the submodules of the inline module `item`.
+fn inline_mod_dir(item :&syn::ItemMod, dir :&Path) -> PathBuf {
+	dir.join(path_attr(item).unwrap_or_else(|| item.ident.to_string()))
+}
+
+/// `#[path = ".."]`
+fn path_attr(item :&syn::ItemMod) -> Option<String> {
+	item.attrs.iter().filter(|a| a.path.is_ident("path")).find_map(|a| match a.parse_meta() {
+		Ok(syn::Meta::NameValue(syn::MetaNameValue { lit : syn::Lit::Str(s), .. })) => Some(s.value()),
+		_ => None,
+	})
+}
+
+/// The features in `#[cfg(..)]`, e.g. `foo` and `bar` of `#[cfg(any(feature = "foo", feature = "bar"))]`.
+fn cfg_features(attr :&syn::Attribute) -> Vec<String> {
+	fn collect(meta :&syn::Meta, features :&mut Vec<String>) {
+		match meta {
+			syn::Meta::NameValue(syn::MetaNameValue { path, lit : syn::Lit::Str(s), .. }) if path.is_ident("feature") => {
+				features.push(s.value());
+			}
+			// A feature under `not` does not enable the item.
+			syn::Meta::List(list) if !list.path.is_ident("not") => {
+				for nested in &list.nested {
+					if let syn::NestedMeta::Meta(meta) = nested {
+						collect(meta, features);
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let mut features = vec![];
+	if attr.path.is_ident("cfg") {
+		if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+			for nested in &list.nested {
+				if let syn::NestedMeta::Meta(meta) = nested {
+					collect(meta, &mut features);
+				}
+			}
+		}
+	}
+	features
+}
+
+struct Visitor<'a> {
+	names :&'a mut HashMap<String, References>,
+	/// The platform-specific `cfg`s the current item is behind.
+	platforms :Vec<String>,
+	/// The current file.
+	path :&'a Path,
+	/// The directory of the current file, which `#[path]` is relative to.
+	parent :&'a Path,
+	/// The directory of the files of the submodules.
+	dir :PathBuf,
+	/// The files of `mod foo;`s, whether they are `mod.rs`-like, and the `cfg`s they are behind.
+	mods :Vec<(PathBuf, bool, Vec<String>)>,
+}
+
+impl Visitor<'_> {
+	fn insert(&mut self, ident :&proc_macro2::Ident) {
+		let platform = match &*self.platforms {
+			[] => None,
+			[platform] => Some(platform.clone()),
+			platforms => Some(format!("all({})", platforms.join(", "))),
+		};
+		let location = Location { file : self.path.to_owned(), line : ident.span().start().line as u32 };
+		let references = self.names.entry(ident.to_string()).or_default();
+		references.platforms.insert(platform);
+		references.locations.insert(location);
+	}
+
+	/// Visits the item with `attrs` by `visit`, with the `cfg`s in `attrs`.
+	fn with_cfgs(&mut self, attrs :&[syn::Attribute], visit :impl FnOnce(&mut Self)) {
+		let num_platforms = self.platforms.len();
+		self.platforms.extend(attrs.iter().filter_map(cfg_platform));
+		visit(self);
+		self.platforms.truncate(num_platforms);
+	}
+
+	fn insert_use_tree_roots(&mut self, tree :&syn::UseTree) {
+		match tree {
+			syn::UseTree::Path(syn::UsePath { ident, .. })
+			| syn::UseTree::Name(syn::UseName { ident })
+			| syn::UseTree::Rename(syn::UseRename { ident, .. }) => {
+				self.insert(ident);
+			}
+			syn::UseTree::Group(group) => {
+				for tree in &group.items {
+					self.insert_use_tree_roots(tree);
+				}
+			}
+			syn::UseTree::Glob(_) => {}
+		}
+	}
+
+	/// Macro inputs are not parsed. Take every `foo` followed by `::`.
+	fn insert_token_paths(&mut self, tokens :TokenStream) {
+		let mut tokens = tokens.into_iter().peekable();
+		while let Some(token) = tokens.next() {
+			match token {
+				TokenTree::Ident(ident) => {
+					if let Some(TokenTree::Punct(punct)) = tokens.peek() {
+						if punct.as_char() == ':' && punct.spacing() == proc_macro2::Spacing::Joint {
+							self.insert(&ident);
+						}
+					}
+				}
+				TokenTree::Group(group) => self.insert_token_paths(group.stream()),
+				TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+			}
+		}
+	}
+}
+
+impl<'ast> Visit<'ast> for Visitor<'_> {
+	fn visit_path(&mut self, path :&'ast syn::Path) {
+		if let Some(first) = path.segments.first() {
+			self.insert(&first.ident);
+		}
+		visit::visit_path(self, path);
+	}
+
+	fn visit_item_use(&mut self, item :&'ast syn::ItemUse) {
+		for attr in &item.attrs {
+			self.visit_attribute(attr);
+		}
+		// Only the roots may be crates.
+		self.insert_use_tree_roots(&item.tree);
+	}
+
+	fn visit_item_extern_crate(&mut self, item :&'ast syn::ItemExternCrate) {
+		self.insert(&item.ident);
+		visit::visit_item_extern_crate(self, item);
+	}
+
+	fn visit_item(&mut self, item :&'ast syn::Item) {
+		let attrs :&[_] = match item {
+			syn::Item::Const(i) => &i.attrs,
+			syn::Item::Enum(i) => &i.attrs,
+			syn::Item::ExternCrate(i) => &i.attrs,
+			syn::Item::Fn(i) => &i.attrs,
+			syn::Item::ForeignMod(i) => &i.attrs,
+			syn::Item::Impl(i) => &i.attrs,
+			syn::Item::Macro(i) => &i.attrs,
+			syn::Item::Macro2(i) => &i.attrs,
+			syn::Item::Mod(i) => &i.attrs,
+			syn::Item::Static(i) => &i.attrs,
+			syn::Item::Struct(i) => &i.attrs,
+			syn::Item::Trait(i) => &i.attrs,
+			syn::Item::TraitAlias(i) => &i.attrs,
+			syn::Item::Type(i) => &i.attrs,
+			syn::Item::Union(i) => &i.attrs,
+			syn::Item::Use(i) => &i.attrs,
+			_ => &[],
+		};
+		self.with_cfgs(attrs, |this| visit::visit_item(this, item));
+	}
+
+	fn visit_impl_item(&mut self, item :&'ast syn::ImplItem) {
+		let attrs :&[_] = match item {
+			syn::ImplItem::Const(i) => &i.attrs,
+			syn::ImplItem::Method(i) => &i.attrs,
+			syn::ImplItem::Type(i) => &i.attrs,
+			syn::ImplItem::Macro(i) => &i.attrs,
+			_ => &[],
+		};
+		self.with_cfgs(attrs, |this| visit::visit_impl_item(this, item));
+	}
+
+	fn visit_local(&mut self, local :&'ast syn::Local) {
+		self.with_cfgs(&local.attrs, |this| visit::visit_local(this, local));
+	}
+
+	fn visit_stmt(&mut self, stmt :&'ast syn::Stmt) {
+		// e.g. `#[cfg(unix)] foo::bar();`
+		let attrs :&[_] = match stmt {
+			syn::Stmt::Expr(syn::Expr::Call(e)) | syn::Stmt::Semi(syn::Expr::Call(e), _) => &e.attrs,
+			syn::Stmt::Expr(syn::Expr::MethodCall(e)) | syn::Stmt::Semi(syn::Expr::MethodCall(e), _) => &e.attrs,
+			syn::Stmt::Expr(syn::Expr::Macro(e)) | syn::Stmt::Semi(syn::Expr::Macro(e), _) => &e.attrs,
+			syn::Stmt::Expr(syn::Expr::Block(e)) | syn::Stmt::Semi(syn::Expr::Block(e), _) => &e.attrs,
+			_ => &[],
+		};
+		self.with_cfgs(attrs, |this| visit::visit_stmt(this, stmt));
+	}
+
+	fn visit_attribute(&mut self, attr :&'ast syn::Attribute) {
+		self.insert_token_paths(attr.tokens.clone());
+		visit::visit_attribute(self, attr);
+	}
+
+	fn visit_macro(&mut self, mac :&'ast syn::Macro) {
+		self.insert_token_paths(mac.tokens.clone());
+		visit::visit_macro(self, mac);
+	}
+
+	fn visit_item_mod(&mut self, item :&'ast syn::ItemMod) {
+		for attr in &item.attrs {
+			self.visit_attribute(attr);
+		}
+		match &item.content {
+			None => {
+				if let Some((path, is_mod_rs)) = mod_file(item, self.parent, &self.dir) {
+					self.mods.push((path, is_mod_rs, self.platforms.clone()));
+				}
+			}
+			Some((_, items)) => {
+				let inline_dir = inline_mod_dir(item, &self.dir);
+				let dir = std::mem::replace(&mut self.dir, inline_dir);
+				for item in items {
+					self.visit_item(item);
+				}
+				self.dir = dir;
+			}
+		}
+	}
+}
+
+struct ItemsVisitor<'a> {
+	crate_name :&'a str,
+	names :HashSet<String>,
+	glob :bool,
+	/// `mod foo;`s in the current file.
+	mods :Vec<syn::ItemMod>,
+}
+
+impl ItemsVisitor<'_> {
+	fn insert_use_tree(&mut self, tree :&syn::UseTree, in_crate :bool) {
+		match tree {
+			syn::UseTree::Path(syn::UsePath { ident, tree, .. }) => {
+				if in_crate {
+					self.names.insert(ident.to_string());
+				}
+				self.insert_use_tree(tree, in_crate || ident == self.crate_name);
+			}
+			syn::UseTree::Name(syn::UseName { ident }) | syn::UseTree::Rename(syn::UseRename { ident, .. }) => {
+				if in_crate {
+					self.names.insert(ident.to_string());
+				}
+			}
+			syn::UseTree::Group(group) => {
+				for tree in &group.items {
+					self.insert_use_tree(tree, in_crate);
+				}
+			}
+			syn::UseTree::Glob(_) => self.glob |= in_crate,
+		}
+	}
+
+	/// Macro inputs are not parsed. Take every identifier after `crate_name ::`.
+	fn insert_token_paths(&mut self, tokens :TokenStream) {
+		let mut in_crate = false;
+		for token in tokens {
+			match token {
+				TokenTree::Ident(ident) => {
+					if in_crate {
+						self.names.insert(ident.to_string());
+					} else {
+						in_crate = ident == self.crate_name;
+					}
+				}
+				TokenTree::Punct(punct) if punct.as_char() == ':' => {}
+				TokenTree::Group(group) => {
+					in_crate = false;
+					self.insert_token_paths(group.stream());
+				}
+				TokenTree::Punct(_) | TokenTree::Literal(_) => in_crate = false,
+			}
+		}
+	}
+}
+
+impl<'ast> Visit<'ast> for ItemsVisitor<'_> {
+	fn visit_path(&mut self, path :&'ast syn::Path) {
+		if path.segments.first().map_or(false, |s| s.ident == self.crate_name) {
+			self.names.extend(path.segments.iter().skip(1).map(|s| s.ident.to_string()));
+		}
+		visit::visit_path(self, path);
+	}
+
+	fn visit_item_use(&mut self, item :&'ast syn::ItemUse) {
+		self.insert_use_tree(&item.tree, false);
+	}
+
+	fn visit_attribute(&mut self, attr :&'ast syn::Attribute) {
+		self.insert_token_paths(attr.tokens.clone());
+		visit::visit_attribute(self, attr);
+	}
+
+	fn visit_macro(&mut self, mac :&'ast syn::Macro) {
+		self.insert_token_paths(mac.tokens.clone());
+		visit::visit_macro(self, mac);
+	}
+
+	fn visit_item_mod(&mut self, item :&'ast syn::ItemMod) {
+		if item.content.is_none() {
+			self.mods.push(item.clone());
+		}
+		visit::visit_item_mod(self, item);
+	}
+}
+
+struct KindsVisitor<'a> {
+	crates :&'a HashSet<String>,
+	/// The names the current file imports from `crates` (e.g. `Serialize` of
+	/// `use serde::Serialize;`), with the crates.
+	imports :HashMap<String, String>,
+	/// `crates` imported with private `use`s or `extern crate`s.
+	imported :HashSet<String>,
+	kinds :HashMap<String, BTreeSet<UsageKind>>,
+	/// Whether the current types are in the signature of a `pub` item.
+	public_api :bool,
+	/// `mod foo;`s in the current file.
+	mods :Vec<syn::ItemMod>,
+}
+
+impl KindsVisitor<'_> {
+	fn insert_imports(&mut self, items :&[syn::Item]) {
+		for item in items {
+			match item {
+				syn::Item::Use(syn::ItemUse { tree : syn::UseTree::Path(path), .. }) => {
+					let krate = path.ident.to_string();
+					if self.crates.contains(&krate) {
+						let mut names = vec![];
+						use_tree_leaves(&path.tree, &mut names);
+						self.imports.extend(names.into_iter().map(|name| (name, krate.clone())));
+					}
+				}
+				syn::Item::Mod(syn::ItemMod { content : Some((_, items)), .. }) => self.insert_imports(items),
+				_ => {}
+			}
+		}
+	}
+
+	/// The crate `path` refers to, if it is one of `crates`.
+	fn resolve(&self, path :&syn::Path) -> Option<String> {
+		let first = path.segments.first()?.ident.to_string();
+		if path.segments.len() > 1 && self.crates.contains(&first) {
+			Some(first)
+		} else {
+			self.imports.get(&first).cloned()
+		}
+	}
+
+	fn insert(&mut self, path :&syn::Path, kind :UsageKind) {
+		if let Some(krate) = self.resolve(path) {
+			self.kinds.entry(krate).or_default().insert(kind);
+		}
+	}
+
+	/// Visits the item by `visit` as a part of the public API if `vis` is `pub`.
+	fn with_public_api(&mut self, public_api :bool, visit :impl FnOnce(&mut Self)) {
+		let outer = std::mem::replace(&mut self.public_api, public_api);
+		visit(self);
+		self.public_api = outer;
+	}
+
+	/// Macro inputs are not parsed. Take every crate followed by `::`.
+	fn insert_token_paths(&mut self, tokens :TokenStream) {
+		let mut tokens = tokens.into_iter().peekable();
+		while let Some(token) = tokens.next() {
+			match token {
+				TokenTree::Ident(ident) => {
+					let krate = ident.to_string();
+					if let Some(TokenTree::Punct(punct)) = tokens.peek() {
+						if punct.as_char() == ':' && self.crates.contains(&krate) {
+							self.kinds.entry(krate).or_default().insert(UsageKind::Other);
+						}
+					}
+				}
+				TokenTree::Group(group) => self.insert_token_paths(group.stream()),
+				TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+			}
+		}
+	}
+}
+
+fn is_pub(vis :&syn::Visibility) -> bool {
+	match vis {
+		syn::Visibility::Public(_) => true,
+		_ => false,
+	}
+}
+
+impl<'ast> Visit<'ast> for KindsVisitor<'_> {
+	fn visit_path(&mut self, path :&'ast syn::Path) {
+		let kind = if self.public_api { UsageKind::PublicApi } else { UsageKind::Other };
+		self.insert(path, kind);
+		visit::visit_path(self, path);
+	}
+
+	fn visit_macro(&mut self, mac :&'ast syn::Macro) {
+		self.insert(&mac.path, UsageKind::Macro);
+		self.insert_token_paths(mac.tokens.clone());
+	}
+
+	fn visit_attribute(&mut self, attr :&'ast syn::Attribute) {
+		if attr.path.is_ident("derive") {
+			if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+				for nested in &list.nested {
+					if let syn::NestedMeta::Meta(syn::Meta::Path(path)) = nested {
+						self.insert(path, UsageKind::Derive);
+					}
+				}
+			}
+		} else {
+			// e.g. `#[tokio::main]`
+			self.insert(&attr.path, UsageKind::Macro);
+		}
+	}
+
+	fn visit_item_use(&mut self, item :&'ast syn::ItemUse) {
+		if let syn::UseTree::Path(path) = &item.tree {
+			let krate = path.ident.to_string();
+			if self.crates.contains(&krate) {
+				if is_pub(&item.vis) {
+					self.kinds.entry(krate).or_default().insert(UsageKind::ReExport);
+				} else {
+					self.imported.insert(krate);
+				}
+			}
+		}
+	}
+
+	fn visit_item_extern_crate(&mut self, item :&'ast syn::ItemExternCrate) {
+		let krate = item.ident.to_string();
+		if self.crates.contains(&krate) {
+			if is_pub(&item.vis) {
+				self.kinds.entry(krate).or_default().insert(UsageKind::ReExport);
+			} else {
+				self.imported.insert(krate);
+			}
+		}
+	}
+
+	fn visit_item_impl(&mut self, item :&'ast syn::ItemImpl) {
+		for attr in &item.attrs {
+			self.visit_attribute(attr);
+		}
+		if let Some((_, path, _)) = &item.trait_ {
+			self.insert(path, UsageKind::TraitImpl);
+		}
+		self.visit_generics(&item.generics);
+		self.visit_type(&item.self_ty);
+		for item in &item.items {
+			self.visit_impl_item(item);
+		}
+	}
+
+	fn visit_item_fn(&mut self, item :&'ast syn::ItemFn) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_item_fn(this, item));
+	}
+
+	fn visit_impl_item_method(&mut self, item :&'ast syn::ImplItemMethod) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_impl_item_method(this, item));
+	}
+
+	fn visit_item_struct(&mut self, item :&'ast syn::ItemStruct) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_item_struct(this, item));
+	}
+
+	fn visit_field(&mut self, field :&'ast syn::Field) {
+		let public_api = self.public_api && is_pub(&field.vis);
+		self.with_public_api(public_api, |this| visit::visit_field(this, field));
+	}
+
+	fn visit_item_enum(&mut self, item :&'ast syn::ItemEnum) {
+		for attr in &item.attrs {
+			self.visit_attribute(attr);
+		}
+		// The fields of the variants are as public as the enum.
+		self.with_public_api(is_pub(&item.vis), |this| {
+			this.visit_generics(&item.generics);
+			for variant in &item.variants {
+				for attr in &variant.attrs {
+					this.visit_attribute(attr);
+				}
+				for field in &variant.fields {
+					for attr in &field.attrs {
+						this.visit_attribute(attr);
+					}
+					this.visit_type(&field.ty);
+				}
+			}
+		});
+	}
+
+	fn visit_item_trait(&mut self, item :&'ast syn::ItemTrait) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_item_trait(this, item));
+	}
+
+	fn visit_item_type(&mut self, item :&'ast syn::ItemType) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_item_type(this, item));
+	}
+
+	fn visit_item_const(&mut self, item :&'ast syn::ItemConst) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_item_const(this, item));
+	}
+
+	fn visit_item_static(&mut self, item :&'ast syn::ItemStatic) {
+		self.with_public_api(is_pub(&item.vis), |this| visit::visit_item_static(this, item));
+	}
+
+	// The bodies are not a part of the signatures.
+	fn visit_block(&mut self, block :&'ast syn::Block) {
+		self.with_public_api(false, |this| visit::visit_block(this, block));
+	}
+
+	fn visit_expr(&mut self, expr :&'ast syn::Expr) {
+		self.with_public_api(false, |this| visit::visit_expr(this, expr));
+	}
+
+	fn visit_item_mod(&mut self, item :&'ast syn::ItemMod) {
+		if item.content.is_none() {
+			self.mods.push(item.clone());
+		}
+		visit::visit_item_mod(self, item);
+	}
+}
+
+struct GatedVisitor<'a> {
+	items :&'a mut HashMap<String, HashSet<String>>,
+	/// The features the current module is behind.
+	features :Vec<String>,
+	parent :&'a Path,
+	dir :PathBuf,
+	/// The files of `mod foo;`s, whether they are `mod.rs`-like, and the features they are
+	/// behind.
+	mods :Vec<(PathBuf, bool, Vec<String>)>,
+}
+
+impl GatedVisitor<'_> {
+	fn visit_item(&mut self, item :&syn::Item) {
+		let name = |vis :&syn::Visibility, ident :&syn::Ident| match vis {
+			syn::Visibility::Public(_) => vec![ident.to_string()],
+			_ => vec![],
+		};
+		// Only the public items can be referred from dependents.
+		let (attrs, names) :(&[syn::Attribute], Vec<String>) = match item {
+			syn::Item::Const(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Enum(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Fn(i) => (&i.attrs, name(&i.vis, &i.sig.ident)),
+			syn::Item::Macro(i) if i.attrs.iter().any(|a| a.path.is_ident("macro_export")) => {
+				(&i.attrs, i.ident.iter().map(ToString::to_string).collect())
+			}
+			syn::Item::Macro2(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Mod(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Static(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Struct(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Trait(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::TraitAlias(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Type(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Union(i) => (&i.attrs, name(&i.vis, &i.ident)),
+			syn::Item::Use(i) => {
+				let mut names = vec![];
+				if let syn::Visibility::Public(_) = i.vis {
+					use_tree_leaves(&i.tree, &mut names);
+				}
+				(&i.attrs, names)
+			}
+			_ => return,
+		};
+		if attrs.iter().any(is_cfg_test) {
+			return;
+		}
+		let num_features = self.features.len();
+		self.features.extend(attrs.iter().flat_map(cfg_features));
+		for feature in &self.features {
+			self.items.entry(feature.clone()).or_default().extend(names.iter().cloned());
+		}
+		if let syn::Item::Mod(item) = item {
+			match &item.content {
+				None => {
+					if let Some((path, is_mod_rs)) = mod_file(item, self.parent, &self.dir) {
+						self.mods.push((path, is_mod_rs, self.features.clone()));
+					}
+				}
+				Some((_, items)) => {
+					let inline_dir = inline_mod_dir(item, &self.dir);
+					let dir = std::mem::replace(&mut self.dir, inline_dir);
+					for item in items {
+						self.visit_item(item);
+					}
+					self.dir = dir;
+				}
+			}
+		}
+		self.features.truncate(num_features);
+	}
+}
+
+/// The condition of `#[cfg(..)]` if it depends only on the target platform, e.g. `unix` or
+/// `any(target_os = "linux", target_os = "android")`.
+fn cfg_platform(attr :&syn::Attribute) -> Option<String> {
+	fn render(meta :&syn::Meta) -> Option<String> {
+		static KEYS :&[&str] = &[
+			"target_arch",
+			"target_endian",
+			"target_env",
+			"target_family",
+			"target_os",
+			"target_pointer_width",
+			"target_vendor",
+		];
+		match meta {
+			syn::Meta::Path(path) if path.is_ident("unix") || path.is_ident("windows") => {
+				path.get_ident().map(ToString::to_string)
+			}
+			syn::Meta::NameValue(syn::MetaNameValue { path, lit : syn::Lit::Str(s), .. })
+				if KEYS.iter().any(|k| path.is_ident(k)) =>
+			{
+				Some(format!("{} = {:?}", path.get_ident()?, s.value()))
+			}
+			// `all()` and `any()` do not depend on the platform.
+			syn::Meta::List(list) if ["all", "any", "not"].iter().any(|k| list.path.is_ident(k)) && !list.nested.is_empty() => {
+				let nested = list
+					.nested
+					.iter()
+					.map(|nested| match nested {
+						syn::NestedMeta::Meta(meta) => render(meta),
+						syn::NestedMeta::Lit(_) => None,
+					})
+					.collect::<Option<Vec<_>>>()?;
+				Some(format!("{}({})", list.path.get_ident()?, nested.join(", ")))
+			}
+			_ => None,
+		}
+	}
+
+	if !attr.path.is_ident("cfg") {
+		return None;
+	}
+	match attr.parse_meta() {
+		Ok(syn::Meta::List(list)) if list.nested.len() == 1 => match list.nested.first() {
+			Some(syn::NestedMeta::Meta(meta)) => render(meta),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// `#[cfg(test)]`
+fn is_cfg_test(attr :&syn::Attribute) -> bool {
+	attr.path.is_ident("cfg")
+		&& matches!(attr.parse_meta(), Ok(syn::Meta::List(list)) if list.nested.len() == 1 && matches!(
+			list.nested.first(),
+			Some(syn::NestedMeta::Meta(syn::Meta::Path(path))) if path.is_ident("test")
+		))
+}
+
+/// The names a `use` declaration brings into scope.
+fn use_tree_leaves(tree :&syn::UseTree, names :&mut Vec<String>) {
+	match tree {
+		syn::UseTree::Path(syn::UsePath { tree, .. }) => use_tree_leaves(tree, names),
+		syn::UseTree::Name(syn::UseName { ident }) | syn::UseTree::Rename(syn::UseRename { rename : ident, .. }) => {
+			names.push(ident.to_string());
+		}
+		syn::UseTree::Group(group) => {
+			for tree in &group.items {
+				use_tree_leaves(tree, names);
+			}
+		}
+		syn::UseTree::Glob(_) => {}
+	}
+}
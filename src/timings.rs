@@ -0,0 +1,128 @@
+//! `--timings`: where the time of a run went, from the spans of [`Trace`].
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::otel::Trace;
+
+/// The phases in the order they run. `parse` is the part of `analyze` which reads the usage of
+/// each unit, e.g. the save-analysis files.
+static PHASES :[&str; 6] = ["resolve", "compile", "doctests", "analyze", "parse", "report"];
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Timings {
+	/// Seconds of the whole run.
+	total :f64,
+	phases :Vec<PhaseTiming>,
+	/// The slowest first. The units of a package are compiled in parallel, so the sums may exceed
+	/// `compile`.
+	packages :Vec<PackageTiming>,
+}
+
+#[derive(Debug, Serialize)]
+struct PhaseTiming {
+	phase :&'static str,
+	seconds :f64,
+}
+
+#[derive(Debug, Serialize)]
+struct PackageTiming {
+	package :String,
+	/// The sum of the `rustc` invocations.
+	seconds :f64,
+}
+
+impl Timings {
+	pub(crate) fn new(trace :&Trace) -> Self {
+		let mut total = 0;
+		let mut phases = BTreeMap::<&str, u64>::new();
+		let mut packages = BTreeMap::<&str, u64>::new();
+		for span in trace.spans() {
+			let phase = if span.name.starts_with("collect ") { "parse" } else { &*span.name };
+			if span.name == "cargo udeps" {
+				total += span.duration();
+			} else if PHASES.contains(&phase) {
+				*phases.entry(phase).or_default() += span.duration();
+			} else if span.name.starts_with("rustc ") {
+				if let Some(package) = span.attribute("cargo.package") {
+					*packages.entry(package).or_default() += span.duration();
+				}
+			}
+		}
+		let mut packages = packages
+			.into_iter()
+			.map(|(package, nanos)| PackageTiming { package : package.to_owned(), seconds : seconds(nanos) })
+			.collect::<Vec<_>>();
+		packages.sort_by(|a, b| b.seconds.partial_cmp(&a.seconds).expect("should not be NaN"));
+		Self {
+			total : seconds(total),
+			phases : PHASES
+				.iter()
+				.filter_map(|&phase| Some(PhaseTiming { phase, seconds : seconds(*phases.get(phase)?) }))
+				.collect(),
+			packages,
+		}
+	}
+
+	pub(crate) fn print_human(&self, mut w :impl Write) -> io::Result<()> {
+		writeln!(w, "Timings:")?;
+		for phase in &self.phases {
+			let indent = if phase.phase == "parse" { "    " } else { "  " };
+			writeln!(w, "{}{:<width$} {:>8.2}s", indent, phase.phase, phase.seconds, width = 30 - indent.len())?;
+		}
+		writeln!(w, "  {:<28} {:>8.2}s", "total", self.total)?;
+		if !self.packages.is_empty() {
+			writeln!(w, "Compile time by package:")?;
+			for package in &self.packages {
+				writeln!(w, "  {:<28} {:>8.2}s", package.package, package.seconds)?;
+			}
+		}
+		w.flush()
+	}
+
+	pub(crate) fn print_json(&self, mut w :impl Write) -> io::Result<()> {
+		writeln!(w, "{}", serde_json::to_string_pretty(self).expect("should not fail"))?;
+		w.flush()
+	}
+
+	pub(crate) fn print_html(&self, mut w :impl Write) -> io::Result<()> {
+		writeln!(w, "<!DOCTYPE html>")?;
+		writeln!(w, r#"<html lang="en">"#)?;
+		writeln!(w, r#"<head><meta charset="utf-8"><title>cargo-udeps timings</title></head>"#)?;
+		writeln!(w, "<body>")?;
+		writeln!(w, "<h1>cargo-udeps timings</h1>")?;
+		writeln!(w, "<p>Total: {:.2}s</p>", self.total)?;
+		writeln!(w, "<table>")?;
+		writeln!(w, "<thead><tr><th>Phase</th><th>Seconds</th></tr></thead>")?;
+		writeln!(w, "<tbody>")?;
+		for phase in &self.phases {
+			writeln!(w, "<tr><td>{}</td><td>{:.2}</td></tr>", phase.phase, phase.seconds)?;
+		}
+		writeln!(w, "</tbody>")?;
+		writeln!(w, "</table>")?;
+		if !self.packages.is_empty() {
+			writeln!(w, "<h2>Compile time by package</h2>")?;
+			writeln!(w, "<table>")?;
+			writeln!(w, "<thead><tr><th>Package</th><th>Seconds</th></tr></thead>")?;
+			writeln!(w, "<tbody>")?;
+			for package in &self.packages {
+				writeln!(w, "<tr><td><code>{}</code></td><td>{:.2}</td></tr>", escape_html(&package.package), package.seconds)?;
+			}
+			writeln!(w, "</tbody>")?;
+			writeln!(w, "</table>")?;
+		}
+		writeln!(w, "</body>")?;
+		writeln!(w, "</html>")?;
+		w.flush()
+	}
+}
+
+fn seconds(nanos :u64) -> f64 {
+	nanos as f64 / 1e9
+}
+
+fn escape_html(s :&str) -> String {
+	s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,418 @@
+//! Strategies for detecting which dependencies a target uses.
+//!
+//! `--backend` selects among the built-in [`UsageCollector`]s. Other strategies can be plugged in
+//! with [`run_with_collectors`](crate::run_with_collectors).
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs};
+
+use failure::{Fallible, ResultExt as _};
+use serde::Deserialize;
+
+use crate::metadata::Workspace;
+use crate::{source, CmdInfo, Shell, Target};
+
+/// The crates a target uses.
+#[derive(Default, Debug)]
+pub struct Usage {
+	/// Names as given to `--extern`, i.e. as referred from the source code.
+	pub extern_crate_names :HashSet<String>,
+	/// Snake-cased names of the `lib` targets, e.g. from the save-analysis.
+	pub lib_names :HashSet<String>,
+	/// Files of the used crates, e.g. `target/debug/deps/librand-<hash>.rmeta`, with their
+	/// `lib_names`.
+	///
+	/// Unlike `lib_names`, they tell apart the versions of a crate by the `-C extra-filename`
+	/// hashes.
+	pub lib_paths :HashMap<PathBuf, String>,
+	/// Where the crates are referred to, by the names as given to `--extern` or of the `lib`
+	/// targets, for `cargo udeps explain`. Not every collector finds them.
+	pub locations :HashMap<String, BTreeSet<Location>>,
+}
+
+impl Usage {
+	pub fn extend(&mut self, other :Usage) {
+		self.extern_crate_names.extend(other.extern_crate_names);
+		self.lib_names.extend(other.lib_names);
+		self.lib_paths.extend(other.lib_paths);
+		for (name, locations) in other.locations {
+			self.locations.entry(name).or_default().extend(locations);
+		}
+	}
+
+	/// Turns `lib_paths` and `lib_names` into the names of the `--extern`s of `unit` for the same
+	/// packages. The other ones (e.g. `std`) are kept as `lib_names`.
+	pub(crate) fn resolve(&mut self, unit :&CmdInfo, ws :&Workspace) {
+		let extern_libs = unit
+			.externs()
+			.iter()
+			.filter_map(|(name, _)| {
+				let lib = ws.package(unit.extern_package(name)?).targets().iter().find(|t| t.is_lib())?;
+				Some((name, lib))
+			})
+			.collect::<Vec<_>>();
+
+		// `.rlib` and `.rmeta` of the same crate.
+		let stem = |path :&Path| path.file_stem().map(ToOwned::to_owned);
+		for (path, lib_name) in self.lib_paths.drain() {
+			let extern_name = unit
+				.externs()
+				.iter()
+				.find(|(_, p)| stem(Path::new(p)) == stem(&path))
+				.map(|(name, _)| name)
+				// The save-analysis refers to the crates by their root modules.
+				.or_else(|| {
+					let path = ws.root().join(unit.unremap_path(&path));
+					extern_libs.iter().find(|(_, lib)| lib.src_path() == path).map(|(name, _)| *name)
+				});
+			match extern_name {
+				Some(name) => {
+					self.extern_crate_names.insert(name.clone());
+				}
+				None => {
+					self.lib_names.insert(lib_name);
+				}
+			}
+		}
+
+		// Only the `--extern`s of the same unit with the same `lib` name remain ambiguous.
+		let mut unresolved = HashSet::new();
+		for lib_name in self.lib_names.drain() {
+			let mut names = extern_libs
+				.iter()
+				.filter(|(_, lib)| lib.name().replace('-', "_") == lib_name)
+				.map(|(name, _)| (*name).clone())
+				.peekable();
+			if names.peek().is_some() {
+				self.extern_crate_names.extend(names);
+			} else {
+				unresolved.insert(lib_name);
+			}
+		}
+		self.lib_names = unresolved;
+	}
+}
+
+/// A line of a source file.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Location {
+	pub file :PathBuf,
+	/// 1-based.
+	pub line :u32,
+}
+
+impl fmt::Display for Location {
+	fn fmt(&self, f :&mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.file.display(), self.line)
+	}
+}
+
+/// Detects the usage of dependencies.
+///
+/// The usages found by each of the collectors in use are combined.
+pub trait UsageCollector: Send + Sync {
+	/// The name shown by `--compare-backends`, e.g. `lint`.
+	fn name(&self) -> String {
+		std::any::type_name::<Self>().to_owned()
+	}
+
+	/// Whether the targets need to be compiled. [`collect_uncompiled`](Self::collect_uncompiled)
+	/// is called instead otherwise.
+	fn compiles(&self) -> bool {
+		true
+	}
+
+	/// Arguments appended to the `rustc` invocations for the workspace members.
+	fn rustc_args(&self) -> Vec<String> {
+		vec![]
+	}
+
+	/// Environment variables set for the `rustc` invocations for the workspace members.
+	fn rustc_envs(&self) -> Vec<(String, String)> {
+		vec![]
+	}
+
+	/// A program run as `program path/to/rustc ARGS..` instead of `rustc` for the workspace
+	/// members. At most one collector in use may have one.
+	fn rustc_wrapper(&self) -> Option<PathBuf> {
+		None
+	}
+
+	/// Codes of the JSON diagnostics (e.g. `unused_crate_dependencies`) meant for this collector.
+	/// They are not shown to the user and are available from [`CmdInfo::stderr_lines`].
+	fn claimed_diagnostics(&self) -> Vec<String> {
+		vec![]
+	}
+
+	/// Returns the crates used by a compiled target.
+	fn collect_compiled(&self, _unit :&CmdInfo, _shell :&mut Shell) -> Fallible<Usage> {
+		Ok(Usage::default())
+	}
+
+	/// Returns the crates used by a target without compiling it.
+	fn collect_uncompiled(&self, _target :&Target) -> Fallible<Usage> {
+		Ok(Usage::default())
+	}
+}
+
+/// `--backend save-analysis`: reads the external crates from the save-analysis data.
+pub struct SaveAnalysis;
+
+impl UsageCollector for SaveAnalysis {
+	fn name(&self) -> String {
+		"save-analysis".to_owned()
+	}
+
+	fn rustc_args(&self) -> Vec<String> {
+		vec!["-Z".to_owned(), "save-analysis".to_owned()]
+	}
+
+	fn rustc_envs(&self) -> Vec<(String, String)> {
+		vec![(
+			"RUST_SAVE_ANALYSIS_CONFIG".to_owned(),
+			r#"{ "reachable_only": true, "full_docs": false, "pub_only": false, "distro_crate": false, "signatures": false, "borrow_data": false }"#.to_owned(),
+		)]
+	}
+
+	fn collect_compiled(&self, unit :&CmdInfo, shell :&mut Shell) -> Fallible<Usage> {
+		let analysis = unit.get_save_analysis(shell)?;
+		let mut usage = Usage::default();
+		let directory = Path::new(&analysis.compilation.directory);
+		for r in &analysis.refs {
+			let ext = analysis.prelude.external_crates.iter().find(|ext| ext.num == r.ref_id.krate);
+			if let Some(ext) = ext {
+				let file = unit.unremap_path(&directory.join(&r.span.file_name));
+				let location = Location { file, line : r.span.line_start };
+				usage.locations.entry(ext.id.name.clone()).or_default().insert(location);
+			}
+		}
+		for ext in analysis.prelude.external_crates {
+			match ext.file_name {
+				Some(file_name) => drop(usage.lib_paths.insert(file_name.into(), ext.id.name)),
+				None => drop(usage.lib_names.insert(ext.id.name)),
+			}
+		}
+		Ok(usage)
+	}
+}
+
+/// `--backend lint`: the `--extern`s except the ones reported by the
+/// `unused_crate_dependencies` lint.
+pub struct Lint;
+
+impl UsageCollector for Lint {
+	fn name(&self) -> String {
+		"lint".to_owned()
+	}
+
+	fn rustc_args(&self) -> Vec<String> {
+		// Unlike `-W`, not turned into an error by `-D warnings` in `RUSTFLAGS` or by
+		// `#![deny(warnings)]`.
+		vec!["--force-warn".to_owned(), "unused-crate-dependencies".to_owned()]
+	}
+
+	fn claimed_diagnostics(&self) -> Vec<String> {
+		vec!["unused_crate_dependencies".to_owned()]
+	}
+
+	fn collect_compiled(&self, unit :&CmdInfo, _shell :&mut Shell) -> Fallible<Usage> {
+		let unused = unit
+			.stderr_lines()
+			.iter()
+			.filter_map(|l| unused_crate_dependency(l))
+			.collect::<HashSet<_>>();
+		let extern_crate_names = unit
+			.externs()
+			.iter()
+			.map(|(name, _)| name)
+			.filter(|name| !unused.contains(*name))
+			.cloned()
+			.collect();
+		Ok(Usage { extern_crate_names, ..Usage::default() })
+	}
+}
+
+/// `--backend source`: scans the sources without compiling them.
+pub struct Source;
+
+impl UsageCollector for Source {
+	fn name(&self) -> String {
+		"source".to_owned()
+	}
+
+	fn compiles(&self) -> bool {
+		false
+	}
+
+	fn collect_uncompiled(&self, target :&Target) -> Fallible<Usage> {
+		let locations = source::referred_locations(target.src_path())?;
+		let extern_crate_names = locations.keys().cloned().collect();
+		Ok(Usage { extern_crate_names, locations, ..Usage::default() })
+	}
+}
+
+/// `--backend rustdoc-json`: documents the targets with `--output-format json` and takes the
+/// crates the documented items refer to, including the intra-doc links.
+///
+/// Function bodies are not documented, so usages in them are missed.
+pub struct RustdocJson;
+
+impl UsageCollector for RustdocJson {
+	fn name(&self) -> String {
+		"rustdoc-json".to_owned()
+	}
+
+	fn collect_compiled(&self, unit :&CmdInfo, shell :&mut Shell) -> Fallible<Usage> {
+		let src_path = match unit.src_path() {
+			Some(src_path) => src_path,
+			None => return Ok(Usage::default()),
+		};
+		let out_dir = unit.out_dir().join("rustdoc-json");
+
+		let mut rustdoc = unit.rustc().clone();
+		rustdoc.program(env::var_os("RUSTDOC").unwrap_or_else(|| rustdoc_next_to(unit.rustc().get_program())));
+		let mut args = rustdoc_args(unit.rustc().get_args());
+		args.push(src_path.into());
+		args.extend(["-Z", "unstable-options", "--output-format", "json", "-o"].iter().map(Into::into));
+		args.push(out_dir.clone().into());
+		rustdoc.args_replace(&args);
+		shell.verbose(|shell| shell.status("Running", &rustdoc))?;
+		rustdoc.exec_with_output()?;
+
+		let path = out_dir.join(format!("{}.json", unit.crate_name()));
+		let json = fs::read_to_string(&path)
+			.with_context(|_| format!("could not read `{}`", path.display()))?;
+		let json = serde_json::from_str::<serde_json::Value>(&json)
+			.with_context(|_| format!("could not parse `{}`", path.display()))?;
+
+		let referred_crate_ids = ["index", "paths"]
+			.iter()
+			.filter_map(|&key| json[key].as_object())
+			.flat_map(|items| items.values())
+			.filter_map(|item| item["crate_id"].as_u64())
+			.collect::<HashSet<_>>();
+		let mut usage = Usage::default();
+		let referred_crates = json["external_crates"]
+			.as_object()
+			.into_iter()
+			.flatten()
+			.filter(|(id, _)| id.parse().map_or(false, |id :u64| referred_crate_ids.contains(&id)));
+		for (_, krate) in referred_crates {
+			let name = match krate["name"].as_str() {
+				Some(name) => name.to_owned(),
+				None => continue,
+			};
+			// Since format version 45.
+			match krate["path"].as_str() {
+				Some(path) => drop(usage.lib_paths.insert(path.into(), name)),
+				None => drop(usage.lib_names.insert(name)),
+			}
+		}
+		Ok(usage)
+	}
+}
+
+/// `--backend driver`: compiles with `cargo-udeps-driver`, which writes the crates the HIR
+/// refers to next to the output.
+///
+/// The driver is built with the `driver` feature on the nightly the targets are checked with.
+pub struct Driver {
+	path :PathBuf,
+}
+
+impl Driver {
+	/// Uses the `cargo-udeps-driver` next to the current executable, or in `$PATH`.
+	pub fn new() -> Self {
+		let file_name = format!("cargo-udeps-driver{}", env::consts::EXE_SUFFIX);
+		let path = env::current_exe()
+			.ok()
+			.map(|exe| exe.with_file_name(&file_name))
+			.filter(|path| path.exists())
+			.unwrap_or_else(|| file_name.into());
+		Self { path }
+	}
+}
+
+impl Default for Driver {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl UsageCollector for Driver {
+	fn name(&self) -> String {
+		"driver".to_owned()
+	}
+
+	fn rustc_wrapper(&self) -> Option<PathBuf> {
+		Some(self.path.clone())
+	}
+
+	fn collect_compiled(&self, unit :&CmdInfo, _shell :&mut Shell) -> Fallible<Usage> {
+		let path = unit
+			.out_dir()
+			.join("udeps-driver")
+			.join(format!("{}{}.json", unit.crate_name(), unit.extra_filename()));
+		let crates = fs::read_to_string(&path)
+			.with_context(|_| format!("could not read `{}`", path.display()))?;
+		let crates = serde_json::from_str::<Vec<DriverCrate>>(&crates)
+			.with_context(|_| format!("could not parse `{}`", path.display()))?;
+		let mut usage = Usage::default();
+		for DriverCrate { name, paths } in crates {
+			usage.lib_paths.extend(paths.into_iter().map(|path| (path, name.clone())));
+		}
+		Ok(usage)
+	}
+}
+
+/// An item written by `cargo-udeps-driver`.
+#[derive(Deserialize)]
+struct DriverCrate {
+	name :String,
+	/// The `.rlib`, `.rmeta` or the dylib.
+	paths :Vec<PathBuf>,
+}
+
+/// The `rustdoc` of the same toolchain as `rustc`, which a `rustup` proxy may not pick.
+pub(crate) fn rustdoc_next_to(rustc :&OsStr) -> OsString {
+	tool_next_to(rustc, "rustdoc")
+}
+
+/// `tool` (e.g. `rustdoc`) in the same directory as `program`, or in `$PATH`.
+pub(crate) fn tool_next_to(program :&OsStr, tool :&str) -> OsString {
+	let file_name = format!("{}{}", tool, env::consts::EXE_SUFFIX);
+	let path = Path::new(program).with_file_name(file_name);
+	if path.is_absolute() && path.exists() {
+		path.into()
+	} else {
+		tool.into()
+	}
+}
+
+/// Keeps the `rustc` arguments which `rustdoc` understands as well, except the input file.
+fn rustdoc_args(rustc_args :&[OsString]) -> Vec<OsString> {
+	let mut args = vec![];
+	let mut rustc_args = rustc_args.iter();
+	while let Some(arg) = rustc_args.next() {
+		let arg_str = arg.to_str().unwrap_or_default();
+		if ["--crate-name", "--crate-type", "--edition", "--cfg", "--extern", "-L", "--cap-lints"].contains(&arg_str) {
+			args.push(arg.clone());
+			args.extend(rustc_args.next().cloned());
+		} else if arg_str.starts_with("--edition=") {
+			args.push(arg.clone());
+		}
+	}
+	args
+}
+
+/// Extracts `foo` from a JSON diagnostic "external crate `foo` unused in `bar`: ..".
+pub(crate) fn unused_crate_dependency(line :&str) -> Option<String> {
+	let diagnostic = serde_json::from_str::<serde_json::Value>(line).ok()?;
+	if diagnostic["code"]["code"] != "unused_crate_dependencies" {
+		return None;
+	}
+	let message = diagnostic["message"].as_str()?;
+	message.split('`').nth(1).map(ToOwned::to_owned)
+}
@@ -0,0 +1,400 @@
+//! `cargo-udeps` as Cargo's `RUSTC_WORKSPACE_WRAPPER`.
+//!
+//! Cargo runs the wrapper as `cargo-udeps path/to/rustc ARGS..` for the workspace members. It
+//! applies the changes the [`UsageCollector`](crate::usage::UsageCollector)s asked for, runs
+//! `rustc`, then records the invocation for the main process to analyze.
+//!
+//! Cargo does not run `rustc` again for the units which are up to date. Their records are kept
+//! from the previous run, one directory per [`WrapperConfig`].
+//!
+//! With `--doc`, it is `RUSTDOC` for `cargo test --doc` as well. It compiles the doctests without
+//! running them, with itself as the test builder of `rustdoc` to find the crates they use.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::io::{BufRead as _, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::{env, fs};
+
+use failure::{Fallible, ResultExt as _};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::Workspace;
+use crate::otel;
+use crate::package_id::PackageId;
+use crate::usage;
+
+/// The variable which carries the [`WrapperConfig`] as JSON.
+pub(crate) static CONFIG_ENV :&str = "CARGO_UDEPS_RUSTC_WRAPPER";
+
+/// The variable which carries the [`RustdocWrapperConfig`] as JSON.
+pub(crate) static RUSTDOC_CONFIG_ENV :&str = "CARGO_UDEPS_RUSTDOC_WRAPPER";
+
+/// The variable which carries the [`DoctestBuilderConfig`] as JSON.
+static DOCTEST_BUILDER_CONFIG_ENV :&str = "CARGO_UDEPS_DOCTEST_BUILDER";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct WrapperConfig {
+	/// Where a [`Record`] is written for each invocation.
+	pub(crate) records_dir :PathBuf,
+	pub(crate) args :Vec<String>,
+	pub(crate) envs :Vec<(String, String)>,
+	/// Run as `wrapper path/to/rustc ARGS..` instead of `rustc`.
+	pub(crate) wrapper :Option<PathBuf>,
+	/// Codes of the JSON diagnostics which are recorded instead of shown.
+	pub(crate) claimed_diagnostics :Vec<String>,
+}
+
+/// A `rustc` invocation for a workspace member, as Cargo prepared it.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Record {
+	pub(crate) rustc :PathBuf,
+	pub(crate) args :Vec<String>,
+	pub(crate) cwd :PathBuf,
+	/// The variables Cargo sets for the crate, e.g. `CARGO_MANIFEST_DIR`.
+	pub(crate) envs :BTreeMap<String, String>,
+	pub(crate) claimed_diagnostics :Vec<String>,
+	/// When `rustc` started, in nanoseconds since the Unix epoch.
+	#[serde(default)]
+	pub(crate) started_at :u64,
+	/// When `rustc` finished, in nanoseconds since the Unix epoch.
+	#[serde(default)]
+	pub(crate) finished_at :u64,
+}
+
+impl Record {
+	/// Reads the records written since `records_dir` was created.
+	pub(crate) fn read_all(records_dir :&Path) -> Fallible<Vec<Self>> {
+		let mut records = vec![];
+		for entry in fs::read_dir(records_dir).with_context(|_| format!("could not read `{}`", records_dir.display()))? {
+			let path = entry?.path();
+			if is_json(&path) {
+				records.push(read_json(&path)?);
+			}
+		}
+		Ok(records)
+	}
+
+	pub(crate) fn manifest_dir(&self) -> Option<&Path> {
+		self.envs.get("CARGO_MANIFEST_DIR").map(Path::new)
+	}
+
+	/// Whether this invocation produced `artifact`.
+	pub(crate) fn produced(&self, artifact :&Artifact) -> bool {
+		let arg = |name :&str| self.args.windows(2).find(|w| w[0] == name).map(|w| &*w[1]);
+		let out_dir = match arg("--out-dir") {
+			Some(out_dir) => self.cwd.join(out_dir),
+			None => return false,
+		};
+		let crate_name = arg("--crate-name").unwrap_or_default();
+		let file_stem = format!("{}{}", crate_name, extra_filename(&self.args));
+		artifact.filenames.iter().any(|f| {
+			// The output directories of build scripts are not shared with other units.
+			f.parent() == Some(&out_dir)
+				&& (crate_name.starts_with("build_script_")
+					|| f.file_stem().map_or(false, |s| *s == *file_stem || *s == *format!("lib{}", file_stem)))
+		})
+	}
+}
+
+/// The arguments of the collectors which are not in the ones Cargo gave `rustc` (e.g. from
+/// `RUSTFLAGS` or `build.rustflags`), as pairs of flags and values.
+fn extra_args<'a>(args :&[String], extra :&'a [String]) -> Vec<&'a String> {
+	extra
+		.chunks(2)
+		.filter(|chunk| !args.windows(chunk.len()).any(|w| w == *chunk))
+		.flatten()
+		.collect()
+}
+
+/// A `compiler-artifact` message of Cargo.
+#[derive(Debug)]
+pub(crate) struct Artifact {
+	pub(crate) package_id :PackageId,
+	pub(crate) filenames :Vec<PathBuf>,
+	/// `rustc` was not run, so there may be no record of this run.
+	pub(crate) fresh :bool,
+}
+
+impl Artifact {
+	/// Parses a line of `cargo check --message-format json`.
+	pub(crate) fn parse(line :&str, ws :&Workspace) -> Option<Self> {
+		let message = serde_json::from_str::<ArtifactMessage>(line).ok()?;
+		if message.reason != "compiler-artifact" {
+			return None;
+		}
+		Some(Self {
+			package_id : ws.package_id(&message.package_id)?,
+			filenames : message.filenames,
+			fresh : message.fresh,
+		})
+	}
+}
+
+#[derive(Deserialize)]
+struct ArtifactMessage {
+	reason :String,
+	package_id :String,
+	#[serde(default)]
+	filenames :Vec<PathBuf>,
+	#[serde(default)]
+	fresh :bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RustdocWrapperConfig {
+	/// The `rustdoc` Cargo would run otherwise.
+	pub(crate) rustdoc :PathBuf,
+	/// Where a [`DoctestRecord`] is written for each `rustdoc --test`.
+	pub(crate) records_dir :PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DoctestBuilderConfig {
+	rustc :PathBuf,
+	/// Where the `unused_crate_dependencies` of each doctest are written.
+	unused_dir :PathBuf,
+}
+
+/// The crates the doctests of a library use.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DoctestRecord {
+	pub(crate) manifest_dir :PathBuf,
+	/// Names as given to `--extern`.
+	pub(crate) used_externs :BTreeSet<String>,
+}
+
+impl DoctestRecord {
+	pub(crate) fn read_all(records_dir :&Path) -> Fallible<Vec<Self>> {
+		let mut records = vec![];
+		for entry in fs::read_dir(records_dir).with_context(|_| format!("could not read `{}`", records_dir.display()))? {
+			let path = entry?.path();
+			if path.is_file() && is_json(&path) {
+				records.push(read_json(&path)?);
+			}
+		}
+		Ok(records)
+	}
+}
+
+/// Acts as `rustc` when Cargo runs the current executable as `RUSTC_WORKSPACE_WRAPPER`, or as
+/// `rustdoc` when it runs it as `RUSTDOC`.
+///
+/// Returns the exit code, or `None` if this process is not a wrapper.
+pub fn run_as_rustc_wrapper() -> Option<i32> {
+	let mut args = env::args_os().skip(1);
+	// `rustdoc` runs the test builder with the arguments for `rustc`, without its path.
+	let result = if let Ok(config) = env::var(DOCTEST_BUILDER_CONFIG_ENV) {
+		build_doctest(&config, args.collect())
+	} else if let Ok(config) = env::var(CONFIG_ENV) {
+		let rustc = PathBuf::from(args.next()?);
+		run(&config, rustc, args.collect())
+	} else if let Ok(config) = env::var(RUSTDOC_CONFIG_ENV) {
+		run_rustdoc(&config, args.collect())
+	} else {
+		return None;
+	};
+	match result {
+		Ok(code) => Some(code),
+		Err(err) => {
+			eprintln!("error: {}", err);
+			for cause in err.iter_causes() {
+				eprintln!("  caused by: {}", cause);
+			}
+			Some(101)
+		}
+	}
+}
+
+fn run(config :&str, rustc :PathBuf, args :Vec<OsString>) -> Fallible<i32> {
+	let config = serde_json::from_str::<WrapperConfig>(config)
+		.with_context(|_| format!("could not parse `${}`", CONFIG_ENV))?;
+	let args = into_strings(args)?;
+
+	let crate_name = args.windows(2).find(|w| w[0] == "--crate-name").map(|w| &*w[1]);
+	// Cargo also asks `rustc` about the target with `--crate-name ___`.
+	if crate_name.map_or(true, |name| name == "___") {
+		let status = Command::new(&rustc).args(&args).status()?;
+		return Ok(status.code().unwrap_or(101));
+	}
+
+	let mut cmd = match &config.wrapper {
+		Some(wrapper) => {
+			let mut cmd = Command::new(wrapper);
+			cmd.arg(&rustc);
+			cmd
+		}
+		None => Command::new(&rustc),
+	};
+	cmd.args(&args).args(extra_args(&args, &config.args)).envs(config.envs.iter().map(|(k, v)| (k, v)));
+	let started_at = otel::unix_nanos();
+	let mut child = cmd
+		.stderr(Stdio::piped())
+		.spawn()
+		.with_context(|_| format!("could not execute `{}`", rustc.display()))?;
+
+	// The diagnostics are ours, not the user's.
+	let mut claimed_diagnostics = vec![];
+	for line in BufReader::new(child.stderr.take().expect("should be piped")).lines() {
+		let line = line?;
+		let code = serde_json::from_str::<serde_json::Value>(&line)
+			.ok()
+			.and_then(|d| d["code"]["code"].as_str().map(ToOwned::to_owned));
+		if code.map_or(false, |c| config.claimed_diagnostics.contains(&c)) {
+			claimed_diagnostics.push(line);
+		} else {
+			eprintln!("{}", line);
+		}
+	}
+	let status = child.wait()?;
+	if !status.success() {
+		return Ok(status.code().unwrap_or(101));
+	}
+	let finished_at = otel::unix_nanos();
+
+	let file_name = format!("{}{}.json", crate_name.unwrap_or_default(), extra_filename(&args));
+	let record = Record {
+		rustc,
+		args : args.clone(),
+		cwd : env::current_dir()?,
+		envs : env::vars().filter(|(k, _)| k.starts_with("CARGO_") && k != CONFIG_ENV || k == "OUT_DIR").collect(),
+		claimed_diagnostics,
+		started_at,
+		finished_at,
+	};
+	let path = config.records_dir.join(&file_name);
+	write_json(&path, &record)?;
+
+	// The records of this unit for the other configurations are outdated now.
+	if let Some(records_root) = config.records_dir.parent() {
+		for entry in fs::read_dir(records_root)? {
+			let other = entry?.path().join(&file_name);
+			if other != path && other.exists() {
+				fs::remove_file(&other).with_context(|_| format!("could not remove `{}`", other.display()))?;
+			}
+		}
+	}
+	Ok(0)
+}
+
+/// Runs `rustdoc --test` so that it only compiles the doctests, then records the `--extern`s
+/// which any of them uses.
+fn run_rustdoc(config :&str, args :Vec<OsString>) -> Fallible<i32> {
+	let config = serde_json::from_str::<RustdocWrapperConfig>(config)
+		.with_context(|_| format!("could not parse `${}`", RUSTDOC_CONFIG_ENV))?;
+	let args = into_strings(args)?;
+	if !args.iter().any(|a| a == "--test") {
+		let status = Command::new(&config.rustdoc).args(&args).status()?;
+		return Ok(status.code().unwrap_or(101));
+	}
+
+	let unused_dir = config.records_dir.join(format!("{}.d", process::id()));
+	fs::create_dir_all(&unused_dir).with_context(|_| format!("could not create `{}`", unused_dir.display()))?;
+	let builder_config = DoctestBuilderConfig {
+		rustc : usage::tool_next_to(config.rustdoc.as_os_str(), "rustc").into(),
+		unused_dir : unused_dir.clone(),
+	};
+	let status = Command::new(&config.rustdoc)
+		.args(&args)
+		.args(&["-Z", "unstable-options", "--no-run", "--test-builder"])
+		.arg(env::current_exe()?)
+		.env(DOCTEST_BUILDER_CONFIG_ENV, serde_json::to_string(&builder_config)?)
+		.status()
+		.with_context(|_| format!("could not execute `{}`", config.rustdoc.display()))?;
+	if !status.success() {
+		return Ok(status.code().unwrap_or(101));
+	}
+
+	// Every doctest is given all the `--extern`s.
+	let externs = args
+		.windows(2)
+		.filter(|w| w[0] == "--extern")
+		.map(|w| w[1].split('=').next().unwrap_or_default().to_owned())
+		.collect::<BTreeSet<_>>();
+	let mut used_externs = BTreeSet::new();
+	for entry in fs::read_dir(&unused_dir)? {
+		let path = entry?.path();
+		if is_json(&path) {
+			let unused = read_json::<BTreeSet<String>>(&path)?;
+			used_externs.extend(externs.difference(&unused).cloned());
+		}
+	}
+	let record = DoctestRecord {
+		manifest_dir : env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default().into(),
+		used_externs,
+	};
+	let path = config.records_dir.join(format!("{}.json", process::id()));
+	write_json(&path, &record)?;
+	Ok(0)
+}
+
+/// Compiles a doctest for `rustdoc`, and writes the `--extern`s it does not use.
+fn build_doctest(config :&str, args :Vec<OsString>) -> Fallible<i32> {
+	let config = serde_json::from_str::<DoctestBuilderConfig>(config)
+		.with_context(|_| format!("could not parse `${}`", DOCTEST_BUILDER_CONFIG_ENV))?;
+	let mut child = Command::new(&config.rustc)
+		.args(&args)
+		.args(&["-W", "unused-crate-dependencies", "--error-format", "json"])
+		.stderr(Stdio::piped())
+		.spawn()
+		.with_context(|_| format!("could not execute `{}`", config.rustc.display()))?;
+
+	let mut unused = BTreeSet::new();
+	for line in BufReader::new(child.stderr.take().expect("should be piped")).lines() {
+		let line = line?;
+		if let Some(name) = usage::unused_crate_dependency(&line) {
+			unused.insert(name);
+		} else {
+			// `rustdoc` shows them if the doctest does not compile.
+			let rendered = serde_json::from_str::<serde_json::Value>(&line)
+				.ok()
+				.and_then(|d| d["rendered"].as_str().map(ToOwned::to_owned));
+			match rendered {
+				Some(rendered) => eprint!("{}", rendered),
+				None => eprintln!("{}", line),
+			}
+		}
+	}
+	let status = child.wait()?;
+	if !status.success() {
+		return Ok(status.code().unwrap_or(101));
+	}
+
+	let path = config.unused_dir.join(format!("{}.json", process::id()));
+	write_json(&path, &unused)?;
+	Ok(0)
+}
+
+fn into_strings(args :Vec<OsString>) -> Fallible<Vec<String>> {
+	args.into_iter()
+		.map(|a| a.into_string().map_err(|a| failure::format_err!("non-utf8 arguments are not supported: {:?}", a)))
+		.collect()
+}
+
+/// Writes `value` to `path` at once, so that readers never see a part of it, e.g. when Cargo is
+/// killed by `--max-duration` while the unit is being recorded.
+fn write_json(path :&Path, value :&impl Serialize) -> Fallible<()> {
+	let tmp = path.with_extension("json.tmp");
+	fs::write(&tmp, serde_json::to_string(value)?).with_context(|_| format!("could not write `{}`", tmp.display()))?;
+	fs::rename(&tmp, path).with_context(|_| format!("could not write `{}`", path.display()))?;
+	Ok(())
+}
+
+/// Whether `path` is a finished record, not a `.json.tmp` being written.
+fn is_json(path :&Path) -> bool {
+	path.extension().map_or(false, |e| e == "json")
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path :&Path) -> Fallible<T> {
+	let json = fs::read_to_string(path).with_context(|_| format!("could not read `{}`", path.display()))?;
+	Ok(serde_json::from_str(&json).with_context(|_| format!("could not parse `{}`", path.display()))?)
+}
+
+/// The value of `-C extra-filename`, which Cargo appends to the names of the outputs.
+fn extra_filename(args :&[String]) -> &str {
+	args.windows(2)
+		.filter(|w| w[0] == "-C")
+		.find_map(|w| w[1].strip_prefix("extra-filename="))
+		.unwrap_or_default()
+}
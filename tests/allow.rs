@@ -0,0 +1,82 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "allow"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+
+[dev-dependencies]
+if_chain = "1.0.0"
+"#;
+
+static REPORT: &str = r#"unused dependencies:
+`allow v0.0.0 (██████████)`
+└─── dev-dependencies
+     └─── "if_chain"
+"#;
+
+fn runner(name :&str, cargo_toml :&str) -> Fallible<Runner> {
+	Ok(Runner::new(name)?
+		.cargo_toml(cargo_toml)?
+		.dir("./src")?
+		.file("./src/lib.rs", "use maplit as _;\n")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--all-targets"))
+}
+
+#[test]
+fn allow_dev() -> Fallible<()> {
+	let (code, stdout_masked, stderr) =
+		runner("cargo_udeps_test_allow_allow_dev", CARGO_TOML)?.arg("--allow").arg("dev").run_with_stderr()?;
+	assert_eq!(0, code);
+	assert_eq!(REPORT, stdout_masked);
+	assert!(stderr.contains("warning: unused dev-dependencies of `allow` are allowed: \"if_chain\""), "{:?}", stderr);
+	Ok(())
+}
+
+#[test]
+fn allow_metadata() -> Fallible<()> {
+	let cargo_toml = CARGO_TOML.replace("[dependencies]", "[package.metadata.cargo-udeps]\nallow = [\"dev\"]\n\n[dependencies]");
+	let (code, stdout_masked) = runner("cargo_udeps_test_allow_allow_metadata", &cargo_toml)?.run()?;
+	assert_eq!(0, code);
+	assert_eq!(REPORT, stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn allow_build() -> Fallible<()> {
+	let (code, _) = runner("cargo_udeps_test_allow_allow_build", CARGO_TOML)?.arg("--allow").arg("build").run()?;
+	assert_eq!(1, code);
+	Ok(())
+}
+
+#[test]
+fn deny_false() -> Fallible<()> {
+	let cargo_toml = CARGO_TOML.replace("[dependencies]", "[package.metadata.cargo-udeps]\ndeny = false\n\n[dependencies]");
+	let (code, _) = runner("cargo_udeps_test_allow_deny_false", &cargo_toml)?.file("./src/lib.rs", "")?.run()?;
+	assert_eq!(0, code);
+	Ok(())
+}
+
+#[test]
+fn max_unused() -> Fallible<()> {
+	let cargo_toml = |max :usize| {
+		CARGO_TOML.replace("[dependencies]", &format!("[package.metadata.cargo-udeps]\nmax-unused = {}\n\n[dependencies]", max))
+	};
+	let (code, _) = runner("cargo_udeps_test_allow_max_unused_2", &cargo_toml(2))?.file("./src/lib.rs", "")?.run()?;
+	assert_eq!(0, code);
+	let (code, _) = runner("cargo_udeps_test_allow_max_unused_1", &cargo_toml(1))?.file("./src/lib.rs", "")?.run()?;
+	assert_eq!(1, code);
+	Ok(())
+}
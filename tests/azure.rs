@@ -0,0 +1,42 @@
+mod runner;
+
+use std::env;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "azure"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn logging_commands() -> Fallible<()> {
+	// Paths are relative to the checkout on Azure Pipelines.
+	env::remove_var("BUILD_SOURCESDIRECTORY");
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_azure_logging_commands")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("azure")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		"##vso[task.logissue type=warning;sourcepath=Cargo.toml;code=unused-dependency;linenumber=9]unused dependency 'if_chain'\n",
+		stdout_masked,
+	);
+	Ok(())
+}
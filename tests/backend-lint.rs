@@ -0,0 +1,91 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "backend-lint"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "pub use byteorder::BigEndian;\n";
+
+#[test]
+fn unused_crate_dependencies() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_backend_lint_unused_crate_dependencies")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--backend")
+			.arg("lint")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`backend-lint v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn combined_with_source() -> Fallible<()> {
+	// `lint` misses the usage behind the `cfg`, `source` ignores `cfg`s.
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_backend_lint_combined_with_source")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file(
+				"./src/lib.rs",
+				"pub use byteorder::BigEndian;\n\n#[cfg(any())]\nfn f() {\n\tif_chain::if_chain! {}\n}\n",
+			)?
+			.arg("--all-targets")
+			.arg("--backend")
+			.arg("lint,source")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn deny_warnings() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_backend_lint_deny_warnings")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", &format!("#![deny(warnings)]\n\n{}", LIB_RS))?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`backend-lint v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,46 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "backend-rustdoc-json"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "/// See [`byteorder::BigEndian`].\npub fn f() {}\n";
+
+#[test]
+fn intra_doc_links() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_backend_rustdoc_json_intra_doc_links")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--backend")
+			.arg("rustdoc-json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`backend-rustdoc-json v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
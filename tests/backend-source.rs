@@ -0,0 +1,53 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "backend-source"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS :&str = "mod a;\n";
+static A_RS :&str = "pub type Endian = byteorder::BigEndian;\n";
+static TEST_RS :&str = "#[test]\nfn test() {\n    let _ = maplit::hashset!(1);\n}\n";
+
+#[test]
+fn module_tree() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_backend_source_module_tree")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./src/a.rs", A_RS)?
+			.dir("./tests")?
+			.file("./tests/test.rs", TEST_RS)?
+			.arg("--backend")
+			.arg("source")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`backend-source v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,75 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "baseline"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+matches = "0.1.8"
+"#;
+
+static BASELINE_JSON: &str = r#"{
+  "unused_deps": [
+    {
+      "package": "baseline",
+      "kind": "normal",
+      "dependency": "maplit"
+    }
+  ]
+}
+"#;
+
+#[test]
+fn write_baseline() -> Fallible<()> {
+	let (code, stdout_masked, baseline) =
+		Runner::new("cargo_udeps_test_baseline_write_baseline")?
+			.cargo_toml(&CARGO_TOML.replace("matches = \"0.1.8\"\n", ""))?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--write-baseline")
+			.arg("baseline.json")
+			.run_and_read("baseline.json")?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	assert_eq!(BASELINE_JSON, baseline);
+	Ok(())
+}
+
+#[test]
+fn baseline() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_baseline_baseline")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.file("./baseline.json", BASELINE_JSON)?
+			.arg("--backend")
+			.arg("source")
+			.arg("--baseline")
+			.arg("baseline.json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`baseline v0.0.0 (██████████)`
+└─── dependencies
+     └─── "matches"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,50 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn blame() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "blame"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	static MATCHES: &str = r#"matches = "0.1.8"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_blame_blame")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.git(&["init", "-q"])?
+			.git(&["add", "Cargo.toml"])?
+			.git(&["commit", "-q", "-m", "Add maplit"])?
+			.cargo_toml(&(CARGO_TOML.to_owned() + MATCHES))?
+			.arg("--blame")
+			.arg("--backend")
+			.arg("source")
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let unused = outcome["unused_deps"].as_array().unwrap().iter().next().unwrap();
+	assert_eq!(serde_json::json!(["maplit", "matches"]), unused["normal"]);
+	// `matches` is not committed.
+	let blame = unused["blame"].as_object().unwrap();
+	assert_eq!(vec!["maplit"], blame.keys().collect::<Vec<_>>());
+	assert_eq!(40, blame["maplit"]["commit"].as_str().unwrap().len());
+	assert_eq!("cargo-udeps", blame["maplit"]["author"]);
+	assert_eq!("Add maplit", blame["maplit"]["summary"]);
+	Ok(())
+}
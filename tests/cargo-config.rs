@@ -0,0 +1,67 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "cargo-config"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+itoa = "1.0.0"
+"#;
+
+#[test]
+fn rustflags() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_cargo_config_rustflags")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "#[cfg(udeps_config)]\npub use itoa::Buffer;\n")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--config")
+		.arg(r#"build.rustflags=["--cfg", "udeps_config"]"#)
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn term() -> Fallible<()> {
+	let err = Runner::new("cargo_udeps_test_cargo_config_term")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub use itoa::Buffer;\n")?
+		.dir("./.cargo")?
+		.file("./.cargo/config.toml", "[term]\nquiet = true\ncolor = \"sometimes\"\n")?
+		.arg("--backend")
+		.arg("source")
+		.run()
+		.unwrap_err();
+	assert_eq!(
+		"argument for --color must be auto, always, or never, but found `sometimes`",
+		err.to_string(),
+	);
+
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_cargo_config_term")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub use itoa::Buffer;\n")?
+		.dir("./.cargo")?
+		.file("./.cargo/config.toml", "[term]\nquiet = true\ncolor = \"sometimes\"\n")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--config")
+		.arg("term.color='never'")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
@@ -0,0 +1,52 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn charset_ascii() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "charset_ascii"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_charset_charset_ascii")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--all-targets")
+			.arg("--charset")
+			.arg("ascii")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`charset_ascii v0.0.0 (██████████)`
+|-- dependencies
+|   |-- "if_chain"
+|   `-- "maplit"
+`-- dev-dependencies
+    `-- "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
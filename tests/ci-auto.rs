@@ -0,0 +1,44 @@
+mod runner;
+
+use std::env;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "ci-auto"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn azure_pipelines() -> Fallible<()> {
+	for var in &["GITHUB_ACTIONS", "GITLAB_CI", "TEAMCITY_VERSION", "JENKINS_URL", "BUILD_SOURCESDIRECTORY"] {
+		env::remove_var(var);
+	}
+	env::set_var("TF_BUILD", "True");
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ci_auto_azure_pipelines")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("ci-auto")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		"##vso[task.logissue type=warning;sourcepath=Cargo.toml;code=unused-dependency;linenumber=9]unused dependency 'if_chain'\n",
+		stdout_masked,
+	);
+	Ok(())
+}
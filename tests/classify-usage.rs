@@ -0,0 +1,75 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "classify-usage"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+either = "1.0.0"
+if_chain = "1.0.0"
+itoa = "1.0.0"
+maplit = "1.0.2"
+serde = { version = "1.0.0", features = ["derive"] }
+"#;
+
+static LIB_RS :&str = r#"pub use itoa::Buffer;
+
+#[derive(serde::Deserialize)]
+pub struct A;
+
+pub struct B;
+
+impl serde::Serialize for B {
+	fn serialize<S :serde::Serializer>(&self, serializer :S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_unit()
+	}
+}
+
+pub fn f(x :bool) -> either::Either<u8, u8> {
+	if_chain::if_chain! {
+		if x;
+		then { return either::Either::Left(0); }
+	}
+	either::Either::Right(1)
+}
+"#;
+
+#[test]
+fn classify_usage() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_classify_usage_classify_usage")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend=source")
+		.arg("--classify-usage")
+		.arg("--output")
+		.arg("json")
+		.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let usage_kinds = outcome["usage_kinds"]
+		.as_array()
+		.into_iter()
+		.flatten()
+		.map(|u| (u["dependency"].clone(), u["kinds"].clone()))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			(serde_json::json!("either"), serde_json::json!(["public-api", "other"])),
+			(serde_json::json!("if_chain"), serde_json::json!(["macro"])),
+			(serde_json::json!("itoa"), serde_json::json!(["re-export"])),
+			(serde_json::json!("serde"), serde_json::json!(["derive", "trait-impl", "other"])),
+		],
+		usage_kinds,
+	);
+	Ok(())
+}
@@ -0,0 +1,44 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "codeclimate"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "";
+
+fn run(prefix :&str) -> Fallible<serde_json::Value> {
+	let (code, stdout_masked) =
+		Runner::new(prefix)?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("codeclimate")
+			.run()?;
+	assert_eq!(1, code);
+	Ok(serde_json::from_str(&stdout_masked)?)
+}
+
+#[test]
+fn stable_fingerprints() -> Fallible<()> {
+	let first = run("cargo_udeps_test_codeclimate_stable_fingerprints_1")?;
+	let second = run("cargo_udeps_test_codeclimate_stable_fingerprints_2")?;
+	assert_eq!(json!("unused-dependency"), first[0]["check_name"]);
+	assert_eq!(json!("unused dependency `if_chain` in `codeclimate v0.0.0`"), first[0]["description"]);
+	assert_eq!(json!({ "path": "Cargo.toml", "lines": { "begin": 9 } }), first[0]["location"]);
+	assert_eq!(first, second);
+	Ok(())
+}
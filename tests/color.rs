@@ -0,0 +1,75 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn color_always() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "color_always"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+
+[dev-dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_color_color_always")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--all-targets")
+			.arg("--color")
+			.arg("always")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		"unused dependencies:
+\x1b[1m`color_always v0.0.0 (██████████)`\x1b[0m
+├─── \x1b[33mdependencies\x1b[0m
+│    └─── \"maplit\"
+└─── \x1b[36mdev-dependencies\x1b[0m
+     └─── \"if_chain\"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+",
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn color_always_success() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "color_always_success"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_color_color_always_success")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--color")
+			.arg("always")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("\x1b[1;32mAll deps seem to have been used.\x1b[0m\n", stdout_masked);
+	Ok(())
+}
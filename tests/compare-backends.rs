@@ -0,0 +1,60 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "compare-backends"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+// `--backend source` ignores the `cfg`s.
+static LIB_RS :&str = "pub type Endian = byteorder::BigEndian;\n\n#[cfg(any())]\nuse if_chain as _;\n";
+
+#[test]
+fn lint_source() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_compare_backends_lint_source")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend")
+			.arg("lint,source")
+			.arg("--compare-backends")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+backends disagree:
+`compare-backends v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain" (unused by `lint`, used by `source` at src/lib.rs:4)
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn single_backend() -> Fallible<()> {
+	let (error, _) =
+		Runner::new("cargo_udeps_test_compare_backends_single_backend")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend")
+			.arg("source")
+			.arg("--compare-backends")
+			.run_err()?;
+	assert_eq!("`--compare-backends` needs two or more `--backend`s", error.to_string());
+	Ok(())
+}
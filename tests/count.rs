@@ -0,0 +1,65 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn count() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "count"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_count_count")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--all-targets")
+			.arg("--count")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!("3\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn count_none() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "count_none"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_count_count_none")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "use maplit as _;\n")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--count")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("0\n", stdout_masked);
+	Ok(())
+}
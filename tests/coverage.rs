@@ -0,0 +1,92 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "coverage"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[target.'cfg(any())'.dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS: &str = "";
+static EXAMPLE_RS: &str = "fn main() {}\n";
+
+#[test]
+fn without_all_targets() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_coverage_without_all_targets")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.dir("./examples")?
+			.file("./examples/foo.rs", EXAMPLE_RS)?
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`coverage v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+not analyzed:
+`coverage v0.0.0 (██████████)`
+├─── targets
+│    └─── example "foo" (not selected)
+└─── dependencies for `cfg(any())`
+     └─── "maplit"
+Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn ensure_full_coverage() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ensure-full-coverage"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_coverage_ensure_full_coverage")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.dir("./examples")?
+			.file("./examples/foo.rs", EXAMPLE_RS)?
+			.arg("--ensure-full-coverage")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"not analyzed:
+`ensure-full-coverage v0.0.0 (██████████)`
+└─── targets
+     └─── example "foo" (not selected)
+Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,73 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+default-members = ["a"]
+"#;
+
+static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+fn runner(name :&str) -> Fallible<Runner> {
+	Ok(Runner::new(name)?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./a/src")?
+		.file("./a/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "a"))?
+		.file("./a/src/lib.rs", "")?
+		.dir("./b/src")?
+		.file("./b/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "b"))?
+		.file("./b/src/lib.rs", "")?
+		.arg("--backend")
+		.arg("source"))
+}
+
+#[test]
+fn default_members() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_default_members_default_members")?.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn workspace() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_default_members_workspace")?.arg("--workspace").run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     └─── "maplit"
+`b v0.0.0 (██████████/b)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,84 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static OLD_JSON: &str = r#"{
+  "success": false,
+  "unused_deps": {
+    "a 0.1.0 (path+file:///old/a)": {
+      "manifest_path": "/old/a/Cargo.toml",
+      "normal": ["if_chain", "matches"],
+      "development": [],
+      "build": []
+    }
+  },
+  "note": null
+}
+"#;
+
+static NEW_JSON: &str = r#"{
+  "success": false,
+  "unused_deps": {
+    "a 0.2.0 (path+file:///new/a)": {
+      "manifest_path": "/new/a/Cargo.toml",
+      "normal": ["matches"],
+      "development": ["maplit"],
+      "build": []
+    }
+  },
+  "note": null
+}
+"#;
+
+#[test]
+fn diff() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_diff_diff")?
+			.file("old.json", OLD_JSON)?
+			.file("new.json", NEW_JSON)?
+			.arg("diff")
+			.arg("old.json")
+			.arg("new.json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"introduced unused dependencies:
+`a`
+└─── dev-dependencies
+     └─── "maplit"
+fixed unused dependencies:
+`a`
+└─── dependencies
+     └─── "if_chain"
+unchanged unused dependencies:
+`a`
+└─── dependencies
+     └─── "matches"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn no_changes() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_diff_no_changes")?
+			.file("old.json", OLD_JSON)?
+			.arg("--output")
+			.arg("json")
+			.arg("diff")
+			.arg("old.json")
+			.arg("old.json")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"{"introduced":[],"fixed":[],"unchanged":[{"package":"a","kind":"normal","dependency":"if_chain"},{"package":"a","kind":"normal","dependency":"matches"}]}
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,80 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "doctests"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = r#"//! ```
+//! use byteorder::BigEndian;
+//! ```
+"#;
+
+#[test]
+fn without_doc() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_doctests_without_doc")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`doctests v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "byteorder"
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn with_doc() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_doctests_with_doc")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--doc")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`doctests v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+used only by tests, benches or examples:
+`doctests v0.0.0 (██████████)`
+└─── dependencies
+     └─── "byteorder" (move it to `[dev-dependencies]`)
+Note: Misplaced dependencies are built for the dependents of the package as well.
+      Move them to the suggested table.
+Note: They might be false-positive.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
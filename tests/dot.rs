@@ -0,0 +1,66 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "dot"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+a = { path = "a" }
+b = { path = "b" }
+"#;
+
+static LIB_RS :&str = "use b as _;\n";
+
+static A_CARGO_TOML :&str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+static B_CARGO_TOML :&str = r#"[package]
+name = "b"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+#[test]
+fn unused_edges() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_dot_unused_edges")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.dir("./a/src")?
+			.file("./a/Cargo.toml", A_CARGO_TOML)?
+			.file("./a/src/lib.rs", "")?
+			.dir("./b/src")?
+			.file("./b/Cargo.toml", B_CARGO_TOML)?
+			.file("./b/src/lib.rs", "")?
+			.arg("--output")
+			.arg("dot")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"digraph udeps {
+	node [shape=box];
+	"a v0.0.0 (██████████/a)" [label="a v0.0.0"];
+	"b v0.0.0 (██████████/b)" [label="b v0.0.0"];
+	"dot v0.0.0 (██████████)" [label="dot v0.0.0"];
+	"dot v0.0.0 (██████████)" -> "a v0.0.0 (██████████/a)" [color=red];
+	"dot v0.0.0 (██████████)" -> "b v0.0.0 (██████████/b)" [color=gray];
+}
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,40 @@
+mod runner;
+
+use std::env;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "environment"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+#[test]
+fn output_and_backend() -> Fallible<()> {
+	// The only test of this file, so no other one sees them.
+	env::set_var("CARGO_UDEPS_OUTPUT", "json");
+	env::set_var("CARGO_UDEPS_BACKEND", "lint,source");
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_environment_output_and_backend")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file(
+			"./src/lib.rs",
+			"pub use byteorder::BigEndian;\n\n#[cfg(any())]\nfn f() {\n\tif_chain::if_chain! {}\n}\n",
+		)?
+		.arg("--all-targets")
+		.run()?;
+	assert_eq!(0, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(serde_json::json!(true), outcome["success"]);
+	Ok(())
+}
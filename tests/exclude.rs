@@ -0,0 +1,175 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["crates/*", "vendor/*"]
+"#;
+
+static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+fn runner(name :&str) -> Fallible<Runner> {
+	let mut runner = Runner::new(name)?.cargo_toml(CARGO_TOML)?;
+	for dir in &["crates/stable", "crates/experimental-a", "crates/experimental-b", "vendor/third-party"] {
+		let name = dir.rsplit('/').next().unwrap();
+		runner = runner
+			.dir(&format!("./{}/src", dir))?
+			.file(&format!("./{}/Cargo.toml", dir), &MEMBER_CARGO_TOML.replace("{}", name))?
+			.file(&format!("./{}/src/lib.rs", dir), "")?;
+	}
+	Ok(runner)
+}
+
+static EXPECTED: &str = r#"unused dependencies:
+`stable v0.0.0 (██████████/crates/stable)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#;
+
+#[test]
+fn glob_and_path() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_exclude_glob_and_path")?
+		.arg("--workspace")
+		.arg("--exclude")
+		.arg("crates/experimental-*")
+		.arg("--exclude-path")
+		.arg("vendor/")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(EXPECTED, stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn name_glob() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_exclude_name_glob")?
+		.arg("--workspace")
+		.arg("--exclude")
+		.arg("experimental-?")
+		.arg("--exclude")
+		.arg("third-*")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(EXPECTED, stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn unpublished() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+members = ["published", "internal"]
+"#;
+
+	static PUBLISHED_CARGO_TOML: &str = r#"[package]
+name = "published"
+version = "0.0.0"
+edition = "2018"
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	static INTERNAL_CARGO_TOML: &str = r#"[package]
+name = "internal"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_exclude_unpublished")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./published/src")?
+		.file("./published/Cargo.toml", PUBLISHED_CARGO_TOML)?
+		.file("./published/src/lib.rs", "")?
+		.dir("./internal/src")?
+		.file("./internal/Cargo.toml", INTERNAL_CARGO_TOML)?
+		.file("./internal/src/lib.rs", "")?
+		.arg("--workspace")
+		.arg("--exclude-unpublished")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`published v0.0.0 (██████████/published)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn package_spec() -> Fallible<()> {
+	let runner = runner("cargo_udeps_test_exclude_package_spec")?;
+	let url = format!("path+file://{}#stable@0.0.0", runner.path().join("crates/stable").display());
+	let (code, stdout_masked) = runner
+		.arg("-p")
+		.arg("stable@0")
+		.arg("-p")
+		.arg(url)
+		.arg("-p")
+		.arg("stable")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(EXPECTED, stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn exclude_package_spec() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_exclude_exclude_package_spec")?
+		.arg("--workspace")
+		.arg("--exclude")
+		.arg("experimental-a:0.0.0")
+		.arg("--exclude")
+		.arg("experimental-b@0.0")
+		.arg("--exclude")
+		.arg("third-party@0")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(EXPECTED, stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn invalid_package_spec() -> Fallible<()> {
+	let (error, _) = runner("cargo_udeps_test_exclude_invalid_package_spec")?
+		.arg("-p")
+		.arg("stable@latest")
+		.arg("--backend")
+		.arg("source")
+		.run_err()?;
+	assert_eq!("invalid version `latest` in package ID specification `stable@latest`", error.to_string());
+	Ok(())
+}
@@ -0,0 +1,79 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "exit_codes"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+#[test]
+fn exit_code_unused() -> Fallible<()> {
+	let (code, _) = Runner::new("cargo_udeps_test_exit_codes_exit_code_unused")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--exit-code-unused")
+		.arg("42")
+		.run()?;
+	assert_eq!(42, code);
+	Ok(())
+}
+
+#[test]
+fn max_duration() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_exit_codes_max_duration")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--max-duration")
+		.arg("0s")
+		.run()?;
+	assert_eq!(2, code);
+	assert!(stdout_masked.ends_with("Note: The time budget (`--max-duration`) was exhausted. These results are incomplete.\n"));
+	Ok(())
+}
+
+#[test]
+fn error() -> Fallible<()> {
+	let (err, _) = Runner::new("cargo_udeps_test_exit_codes_error")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "compile_error!(\"nope\");\n")?
+		.run_err()?;
+	assert_eq!(101, cargo_udeps::error_exit_code(&err));
+	Ok(())
+}
+
+// The tests run on nightly, so the message of a stable `rustc` is given as is.
+#[test]
+fn unsupported_toolchain() {
+	let err = failure::err_msg(
+		"`cargo check` didn't exit successfully (exit status: 101): error[E0554]: `#![feature]` may not be used on the stable release channel",
+	);
+	assert_eq!(3, cargo_udeps::error_exit_code(&err));
+	let err = failure::err_msg("error: the `-Z` flag is only accepted on the nightly channel of Cargo").context("could not load the workspace");
+	assert_eq!(3, cargo_udeps::error_exit_code(&err.into()));
+}
+
+#[test]
+fn no_fail() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_exit_codes_no_fail")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--no-fail")
+		.run()?;
+	assert_eq!(0, code);
+	assert!(stdout_masked.starts_with("unused dependencies:\n"), "{}", stdout_masked);
+	Ok(())
+}
@@ -0,0 +1,81 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "explain"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+"#;
+
+static LIB_RS: &str = r#"use maplit::hashmap;
+
+pub fn f() {
+	let _ = hashmap! { 1 => 2 };
+	let _ = maplit::hashset! { 1 };
+}
+"#;
+
+#[test]
+fn explain_used() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_explain_explain_used")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend=source")
+			.arg("explain")
+			.arg("-p")
+			.arg("explain")
+			.arg("maplit")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"`explain v0.0.0 (██████████)` uses "maplit" at:
+  src/lib.rs:1
+  src/lib.rs:5
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn explain_unused() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_explain_explain_unused")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend=source")
+			.arg("explain")
+			.arg("if_chain")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("`explain v0.0.0 (██████████)` does not use \"if_chain\".\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn explain_undeclared() -> Fallible<()> {
+	let (err, _) =
+		Runner::new("cargo_udeps_test_explain_explain_undeclared")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend=source")
+			.arg("explain")
+			.arg("serde")
+			.run_err()?;
+	assert_eq!("no package selected declares `serde`", err.to_string());
+	Ok(())
+}
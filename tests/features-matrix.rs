@@ -0,0 +1,256 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "features-matrix"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[features]
+be = []
+chain = []
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+maplit = "1.0.2"
+"#;
+
+static LIB_RS :&str = r#"#[cfg(feature = "be")]
+pub use byteorder::BigEndian;
+
+#[cfg(feature = "chain")]
+pub use if_chain::if_chain;
+"#;
+
+#[test]
+fn with_sets() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_features_matrix_with_sets")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--features-matrix")
+		.arg("be")
+		.arg("chain")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+used only with some features:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "byteorder" (with `be`)
+     └─── "if_chain" (with `chain`)
+Note: Dependencies used only with some features can be made `optional = true`,
+      and enabled by those features with `dep:<name>`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn from_metadata() -> Fallible<()> {
+	let cargo_toml = CARGO_TOML.replace(
+		"[workspace]\n",
+		"[workspace]\n[workspace.metadata.cargo-udeps]\nfeatures-matrix = [\"\", \"be\"]\n",
+	);
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_features_matrix_from_metadata")?
+		.cargo_toml(&cargo_toml)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--features-matrix")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "if_chain"
+     └─── "maplit"
+used only with some features:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "byteorder" (with `be`)
+Note: Dependencies used only with some features can be made `optional = true`,
+      and enabled by those features with `dep:<name>`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn each_feature() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_features_matrix_each_feature")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--each-feature")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+used only with some features:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "byteorder" (with `be`)
+     └─── "if_chain" (with `chain`)
+Note: Dependencies used only with some features can be made `optional = true`,
+      and enabled by those features with `dep:<name>`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn feature_powerset() -> Fallible<()> {
+	// `if_chain` is used only if both features are enabled.
+	let lib_rs = LIB_RS.replace(r#"#[cfg(feature = "chain")]"#, r#"#[cfg(all(feature = "be", feature = "chain"))]"#);
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_features_matrix_feature_powerset")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", &lib_rs)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--feature-powerset")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+used only with some features:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "byteorder" (with `be`)
+     └─── "if_chain" (with `be,chain`)
+Note: Dependencies used only with some features can be made `optional = true`,
+      and enabled by those features with `dep:<name>`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn optional() -> Fallible<()> {
+	let cargo_toml = CARGO_TOML
+		.replace("be = []", "be = [\"byteorder\"]")
+		.replace("chain = []", "chain = [\"dep:if_chain\"]")
+		.replace("byteorder = \"1.0.0\"", "byteorder = { version = \"1.0.0\", optional = true }")
+		.replace("if_chain = \"1.0.0\"", "if_chain = { version = \"1.0.0\", optional = true }");
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_features_matrix_optional")?
+		.cargo_toml(&cargo_toml)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--each-feature")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn feature_powerset_depth() -> Fallible<()> {
+	// `if_chain` is used only if both features are enabled, which `--depth 1` never does.
+	let lib_rs = LIB_RS.replace(r#"#[cfg(feature = "chain")]"#, r#"#[cfg(all(feature = "be", feature = "chain"))]"#);
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_features_matrix_feature_powerset_depth")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", &lib_rs)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--feature-powerset")
+		.arg("--depth")
+		.arg("1")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "if_chain"
+     └─── "maplit"
+used only with some features:
+`features-matrix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "byteorder" (with `be`)
+Note: Dependencies used only with some features can be made `optional = true`,
+      and enabled by those features with `dep:<name>`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn feature_powerset_too_large() -> Fallible<()> {
+	let features = (0..11).map(|i| format!("f{} = []\n", i)).collect::<String>();
+	let (err, _) = Runner::new("cargo_udeps_test_features_matrix_feature_powerset_too_large")?
+		.cargo_toml(&CARGO_TOML.replace("be = []\nchain = []\n", &features))?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--backend")
+		.arg("lint")
+		.arg("--feature-powerset")
+		.run_err()?;
+	assert_eq!(
+		"`--feature-powerset` would check `features-matrix` with 2048 feature sets (at most 1024). Limit it with `--depth`",
+		err.to_string(),
+	);
+	Ok(())
+}
@@ -0,0 +1,106 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "fix"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+# Used by `lib.rs`.
+byteorder = "1.0.0"
+if_chain = "1.0.0" # Unused.
+"#;
+
+static LIB_RS :&str = "use byteorder as _;\n";
+
+#[test]
+fn removes_unused() -> Fallible<()> {
+	let (code, stdout_masked, cargo_toml) =
+		Runner::new("cargo_udeps_test_fix_removes_unused")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--fix")
+			.run_and_read("Cargo.toml")?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`fix v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	assert_eq!(
+		r#"[workspace]
+[package]
+name = "fix"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+# Used by `lib.rs`.
+byteorder = "1.0.0"
+"#,
+		cargo_toml,
+	);
+	Ok(())
+}
+
+static CARGO_TOML_OPTIONAL :&str = r#"[workspace]
+[package]
+name = "fix"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[features]
+be = ["dep:byteorder"]
+std = ["byteorder?/std"]
+
+[dependencies]
+byteorder = { version = "1.0.0", optional = true }
+"#;
+
+#[test]
+fn removes_feature_references() -> Fallible<()> {
+	let (code, _, cargo_toml) =
+		Runner::new("cargo_udeps_test_fix_removes_feature_references")?
+			.cargo_toml(CARGO_TOML_OPTIONAL)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--all-targets")
+			.arg("--all-features")
+			.arg("--fix")
+			.run_and_read("Cargo.toml")?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"[workspace]
+[package]
+name = "fix"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[features]
+be = []
+std = []
+
+[dependencies]
+"#,
+		cargo_toml,
+	);
+	Ok(())
+}
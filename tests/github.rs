@@ -0,0 +1,52 @@
+mod runner;
+
+use std::env;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "github"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[target.'cfg(any(unix, windows))'.dependencies]
+libc = "0.2"
+
+[dev-dependencies.maplit]
+version = "1.0.2"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn annotations() -> Fallible<()> {
+	// Paths are relative to the checkout on GitHub Actions.
+	env::remove_var("GITHUB_WORKSPACE");
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_github_annotations")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("github")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"::warning file=Cargo.toml,line=9::unused dependency 'if_chain'
+::warning file=Cargo.toml,line=12::unused dependency 'libc'
+::warning file=Cargo.toml,line=14::unused dev-dependency 'maplit'
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,147 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+static A_CARGO_TOML: &str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static B_CARGO_TOML: &str = r#"[package]
+name = "b"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+
+[build-dependencies]
+maplit = "1.0.2"
+"#;
+
+static NOTE: &str = r#"Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#;
+
+fn runner(name :&str) -> Fallible<Runner> {
+	Ok(Runner::new(name)?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./a/src")?
+		.file("./a/Cargo.toml", A_CARGO_TOML)?
+		.file("./a/src/lib.rs", "")?
+		.dir("./b/src")?
+		.file("./b/Cargo.toml", B_CARGO_TOML)?
+		.file("./b/src/lib.rs", "")?
+		.file("./b/build.rs", "fn main() {}\n")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--all-targets")
+		.arg("--workspace"))
+}
+
+#[test]
+fn sort_by_count() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_group_by_sort_by_count")?.arg("--sort").arg("count").run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`b v0.0.0 (██████████/b)`
+├─── dependencies
+│    ├─── "if_chain"
+│    └─── "maplit"
+└─── build-dependencies
+     └─── "maplit"
+`a v0.0.0 (██████████/a)`
+├─── dependencies
+│    └─── "if_chain"
+└─── dev-dependencies
+     └─── "maplit"
+"#
+		.to_owned() + NOTE,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn group_by_dependency() -> Fallible<()> {
+	let (code, stdout_masked) =
+		runner("cargo_udeps_test_group_by_group_by_dependency")?.arg("--group-by").arg("dependency").run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+"if_chain"
+└─── dependencies
+     ├─── `a v0.0.0 (██████████/a)`
+     └─── `b v0.0.0 (██████████/b)`
+"maplit"
+├─── dependencies
+│    └─── `b v0.0.0 (██████████/b)`
+├─── dev-dependencies
+│    └─── `a v0.0.0 (██████████/a)`
+└─── build-dependencies
+     └─── `b v0.0.0 (██████████/b)`
+"#
+		.to_owned() + NOTE,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn group_by_kind() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_group_by_group_by_kind")?.arg("--group-by").arg("kind").run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`[dependencies]`
+├─── `a v0.0.0 (██████████/a)`
+│    └─── "if_chain"
+└─── `b v0.0.0 (██████████/b)`
+     ├─── "if_chain"
+     └─── "maplit"
+`[dev-dependencies]`
+└─── `a v0.0.0 (██████████/a)`
+     └─── "maplit"
+`[build-dependencies]`
+└─── `b v0.0.0 (██████████/b)`
+     └─── "maplit"
+"#
+		.to_owned() + NOTE,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn aggregate() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_group_by_aggregate")?.arg("--aggregate").run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+"if_chain" is unused in 2 packages: `a`, `b`
+"maplit" is unused in 2 packages: `a` (dev), `b` (normal, build)
+"#
+		.to_owned() + NOTE,
+		stdout_masked,
+	);
+	Ok(())
+}
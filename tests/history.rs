@@ -0,0 +1,72 @@
+#![cfg(feature = "history")]
+
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+use tempdir::TempDir;
+
+use crate::runner::Runner;
+
+static REPORT_1_JSON: &str = r#"{
+  "success": false,
+  "unused_deps": {
+    "a 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)": {
+      "manifest_path": "a/Cargo.toml",
+      "normal": ["if_chain", "maplit"],
+      "development": [],
+      "build": []
+    }
+  },
+  "note": null
+}
+"#;
+
+static REPORT_2_JSON: &str = r#"{
+  "success": false,
+  "unused_deps": {
+    "b 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)": {
+      "manifest_path": "b/Cargo.toml",
+      "normal": [],
+      "development": ["maplit"],
+      "build": []
+    }
+  },
+  "note": null
+}
+"#;
+
+#[test]
+fn record_and_trend() -> Fallible<()> {
+	let dir = TempDir::new("cargo_udeps_test_history_record_and_trend")?;
+	let database = dir.path().join("history.sqlite");
+
+	for (i, report) in [REPORT_1_JSON, REPORT_2_JSON].iter().enumerate() {
+		let (code, _) = Runner::new(&format!("cargo_udeps_test_history_record_{}", i))?
+			.file("report.json", report)?
+			.arg("record")
+			.arg("--database")
+			.arg(&database)
+			.arg("report.json")
+			.run()?;
+		assert_eq!(0, code);
+	}
+
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_history_trend")?
+		.arg("--output")
+		.arg("json")
+		.arg("trend")
+		.arg("--database")
+		.arg(&database)
+		.run()?;
+	assert_eq!(0, code);
+	let rows = serde_json::from_str::<Vec<serde_json::Value>>(&stdout_masked)?
+		.iter()
+		.map(|row| (row["run"].as_i64().unwrap(), row["package"].as_str().unwrap().to_owned(), row["unused"].as_i64().unwrap()))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![(1, "a".to_owned(), 2), (2, "a".to_owned(), 0), (1, "b".to_owned(), 0), (2, "b".to_owned(), 1)],
+		rows,
+	);
+	Ok(())
+}
@@ -0,0 +1,39 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "html"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn report_path() -> Fallible<()> {
+	let (code, stdout_masked, report) =
+		Runner::new("cargo_udeps_test_html_report_path")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("html")
+			.arg("--report-path")
+			.arg("report.html")
+			.run_and_read("report.html")?;
+	assert_eq!(1, code);
+	assert_eq!("", stdout_masked);
+	assert!(report.starts_with("<!DOCTYPE html>\n"));
+	assert!(report.contains("<tr><td>dependencies</td><td><code>if_chain</code></td></tr>\n"));
+	assert!(report.ends_with("</html>\n"));
+	Ok(())
+}
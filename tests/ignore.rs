@@ -1,12 +1,12 @@
 mod runner;
 
-use cargo::CargoResult;
+use failure::Fallible;
 use pretty_assertions::assert_eq;
 
 use crate::runner::Runner;
 
 #[test]
-fn ignore_if_chain() -> CargoResult<()> {
+fn ignore_if_chain() -> Fallible<()> {
 	static CARGO_TOML: &str = r#"[workspace]
 [package]
 name = "ignore-if-chain"
@@ -49,7 +49,7 @@ Note: They might be false-positive.
 }
 
 #[test]
-fn ignore_all() -> CargoResult<()> {
+fn ignore_all() -> Fallible<()> {
 	static CARGO_TOML: &str = r#"[workspace]
 [package]
 name = "ignore-all"
@@ -78,3 +78,238 @@ matches = "0.1.8"
 	assert_eq!("All deps seem to have been used.\n", stdout_masked);
 	Ok(())
 }
+
+#[test]
+fn ignore_flag() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-flag"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+
+[dev-dependencies]
+matches = "0.1.8"
+"#;
+
+	static LIB_RS: &str = "";
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_flag")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--backend")
+			.arg("source")
+			.arg("--ignore")
+			.arg("if_chain")
+			.arg("--ignore")
+			.arg("dev:matches")
+			.arg("--ignore")
+			.arg("dev:maplit")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`ignore-flag v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn ignore_in_workspace() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+
+[workspace.metadata.cargo-udeps.ignore]
+normal = ["if_chain"]
+
+[workspace.metadata.cargo-udeps.ignore.members.b]
+normal = ["maplit"]
+"#;
+
+	static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["matches"]
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+matches = "0.1.8"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_in_workspace")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./a/src")?
+			.file("./a/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "a"))?
+			.file("./a/src/lib.rs", "")?
+			.dir("./b/src")?
+			.file("./b/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "b"))?
+			.file("./b/src/lib.rs", "")?
+			.arg("--workspace")
+			.arg("--backend")
+			.arg("source")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     └─── "maplit"
+`b v0.0.0 (██████████/b)`
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn ignore_comment() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-comment"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0" # cargo-udeps: ignore (used only in doctests)
+maplit = { version = "1.0.2" } #cargo-udeps:ignore
+# cargo-udeps: ignore
+matches = "0.1.8"
+"#;
+
+	static LIB_RS: &str = "";
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_comment")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend")
+			.arg("source")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`ignore-comment v0.0.0 (██████████)`
+└─── dependencies
+     └─── "matches"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn stale() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "stale"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain", "maplit"]
+build = ["cc"]
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_stale")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(0, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let stale = outcome["stale_ignores"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|s| (s["kind"].clone(), s["dependency"].clone()))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			(serde_json::json!("normal"), serde_json::json!("maplit")),
+			(serde_json::json!("build"), serde_json::json!("cc")),
+		],
+		stale,
+	);
+	Ok(())
+}
+
+#[test]
+fn unnecessary() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "unnecessary"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain"]
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+	static LIB_RS: &str = r#"use if_chain as _;
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_unnecessary")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend")
+			.arg("source")
+			.arg("--deny")
+			.arg("unnecessary-ignore")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unnecessary ignores:
+`unnecessary v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain" (used, remove it from the ignored ones)
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,45 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "incremental"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0" }
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "pub use bo::BigEndian;\n";
+
+static EXPECTED :&str = r#"unused dependencies:
+`incremental v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#;
+
+#[test]
+fn fresh_units_reuse_records() -> Fallible<()> {
+	let ((code1, stdout1), (code2, stdout2)) =
+		Runner::new("cargo_udeps_test_incremental_fresh_units_reuse_records")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--backend")
+			.arg("lint")
+			.run_twice()?;
+	assert_eq!((1, EXPECTED), (code1, &*stdout1));
+	assert_eq!((1, EXPECTED), (code2, &*stdout2));
+	Ok(())
+}
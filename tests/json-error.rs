@@ -0,0 +1,31 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn compile_error() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "json_error"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+	let (err, stdout_masked) =
+		Runner::new("cargo_udeps_test_json_error_compile_error")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "compile_error!(\"nope\");\n")?
+			.arg("--output")
+			.arg("json")
+			.run_err()?;
+	let json = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(serde_json::json!(false), json["success"]);
+	assert_eq!(serde_json::json!(err.to_string()), json["error"]["message"]);
+	assert!(json["error"]["message"].as_str().unwrap().contains("cargo check"), "{}", json);
+	Ok(())
+}
@@ -0,0 +1,49 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "junit"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn failures() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_junit_failures")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("junit")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="cargo-udeps" tests="3" failures="1">
+  <testsuite name="junit v0.0.0 (██████████)" tests="3" failures="1">
+    <testcase classname="junit v0.0.0 (██████████)" name="dependencies">
+      <failure type="unused-dependency" message="unused dependency `if_chain`">██████████/Cargo.toml</failure>
+      <failure type="unused-dependency" message="unused dependency `maplit`">██████████/Cargo.toml</failure>
+    </testcase>
+    <testcase classname="junit v0.0.0 (██████████)" name="dev-dependencies"/>
+    <testcase classname="junit v0.0.0 (██████████)" name="build-dependencies"/>
+  </testsuite>
+</testsuites>
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
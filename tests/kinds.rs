@@ -0,0 +1,60 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "kinds"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+
+[dev-dependencies]
+if_chain = "1.0.0"
+"#;
+
+fn runner(name :&str) -> Fallible<Runner> {
+	Ok(Runner::new(name)?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--all-targets"))
+}
+
+#[test]
+fn normal_build() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_kinds_normal_build")?.arg("--kinds").arg("normal,build").run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`kinds v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn build() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_kinds_build")?
+		.file("./src/lib.rs", "use maplit as _;\n")?
+		.arg("--kinds")
+		.arg("build")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
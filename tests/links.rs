@@ -0,0 +1,55 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn dep_env_in_build_script() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "links"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+foo-sys = { path = "./foo-sys" }
+"#;
+
+	static FOO_SYS_CARGO_TOML: &str = r#"[package]
+name = "foo-sys"
+version = "0.0.0"
+edition = "2018"
+publish = false
+links = "foo"
+"#;
+
+	static FOO_SYS_BUILD_RS: &str = r#"fn main() {
+    println!("cargo:include=/nonexistent");
+}
+"#;
+
+	static BUILD_RS: &str = r#"fn main() {
+    let _ = std::env::var("DEP_FOO_INCLUDE");
+}
+"#;
+
+	static LIB_RS: &str = "";
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_links_dep_env_in_build_script")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./build.rs", BUILD_RS)?
+			.dir("./foo-sys/src")?
+			.file("./foo-sys/Cargo.toml", FOO_SYS_CARGO_TOML)?
+			.file("./foo-sys/src/lib.rs", LIB_RS)?
+			.file("./foo-sys/build.rs", FOO_SYS_BUILD_RS)?
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
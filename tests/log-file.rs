@@ -0,0 +1,36 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn log_file() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "log_file"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, _, log) =
+		Runner::new("cargo_udeps_test_log_file_log_file")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("lint")
+			.arg("--log-file")
+			.arg("udeps.log")
+			.run_and_read("udeps.log")?;
+	assert_eq!(1, code);
+	for expected in &[" INFO ", "running `", " check ", " DEBUG ", "`--extern` of `lib`", "name=maplit"] {
+		assert!(log.contains(expected), "no {:?} in {}", expected, log);
+	}
+	Ok(())
+}
@@ -0,0 +1,59 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "markdown"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn table() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_markdown_table")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("markdown")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"## Unused dependencies
+
+### `markdown v0.0.0` (`Cargo.toml`)
+
+| Kind | Dependency |
+| ---- | ---------- |
+| dependencies | `if_chain` |
+| dev-dependencies | `maplit` |
+
+<details><summary>Ignoring false positives</summary>
+
+```toml
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain"]
+development = ["maplit"]
+```
+
+</details>
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,59 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static SHARD_1_JSON: &str = r#"{
+  "success": false,
+  "unused_deps": {
+    "a 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)": {
+      "manifest_path": "a/Cargo.toml",
+      "normal": ["if_chain"],
+      "development": [],
+      "build": []
+    }
+  },
+  "note": null
+}
+"#;
+
+static SHARD_2_JSON: &str = r#"{
+  "success": false,
+  "unused_deps": {
+    "b 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)": {
+      "manifest_path": "b/Cargo.toml",
+      "normal": [],
+      "development": ["maplit"],
+      "build": []
+    }
+  },
+  "note": null
+}
+"#;
+
+#[test]
+fn merge_shards() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_merge_merge_shards")?
+			.file("shard-1.json", SHARD_1_JSON)?
+			.file("shard-2.json", SHARD_2_JSON)?
+			.arg("merge")
+			.arg("shard-1.json")
+			.arg("shard-2.json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.1.0`
+└─── dependencies
+     └─── "if_chain"
+`b v0.1.0`
+└─── dev-dependencies
+     └─── "maplit"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,37 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "message-format"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+#[test]
+fn json() -> Fallible<()> {
+	let (code, stdout_masked, stderr_masked) =
+		Runner::new("cargo_udeps_test_message_format_json")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--message-format")
+			.arg("json")
+			.arg("--output")
+			.arg("json")
+			.run_with_stderr()?;
+	assert_eq!(1, code);
+	// The messages of Cargo go to stderr.
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(serde_json::json!(["if_chain"]), outcome["unused_deps"][0]["normal"]);
+	assert!(stderr_masked.lines().any(|l| l.contains(r#""reason":"build-finished""#)), "{}", stderr_masked);
+	Ok(())
+}
@@ -0,0 +1,88 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn metrics_file() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "metrics"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+matches = "0.1.8"
+
+[dev-dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let (code, _, metrics) =
+		Runner::new("cargo_udeps_test_metrics_metrics_file")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--metrics-file")
+			.arg("metrics.prom")
+			.run_and_read("metrics.prom")?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"# HELP cargo_udeps_unused_total The number of the unused dependencies.
+# TYPE cargo_udeps_unused_total gauge
+cargo_udeps_unused_total{package="metrics",kind="normal"} 2
+cargo_udeps_unused_total{package="metrics",kind="development"} 1
+cargo_udeps_unused_total{package="metrics",kind="build"} 0
+# HELP cargo_udeps_success Whether no problem was found.
+# TYPE cargo_udeps_success gauge
+cargo_udeps_success 0
+"#,
+		metrics,
+	);
+	Ok(())
+}
+
+#[test]
+fn no_packages() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "metrics"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps]
+skip = true
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, _, metrics) =
+		Runner::new("cargo_udeps_test_metrics_no_packages")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--metrics-file")
+			.arg("metrics.prom")
+			.run_and_read("metrics.prom")?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"# HELP cargo_udeps_unused_total The number of the unused dependencies.
+# TYPE cargo_udeps_unused_total gauge
+# HELP cargo_udeps_success Whether no problem was found.
+# TYPE cargo_udeps_success gauge
+cargo_udeps_success 1
+"#,
+		metrics,
+	);
+	Ok(())
+}
@@ -0,0 +1,128 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "misplaced"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+itoa = "1.0.0"
+"#;
+
+static LIB_RS :&str = r#"pub fn f(x :bool) -> bool {
+	if_chain::if_chain! {
+		if x;
+		then { return true; }
+	}
+	false
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn f() {
+		assert_eq!("1", itoa::Buffer::new().format(1));
+	}
+}
+"#;
+
+#[test]
+fn test_only() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_misplaced_test_only")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+used only by tests, benches or examples:
+`misplaced v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa" (move it to `[dev-dependencies]`)
+Note: Misplaced dependencies are built for the dependents of the package as well.
+      Move them to the suggested table.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn without_tests() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_misplaced_without_tests")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`misplaced v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa"
+Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+static CARGO_TOML_BUILD :&str = r#"[workspace]
+[package]
+name = "misplaced"
+version = "0.0.0"
+edition = "2018"
+publish = false
+build = "build.rs"
+
+[dependencies]
+itoa = "1.0.0"
+"#;
+
+#[test]
+fn build_only() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_misplaced_build_only")?
+		.cargo_toml(CARGO_TOML_BUILD)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.file("./build.rs", "fn main() {\n\tprintln!(\"{}\", itoa::Buffer::new().format(1));\n}\n")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("source")
+		.arg("--deny")
+		.arg("misplaced")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"used only by build scripts:
+`misplaced v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa" (move it to `[build-dependencies]`)
+Note: Misplaced dependencies are built for the dependents of the package as well.
+      Move them to the suggested table.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,70 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "multiple-versions"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+itoa = "0.4"
+itoa1 = { package = "itoa", version = "1" }
+"#;
+
+static LIB_RS :&str = "/// Formats with [`itoa1::Buffer`].\npub fn f() {}\n";
+
+#[test]
+fn rustdoc_json() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_multiple_versions_rustdoc_json")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("rustdoc-json")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`multiple-versions v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn lint() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_multiple_versions_lint")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub fn f() -> itoa1::Buffer {\n\titoa1::Buffer::new()\n}\n")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`multiple-versions v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
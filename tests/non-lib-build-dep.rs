@@ -1,6 +1,6 @@
 mod runner;
 
-use cargo::CargoResult;
+use failure::Fallible;
 use pretty_assertions::assert_eq;
 
 use crate::runner::Runner;
@@ -18,7 +18,7 @@ diffr = "=0.1.3"
 static LIB_RS :&str = "";
 
 #[test]
-fn without_all_targets() -> CargoResult<()> {
+fn without_all_targets() -> Fallible<()> {
 	let (code, stdout_masked) =
 		Runner::new("cargo_udeps_test_non_lib_build_dep_without_all_targets")?
 			.cargo_toml(CARGO_TOML)?
@@ -46,7 +46,7 @@ Note: They might be false-positive.
 
 
 #[test]
-fn with_all_targets() -> CargoResult<()> {
+fn with_all_targets() -> Fallible<()> {
 	let (code, stdout_masked) =
 		Runner::new("cargo_udeps_test_non_lib_build_dep_with_all_targets")?
 			.cargo_toml(CARGO_TOML)?
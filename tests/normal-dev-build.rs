@@ -1,6 +1,6 @@
 mod runner;
 
-use cargo::CargoResult;
+use failure::Fallible;
 use pretty_assertions::assert_eq;
 
 use crate::runner::Runner;
@@ -27,7 +27,7 @@ static LIB_RS: &str = "";
 static BUILD_RS: &str = "fn main() {}\n";
 
 #[test]
-fn without_all_targets() -> CargoResult<()> {
+fn without_all_targets() -> Fallible<()> {
 	let (code, stdout_masked) =
 		Runner::new("cargo_udeps_test_normal_dev_build_without_all_targets")?
 			.cargo_toml(CARGO_TOML)?
@@ -56,7 +56,7 @@ Note: They might be false-positive.
 
 
 #[test]
-fn with_all_targets() -> CargoResult<()> {
+fn with_all_targets() -> Fallible<()> {
 	let (code, stdout_masked) =
 		Runner::new("cargo_udeps_test_normal_dev_build_with_all_targets")?
 			.cargo_toml(CARGO_TOML)?
@@ -0,0 +1,63 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "notes"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+#[test]
+fn notes_json() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_notes_notes_json")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(
+		serde_json::json!([
+			{ "code": "missing-all-targets", "suggest_all_targets": true },
+			{ "code": "possible-false-positive", "doctests_analyzed": false },
+		]),
+		outcome["notes"],
+	);
+	Ok(())
+}
+
+#[test]
+fn notes_human() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_notes_notes_human")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--all-targets")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`notes v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,133 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "optional-deps"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[features]
+be = ["byteorder?/std"]
+chain = ["dep:if_chain"]
+
+[dependencies]
+byteorder = { version = "1.0.0", optional = true }
+if_chain = { version = "1.0.0", optional = true }
+maplit = { version = "1.0.2", optional = true }
+"#;
+
+#[test]
+fn never_enabled() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_optional_deps_never_enabled")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+optional dependencies not enabled by any feature:
+`optional-deps v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "byteorder"
+     └─── "maplit"
+Note: Optional dependencies which no feature enables can still be enabled with `--features <name>`.
+      Enable them from a feature with `dep:<name>`, or remove them.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn weakly_enabled() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_optional_deps_weakly_enabled")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("source")
+		.arg("--features")
+		.arg("be")
+		.arg("--deny")
+		.arg("never-enabled")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"optional dependencies not enabled by any feature:
+`optional-deps v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "byteorder"
+     └─── "maplit"
+not analyzed:
+`optional-deps v0.0.0 (██████████)`
+└─── optional dependencies not enabled
+     └─── "byteorder" (`byteorder?/..` in `be` does not enable it)
+Note: Optional dependencies which no feature enables can still be enabled with `--features <name>`.
+      Enable them from a feature with `dep:<name>`, or remove them.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+static CARGO_TOML_RENAMED :&str = r#"[workspace]
+[package]
+name = "optional-deps"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[features]
+be = ["dep:bo"]
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0", optional = true }
+chain = { package = "if_chain", version = "1.0.0", optional = true }
+"#;
+
+#[test]
+fn renamed() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_optional_deps_renamed")?
+		.cargo_toml(CARGO_TOML_RENAMED)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--features")
+		.arg("be")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`optional-deps v0.0.0 (██████████)`
+└─── dependencies
+     └─── "bo" (byteorder)
+optional dependencies not enabled by any feature:
+`optional-deps v0.0.0 (██████████)`
+└─── dependencies
+     └─── "chain" (if_chain)
+Note: Optional dependencies which no feature enables can still be enabled with `--features <name>`.
+      Enable them from a feature with `dep:<name>`, or remove them.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
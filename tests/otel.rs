@@ -0,0 +1,68 @@
+mod runner;
+
+use std::io::{BufRead as _, BufReader, Read as _, Write as _};
+use std::net::TcpListener;
+use std::thread;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "otel"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+#[test]
+fn otel_endpoint() -> Fallible<()> {
+	let listener = TcpListener::bind("127.0.0.1:0")?;
+	let endpoint = format!("http://{}", listener.local_addr()?);
+	// Accepts a single request, and returns its request line and body.
+	let collector = thread::spawn(move || -> Fallible<(String, String)> {
+		let (stream, _) = listener.accept()?;
+		let mut reader = BufReader::new(stream);
+		let mut request_line = String::new();
+		reader.read_line(&mut request_line)?;
+		let mut content_length = 0;
+		loop {
+			let mut header = String::new();
+			reader.read_line(&mut header)?;
+			if header.trim_end().is_empty() {
+				break;
+			}
+			if let Some(length) = header.to_lowercase().strip_prefix("content-length:") {
+				content_length = length.trim().parse()?;
+			}
+		}
+		let mut body = vec![0; content_length];
+		reader.read_exact(&mut body)?;
+		reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")?;
+		Ok((request_line.trim_end().to_owned(), String::from_utf8(body)?))
+	});
+
+	let (code, _) = Runner::new("cargo_udeps_test_otel_otel_endpoint")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--otel-endpoint")
+		.arg(&endpoint)
+		.run()?;
+	assert_eq!(1, code);
+
+	let (request_line, body) = collector.join().expect("should not panic")?;
+	assert_eq!("POST /v1/traces HTTP/1.1", request_line);
+	let traces = serde_json::from_str::<serde_json::Value>(&body)?;
+	let spans = traces["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().cloned().unwrap_or_default();
+	let names = spans.iter().filter_map(|s| s["name"].as_str()).collect::<Vec<_>>();
+	for name in &["cargo udeps", "compile", "rustc otel", "analyze", "collect otel"] {
+		assert!(names.contains(name), "no `{}` in {:?}", name, names);
+	}
+	Ok(())
+}
@@ -0,0 +1,119 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn package_keys() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "package_keys"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_package_keys_package_keys")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(
+		serde_json::json!({
+			"name": "package_keys",
+			"version": "0.0.0",
+			"manifest_path": "██████████/Cargo.toml",
+		}),
+		outcome["unused_deps"][0]["package"],
+	);
+	assert_eq!(serde_json::json!(["maplit"]), outcome["unused_deps"][0]["normal"]);
+	Ok(())
+}
+
+#[test]
+fn no_package_ids() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "no_package_ids"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain"]
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_package_keys_no_package_ids")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let package = serde_json::json!({
+		"name": "no_package_ids",
+		"version": "0.0.0",
+		"manifest_path": "██████████/Cargo.toml",
+	});
+	assert_eq!(package, outcome["suggestions"][0]["package"]);
+	assert_eq!(package, outcome["stale_ignores"][0]["package"]);
+	assert_eq!(package, outcome["warnings"][0]["package"]);
+	assert!(!stdout_masked.contains("path+file"), "{}", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn read_package_keys() -> Fallible<()> {
+	static OUTCOME_JSON: &str = r#"{
+  "schema_version": 2,
+  "success": false,
+  "unused_deps": [
+    {
+      "package": {
+        "name": "a",
+        "version": "0.1.0",
+        "manifest_path": "/workspace/a/Cargo.toml"
+      },
+      "manifest_path": "/workspace/a/Cargo.toml",
+      "normal": ["if_chain"],
+      "development": [],
+      "build": []
+    }
+  ],
+  "note": null
+}
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_package_keys_read_package_keys")?
+			.file("outcome.json", OUTCOME_JSON)?
+			.arg("merge")
+			.arg("outcome.json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.1.0 (/workspace/a)`
+└─── dependencies
+     └─── "if_chain"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
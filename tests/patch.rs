@@ -0,0 +1,99 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "patch"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+byteorder = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS :&str = "use byteorder as _;\n";
+
+#[test]
+fn unified_diff() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_patch_unified_diff")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("patch")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -6,8 +6,6 @@
+ publish = false
+ 
+ [dependencies]
+-if_chain = "1.0.0"
+ byteorder = "1.0.0"
+ 
+ [dev-dependencies]
+-maplit = "1.0.2"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+static CARGO_TOML_DEFAULT_FEATURES :&str = r#"[workspace]
+[package]
+name = "patch"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+byteorder = "1.0.0"
+"#;
+
+static LIB_RS_DEFAULT_FEATURES :&str =
+	"pub fn read(buf :&[u8]) -> u32 { <byteorder::BigEndian as byteorder::ByteOrder>::read_u32(buf) }\n";
+
+#[test]
+fn default_features() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_patch_default_features")?
+			.cargo_toml(CARGO_TOML_DEFAULT_FEATURES)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS_DEFAULT_FEATURES)?
+			.arg("--all-targets")
+			.arg("--backend")
+			.arg("lint")
+			.arg("--unused-features")
+			.arg("--output")
+			.arg("patch")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -6,5 +6,4 @@
+ publish = false
+ 
+ [dependencies]
+-if_chain = "1.0.0"
+-byteorder = "1.0.0"
++byteorder = { version = "1.0.0", default-features = false }
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,121 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["a"]
+
+[package]
+name = "paths"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+static A_CARGO_TOML: &str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+fn runner(name :&str) -> Fallible<Runner> {
+	Ok(Runner::new(name)?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.dir("./a/src")?
+		.file("./a/Cargo.toml", A_CARGO_TOML)?
+		.file("./a/src/lib.rs", "")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--workspace")
+		.arg("--paths")
+		.arg("relative"))
+}
+
+#[test]
+fn relative_human() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_paths_relative_human")?.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (a)`
+└─── dependencies
+     └─── "maplit"
+`paths v0.0.0 (.)`
+└─── dependencies
+     └─── "if_chain"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn relative_json() -> Fallible<()> {
+	let (code, stdout_masked) =
+		runner("cargo_udeps_test_paths_relative_json")?.arg("--output").arg("json").run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let unused_deps = outcome["unused_deps"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|u| (u["package"]["name"].clone(), u["package"]["manifest_path"].clone(), u["manifest_path"].clone()))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			(serde_json::json!("a"), serde_json::json!("a/Cargo.toml"), serde_json::json!("a/Cargo.toml")),
+			(serde_json::json!("paths"), serde_json::json!("Cargo.toml"), serde_json::json!("Cargo.toml")),
+		],
+		unused_deps,
+	);
+	Ok(())
+}
+
+#[test]
+fn relative_patch_outside_root() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_paths_relative_patch_outside_root")?
+			.dir("./ws/src")?
+			.file("./ws/Cargo.toml", CARGO_TOML)?
+			.file("./ws/src/lib.rs", "")?
+			.dir("./ws/a/src")?
+			.file("./ws/a/Cargo.toml", A_CARGO_TOML)?
+			.file("./ws/a/src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--manifest-path")
+			.arg("ws/a/Cargo.toml")
+			.arg("--paths")
+			.arg("relative")
+			.arg("--output")
+			.arg("patch")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"--- a/ws/a/Cargo.toml
++++ b/ws/a/Cargo.toml
+@@ -5,4 +5,3 @@
+ publish = false
+ 
+ [dependencies]
+-maplit = "1.0.2"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,45 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "progress"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+#[test]
+fn always() -> Fallible<()> {
+	let (code, _, stderr) = Runner::new("cargo_udeps_test_progress_always")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./.cargo")?
+		.file("./.cargo/config.toml", "[term]\nprogress = { when = \"always\", width = 80 }\n")?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.run_with_stderr()?;
+	assert_eq!(1, code);
+	assert!(stderr.contains("   Analyzing [>                        ] 0/1: progress\x1b[K"), "{:?}", stderr);
+	Ok(())
+}
+
+#[test]
+fn never() -> Fallible<()> {
+	let (code, _, stderr) = Runner::new("cargo_udeps_test_progress_never")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./.cargo")?
+		.file("./.cargo/config.toml", "[term]\nprogress = { when = \"never\" }\n")?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.run_with_stderr()?;
+	assert_eq!(1, code);
+	assert!(!stderr.contains("Analyzing"), "{:?}", stderr);
+	Ok(())
+}
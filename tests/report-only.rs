@@ -0,0 +1,63 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+fn runner(name :&str, a_lib_rs :&str) -> Fallible<Runner> {
+	Ok(Runner::new(name)?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./a/src")?
+		.file("./a/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "a"))?
+		.file("./a/src/lib.rs", a_lib_rs)?
+		.dir("./b/src")?
+		.file("./b/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "b"))?
+		.file("./b/src/lib.rs", "")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--report-only")
+		.arg("-p")
+		.arg("a"))
+}
+
+#[test]
+fn report_only() -> Fallible<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_report_only_report_only", "")?.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn other_packages_unused() -> Fallible<()> {
+	let (code, stdout_masked) =
+		runner("cargo_udeps_test_report_only_other_packages_unused", "use maplit as _;\n")?.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
@@ -0,0 +1,37 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn response_file() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "response-file"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+	static LIB_RS: &str = "";
+
+	static ARGS_TXT: &str = "--all-targets\n--output\njson\n";
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_response_file_response_file")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./args.txt", ARGS_TXT)?
+			.arg("@args.txt")
+			.run()?;
+	assert_eq!(1, code);
+	assert!(stdout_masked.starts_with('{'), "{}", stdout_masked);
+	assert!(stdout_masked.contains(r#""development":["maplit"]"#), "{}", stdout_masked);
+	Ok(())
+}
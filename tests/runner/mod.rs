@@ -1,11 +1,15 @@
+// Not every test uses every method.
+#![allow(dead_code)]
+
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::path::Path;
 use std::process::{Command, Output};
 use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, Mutex};
 use std::{env, fs, io, str};
 
-use cargo::core::shell::Shell;
-use cargo::{CargoResult, CliError};
+use cargo_udeps::{Config, Shell};
+use failure::Fallible;
 use tempdir::TempDir;
 
 static DEFAULT_TOOLCHAIN :&str = "nightly";
@@ -14,12 +18,11 @@ static RUSTC_ENV_SET :AtomicBool = AtomicBool::new(false);
 
 pub(crate) struct Runner {
 	cwd :TempDir,
-	homedir :PathBuf,
 	args :Vec<OsString>,
 }
 
 impl Runner {
-	pub(crate) fn new(prefix :&str) -> CargoResult<Self> {
+	pub(crate) fn new(prefix :&str) -> Fallible<Self> {
 		if !RUSTC_ENV_SET.swap(true, atomic::Ordering::SeqCst) {
 			let toolchain =
 				env::var("CARGO_UDEPS_TEST_TOOLCHAIN").unwrap_or_else(|_| DEFAULT_TOOLCHAIN.to_owned());
@@ -33,13 +36,13 @@ impl Runner {
 					.into());
 			}
 			env::set_var("RUSTC", str::from_utf8(&stdout)?.trim());
+			// `--charset auto` follows the locale.
+			env::set_var("LC_ALL", "C.UTF-8");
 		}
 		let cwd = TempDir::new(prefix)?;
-		let homedir = dirs::home_dir().expect("couldn't get the current directory of the process");
 		let args = vec!["".into(), "udeps".into()];
 		Ok(Self {
 			cwd,
-			homedir,
 			args,
 		})
 	}
@@ -60,26 +63,94 @@ impl Runner {
 		Ok(self)
 	}
 
+	/// Runs `git` in the directory, e.g. to commit the files written so far.
+	pub(crate) fn git(self, args :&[&str]) -> Fallible<Self> {
+		let status = Command::new("git")
+			.args(&["-c", "user.name=cargo-udeps", "-c", "user.email=cargo-udeps@example.com"])
+			.args(args)
+			.current_dir(self.cwd.path())
+			.status()?;
+		if !status.success() {
+			return Err(failure::err_msg(format!("`git {}` failed: {}", args.join(" "), status)));
+		}
+		Ok(self)
+	}
+
+	/// The temporary directory the files are written to.
+	pub(crate) fn path(&self) -> &Path {
+		self.cwd.path()
+	}
+
 	pub(crate) fn arg<S: Into<OsString>>(mut self, arg: S) -> Self {
 		self.args.push(arg.into());
 		self
 	}
 
-	pub(crate) fn run(self) -> CargoResult<(i32, String)> {
+	pub(crate) fn run(self) -> Fallible<(i32, String)> {
+		self.run_in_place()
+	}
+
+	/// Runs, then reads `file_name` before the temporary directory is removed.
+	pub(crate) fn run_and_read(self, file_name :&str) -> Fallible<(i32, String, String)> {
+		let (code, stdout) = self.run_in_place()?;
+		let content = fs::read_to_string(self.cwd.path().join(file_name))?;
+		Ok((code, stdout, content))
+	}
+
+	/// Runs twice in the same directory, e.g. to check that the second run reuses the build.
+	pub(crate) fn run_twice(self) -> Fallible<((i32, String), (i32, String))> {
+		let first = self.run_in_place()?;
+		let second = self.run_in_place()?;
+		Ok((first, second))
+	}
+
+	/// Runs a failing invocation, and returns the error and what was written to stdout.
+	pub(crate) fn run_err(self) -> Fallible<(failure::Error, String)> {
 		let mut stdout = vec![];
-		let stderr = Shell::from_write(Box::new(vec![]));
-		let mut config = cargo::Config::new(stderr, self.cwd.path().to_owned(), self.homedir.clone());
-		let code = match cargo_udeps::run(self.args.clone(), &mut config, &mut stdout) {
-			Ok(()) => 0,
-			Err(CliError {
-				error, exit_code, ..
-			}) => match error {
-				None => exit_code,
-				Some(error) => return Err(error),
+		let mut config = Config::new(Shell::from_write(Box::new(vec![])), self.cwd.path().to_owned())?;
+		config.set_rustc_wrapper(env!("CARGO_BIN_EXE_cargo-udeps").into());
+		match cargo_udeps::run(self.args.clone(), &mut config, &mut stdout) {
+			Ok(code) => Err(failure::err_msg(format!("expected an error, but exited with {}", code))),
+			Err(err) => {
+				let stdout = str::from_utf8(&stdout)?.replace(&*self.cwd.path().to_string_lossy(), "██████████");
+				Ok((err, stdout))
 			}
-		};
+		}
+	}
+
+	/// Runs, and returns the status messages as well.
+	pub(crate) fn run_with_stderr(self) -> Fallible<(i32, String, String)> {
+		let stderr = SharedBuffer::default();
+		let (code, stdout) = self.run_with_shell(Shell::from_write(Box::new(stderr.clone())))?;
+		let stderr = String::from_utf8(stderr.0.lock().unwrap().clone())?;
+		Ok((code, stdout, stderr.replace(&*self.cwd.path().to_string_lossy(), "██████████")))
+	}
+
+	fn run_in_place(&self) -> Fallible<(i32, String)> {
+		self.run_with_shell(Shell::from_write(Box::new(vec![])))
+	}
+
+	fn run_with_shell(&self, stderr :Shell) -> Fallible<(i32, String)> {
+		let mut stdout = vec![];
+		let mut config = Config::new(stderr, self.cwd.path().to_owned())?;
+		config.set_rustc_wrapper(env!("CARGO_BIN_EXE_cargo-udeps").into());
+		let code = cargo_udeps::run(self.args.clone(), &mut config, &mut stdout)?;
 		let cwd_lossy = self.cwd.path().to_string_lossy();
 		let stdout = str::from_utf8(&stdout)?.replace(&*cwd_lossy, "██████████");
 		Ok((code, stdout))
 	}
 }
+
+/// Stderr which can be read after `Config` takes it.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for SharedBuffer {
+	fn write(&mut self, buf :&[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
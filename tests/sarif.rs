@@ -0,0 +1,70 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "sarif"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS :&str = "";
+
+#[test]
+fn results() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_sarif_results")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("sarif")
+			.run()?;
+	assert_eq!(1, code);
+	let sarif = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(json!("2.1.0"), sarif["version"]);
+	let results = sarif["runs"][0]["results"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|r| {
+			(
+				r["ruleId"].clone(),
+				r["message"]["text"].clone(),
+				r["locations"][0]["physicalLocation"]["artifactLocation"].clone(),
+				r["locations"][0]["physicalLocation"]["region"]["startLine"].clone(),
+			)
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			(
+				json!("unused-dependency"),
+				json!("`if_chain` is not used by `sarif v0.0.0 (██████████)`"),
+				json!({ "uri": "Cargo.toml", "uriBaseId": "%SRCROOT%" }),
+				json!(9),
+			),
+			(
+				json!("unused-dev-dependency"),
+				json!("`maplit` is not used by `sarif v0.0.0 (██████████)`"),
+				json!({ "uri": "Cargo.toml", "uriBaseId": "%SRCROOT%" }),
+				json!(12),
+			),
+		],
+		results,
+	);
+	Ok(())
+}
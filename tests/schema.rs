@@ -0,0 +1,46 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static SCHEMA :&str = include_str!("../schema/outcome.json");
+
+#[test]
+fn up_to_date() -> Fallible<()> {
+	let (code, stdout) = Runner::new("cargo_udeps_test_schema_up_to_date")?.arg("schema").run()?;
+	assert_eq!(0, code);
+	assert_eq!(SCHEMA, stdout, "run `cargo udeps schema > schema/outcome.json`");
+	Ok(())
+}
+
+#[test]
+fn schema_version() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "schema_version"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_schema_schema_version")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(0, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let schema = serde_json::from_str::<serde_json::Value>(SCHEMA)?;
+	assert_eq!(serde_json::json!(4), outcome["schema_version"]);
+	for (key, _) in outcome.as_object().unwrap() {
+		assert!(schema["properties"].get(key).is_some(), "`{}` is not in the schema", key);
+	}
+	Ok(())
+}
@@ -0,0 +1,39 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn short() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "short"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_short_short")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--backend")
+			.arg("source")
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("short")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!("short normal if_chain\nshort normal maplit\nshort dev maplit\n", stdout_masked);
+	Ok(())
+}
@@ -0,0 +1,65 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn since() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b", "c"]
+"#;
+
+	static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	static B_DEPS: &str = r#"a = { path = "../a" }
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_since_since")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./a/src")?
+			.file("./a/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "a"))?
+			.file("./a/src/lib.rs", "")?
+			.dir("./b/src")?
+			.file("./b/Cargo.toml", &(MEMBER_CARGO_TOML.replace("{}", "b") + B_DEPS))?
+			.file("./b/src/lib.rs", "use a as _;\n")?
+			.dir("./c/src")?
+			.file("./c/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "c"))?
+			.file("./c/src/lib.rs", "")?
+			.file(".gitignore", "/target\nCargo.lock\n")?
+			.git(&["init", "-q"])?
+			.git(&["add", "."])?
+			.git(&["commit", "-q", "-m", "Initial commit"])?
+			.file("./a/src/lib.rs", "pub fn f() {}\n")?
+			.arg("--since")
+			.arg("HEAD")
+			.arg("--backend")
+			.arg("source")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     └─── "maplit"
+`b v0.0.0 (██████████/b)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,55 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn skip() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "generated"]
+"#;
+
+	static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	static SKIP: &str = r#"
+[package.metadata.cargo-udeps]
+skip = true
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_skip_skip")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./a/src")?
+			.file("./a/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "a"))?
+			.file("./a/src/lib.rs", "")?
+			.dir("./generated/src")?
+			.file("./generated/Cargo.toml", &(MEMBER_CARGO_TOML.replace("{}", "generated") + SKIP))?
+			.file("./generated/src/lib.rs", "")?
+			.arg("--workspace")
+			.arg("--backend")
+			.arg("source")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
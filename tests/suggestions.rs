@@ -0,0 +1,70 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "suggestions"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = { version = "1.0.0", features = [
+] }
+byteorder = "1.0.0"
+
+[dev-dependencies.maplit]
+version = "1.0.2"
+"#;
+
+static LIB_RS :&str = "use byteorder as _;\n";
+
+#[test]
+fn byte_ranges() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_suggestions_byte_ranges")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let mut suggestions = outcome["suggestions"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|s| {
+			let start = s["byte_start"].as_u64().unwrap() as usize;
+			let end = s["byte_end"].as_u64().unwrap() as usize;
+			(start..end, s["replacement"].as_str().unwrap().to_owned())
+		})
+		.collect::<Vec<_>>();
+	suggestions.sort_by_key(|(range, _)| range.start);
+
+	let mut fixed = CARGO_TOML.to_owned();
+	for (range, replacement) in suggestions.into_iter().rev() {
+		fixed.replace_range(range, &replacement);
+	}
+	assert_eq!(
+		r#"[workspace]
+[package]
+name = "suggestions"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+
+"#,
+		fixed,
+	);
+	Ok(())
+}
@@ -0,0 +1,77 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "long-name"]
+"#;
+
+static A_CARGO_TOML: &str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain"]
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LONG_NAME_CARGO_TOML: &str = r#"[package]
+name = "long-name"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+"#;
+
+#[test]
+fn summary() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_summary_summary")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./a/src")?
+		.file("./a/Cargo.toml", A_CARGO_TOML)?
+		.file("./a/src/lib.rs", "")?
+		.dir("./long-name/src")?
+		.file("./long-name/Cargo.toml", LONG_NAME_CARGO_TOML)?
+		.file("./long-name/src/lib.rs", "")?
+		.arg("--backend")
+		.arg("source")
+		.arg("--all-targets")
+		.arg("--workspace")
+		.arg("--summary")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dev-dependencies
+     └─── "maplit"
+`long-name v0.1.0 (██████████/long-name)`
+└─── dependencies
+     ├─── "if_chain"
+     └─── "maplit"
+summary:
+package            normal      dev    build  ignored
+a v0.0.0                0        1        0        1
+long-name v0.1.0        2        0        0        0
+total                   2        1        0        1
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
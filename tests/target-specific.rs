@@ -0,0 +1,92 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "target-specific"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+itoa = "1.0.0"
+"#;
+
+static LIB_RS :&str = r#"#[cfg(unix)]
+mod unix;
+
+#[cfg(any())]
+mod not_there;
+
+pub fn f(x :bool) -> bool {
+	if_chain::if_chain! {
+		if x;
+		then { return true; }
+	}
+	false
+}
+"#;
+
+static UNIX_RS :&str = r#"pub fn g() -> String {
+	itoa::Buffer::new().format(1).to_owned()
+}
+"#;
+
+#[test]
+fn unix() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_target_specific_unix")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.file("./src/unix.rs", UNIX_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+used only on some platforms:
+`target-specific v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa" (move it to `[target.'cfg(unix)'.dependencies]`)
+Note: Dependencies used only on some platforms are built on the other ones as well.
+      Move them to the suggested `[target.'cfg(..)'.*]` table.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn ignored() -> Fallible<()> {
+	let cargo_toml = format!(
+		"{}\n[package.metadata.cargo-udeps.ignore]\nnormal = [\"itoa\"]\n",
+		CARGO_TOML,
+	);
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_target_specific_ignored")?
+		.cargo_toml(&cargo_toml)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.file("./src/unix.rs", UNIX_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+unnecessary ignores:
+`target-specific v0.0.0 (██████████)`
+└─── dependencies
+     └─── "itoa" (used, remove it from the ignored ones)
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
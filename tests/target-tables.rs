@@ -0,0 +1,78 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "target-tables"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[target.'cfg(unix)'.dependencies]
+if_chain = "1.0.0"
+
+[target.'cfg(windows)'.dependencies]
+itoa = "1.0.0"
+"#;
+
+#[test]
+fn other_platform() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_target_tables_other_platform")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("source")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`target-tables v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain" (for `cfg(unix)`)
+not analyzed:
+`target-tables v0.0.0 (██████████)`
+└─── dependencies for `cfg(windows)`
+     └─── "itoa"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn multiple_targets() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_target_tables_multiple_targets")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub use itoa::Buffer;\n")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("source")
+		.arg("--target")
+		.arg("x86_64-unknown-linux-gnu")
+		.arg("--target")
+		.arg("x86_64-pc-windows-msvc")
+		.arg("--output")
+		.arg("json")
+		.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let unused_deps = outcome["unused_deps"].as_array().unwrap().iter().collect::<Vec<_>>();
+	assert_eq!(vec![&serde_json::json!(["if_chain"])], unused_deps.iter().map(|u| &u["normal"]).collect::<Vec<_>>());
+	assert_eq!(serde_json::json!([]), outcome["coverage"]);
+	let by_target = |target :&str| {
+		outcome["by_target"][target].as_array().unwrap().iter().map(|u| u["normal"].clone()).collect::<Vec<_>>()
+	};
+	assert_eq!(vec![serde_json::json!(["if_chain"])], by_target("x86_64-unknown-linux-gnu"));
+	assert_eq!(Vec::<serde_json::Value>::new(), by_target("x86_64-pc-windows-msvc"));
+	Ok(())
+}
@@ -0,0 +1,42 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "teamcity"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[build-dependencies]
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS :&str = "";
+static BUILD_RS :&str = "fn main() {}\n";
+
+#[test]
+fn service_messages() -> Fallible<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_teamcity_service_messages")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./build.rs", BUILD_RS)?
+			.arg("--output")
+			.arg("teamcity")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"##teamcity[inspectionType id='unused-build-dependency' name='Unused build-dependency' category='cargo-udeps' description='Unused build-dependency']
+##teamcity[inspection typeId='unused-build-dependency' message='unused build-dependency `if_chain` in `teamcity v0.0.0 (██████████)`' file='Cargo.toml' line='9' SEVERITY='WARNING']
+##teamcity[buildProblem description='unused build-dependency `if_chain` in `teamcity v0.0.0 (██████████)`' identity='2a9404d8af79e92c']
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,45 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn timings_json() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "timings"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, _, timings) =
+		Runner::new("cargo_udeps_test_timings_timings_json")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--timings=json")
+			.run_and_read("target/udeps/timings.json")?;
+	assert_eq!(1, code);
+	let timings = serde_json::from_str::<serde_json::Value>(&timings)?;
+	let phases = timings["phases"]
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter_map(|p| p["phase"].as_str())
+		.collect::<Vec<_>>();
+	assert_eq!(vec!["resolve", "compile", "analyze", "parse", "report"], phases);
+	let packages = timings["packages"]
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter_map(|p| p["package"].as_str())
+		.collect::<Vec<_>>();
+	assert_eq!(vec!["timings 0.0.0"], packages);
+	Ok(())
+}
@@ -1,6 +1,6 @@
 mod runner;
 
-use cargo::CargoResult;
+use failure::Fallible;
 use pretty_assertions::assert_eq;
 
 use crate::runner::Runner;
@@ -16,7 +16,7 @@ byteorder = "1.0.0"
 static LIB_RS :&str = "";
 
 #[test]
-fn without_all_targets() -> CargoResult<()> {
+fn without_all_targets() -> Fallible<()> {
 	let (code, stdout_masked) =
 		Runner::new("cargo_udeps_test_unused_byteorder_without_all_targets")?
 			.cargo_toml(CARGO_TOML)?
@@ -42,7 +42,7 @@ Note: They might be false-positive.
 
 
 #[test]
-fn with_all_targets() -> CargoResult<()> {
+fn with_all_targets() -> Fallible<()> {
 	let (code, stdout_masked) =
 		Runner::new("cargo_udeps_test_unused_byteorder_with_all_targets")?
 			.cargo_toml(CARGO_TOML)?
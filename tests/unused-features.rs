@@ -0,0 +1,174 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "unused-features"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = { version = "1.0.0", default-features = false, features = ["std", "i128"] }
+"#;
+
+#[test]
+fn unused() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_unused_features_unused")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub fn read(buf :&[u8]) -> u32 { <byteorder::BigEndian as byteorder::ByteOrder>::read_u32(buf) }\n")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--unused-features")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+unused features of dependencies:
+`unused-features v0.0.0 (██████████)`
+└─── dependencies
+     └─── "byteorder" (`std`)
+Note: Features are regarded as unused if their `#[cfg(feature = "..")]` items are never referred to.
+      Their `impl`s and the features of other crates they enable may still be used.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn used() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_unused_features_used")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file(
+			"./src/lib.rs",
+			"use byteorder::{BigEndian, ReadBytesExt as _};\n\npub fn read(mut r :impl std::io::Read) -> std::io::Result<u32> { r.read_u32::<BigEndian>() }\n",
+		)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--unused-features")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
+
+static CARGO_TOML_DEFAULT :&str = r#"[workspace]
+[package]
+name = "unused-features"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+"#;
+
+#[test]
+fn default_features() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_unused_features_default_features")?
+		.cargo_toml(CARGO_TOML_DEFAULT)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub fn read(buf :&[u8]) -> u32 { <byteorder::BigEndian as byteorder::ByteOrder>::read_u32(buf) }\n")?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--unused-features")
+		.arg("--output")
+		.arg("json")
+		.run()?;
+	assert_eq!(0, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(serde_json::json!(["std"]), outcome["unused_default_features"][0]["features"]);
+	let suggestion = &outcome["suggestions"][0];
+	let start = suggestion["byte_start"].as_u64().unwrap() as usize;
+	let end = suggestion["byte_end"].as_u64().unwrap() as usize;
+	let mut fixed = CARGO_TOML_DEFAULT.to_owned();
+	fixed.replace_range(start..end, suggestion["replacement"].as_str().unwrap());
+	assert_eq!(
+		r#"[workspace]
+[package]
+name = "unused-features"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = { version = "1.0.0", default-features = false }
+"#,
+		fixed,
+	);
+	Ok(())
+}
+
+static CARGO_TOML_BUILD :&str = r#"[workspace]
+[package]
+name = "unused-features"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+
+[build-dependencies]
+byteorder = "1.0.0"
+"#;
+
+static BUILD_RS :&str = "use byteorder::ReadBytesExt as _;\n\nfn main() {}\n";
+
+#[test]
+fn unified_with_build_dependencies() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_unused_features_unified_with_build_dependencies")?
+		.cargo_toml(CARGO_TOML_BUILD)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub fn read(buf :&[u8]) -> u32 { <byteorder::BigEndian as byteorder::ByteOrder>::read_u32(buf) }\n")?
+		.file("./build.rs", BUILD_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--unused-features")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn not_unified_with_build_dependencies() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_unused_features_not_unified_with_build_dependencies")?
+		.cargo_toml(&CARGO_TOML_BUILD.replace("[workspace]\n", "[workspace]\nresolver = \"2\"\n"))?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub fn read(buf :&[u8]) -> u32 { <byteorder::BigEndian as byteorder::ByteOrder>::read_u32(buf) }\n")?
+		.file("./build.rs", BUILD_RS)?
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.arg("--unused-features")
+		.arg("--deny")
+		.arg("unused-features")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unneeded default features:
+`unused-features v0.0.0 (██████████)`
+└─── dependencies
+     └─── "byteorder" (`std`)
+Note: The default features of some dependencies seem unneeded.
+      Set `default-features = false` on them.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,54 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+#[test]
+fn warnings() -> Fallible<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "warnings"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain", "maplit"]
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_warnings_warnings")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "pub use if_chain::if_chain;\n")?
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(0, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let warnings = outcome["warnings"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|w| (w["code"].clone(), w["message"].clone()))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			(
+				serde_json::json!("unnecessary-ignore"),
+				serde_json::json!("`warnings` ignores `if_chain` (Normal), which is used"),
+			),
+			(
+				serde_json::json!("stale-ignore"),
+				serde_json::json!("`warnings` ignores `maplit` (Normal), which it does not declare"),
+			),
+		],
+		warnings,
+	);
+	Ok(())
+}
@@ -0,0 +1,70 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+[package]
+name = "workspace-config"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+itoa = "1.0.0"
+"#;
+
+static UDEPS_TOML :&str = r#"output = "json"
+backend = ["source"]
+
+[ignore]
+normal = ["if_chain"]
+"#;
+
+#[test]
+fn udeps_toml() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_workspace_config_udeps_toml")?
+		.cargo_toml(CARGO_TOML)?
+		.file("./udeps.toml", UDEPS_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub use byteorder::BigEndian;\n")?
+		.run()?;
+	assert_eq!(1, code);
+	let outcome = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let unused_deps = outcome["unused_deps"].as_array().unwrap().iter().map(|u| &u["normal"]).collect::<Vec<_>>();
+	assert_eq!(vec![&serde_json::json!(["itoa"])], unused_deps);
+	Ok(())
+}
+
+#[test]
+fn flags_take_precedence() -> Fallible<()> {
+	let cargo_toml = CARGO_TOML.replace(
+		"[workspace]\n",
+		"[workspace]\n[workspace.metadata.cargo-udeps]\noutput = \"json\"\nbackend = [\"source\"]\n",
+	);
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_workspace_config_flags_take_precedence")?
+		.cargo_toml(&cargo_toml)?
+		.dir("./src")?
+		.file("./src/lib.rs", "pub use byteorder::BigEndian;\n")?
+		.arg("--output")
+		.arg("human")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`workspace-config v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "if_chain"
+     └─── "itoa"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
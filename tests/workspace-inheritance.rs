@@ -0,0 +1,70 @@
+mod runner;
+
+use failure::Fallible;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+members = ["a", "b"]
+
+[workspace.dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+static A_CARGO_TOML :&str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = { workspace = true }
+if_chain = { workspace = true }
+"#;
+
+static B_CARGO_TOML :&str = r#"[package]
+name = "b"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = { workspace = true }
+"#;
+
+#[test]
+fn inherited() -> Fallible<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_workspace_inheritance_inherited")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./a/src")?
+		.file("./a/Cargo.toml", A_CARGO_TOML)?
+		.file("./a/src/lib.rs", "")?
+		.dir("./b/src")?
+		.file("./b/Cargo.toml", B_CARGO_TOML)?
+		.file("./b/src/lib.rs", "pub use byteorder::BigEndian;\n")?
+		.arg("--workspace")
+		.arg("--all-targets")
+		.arg("--backend")
+		.arg("lint")
+		.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`a v0.0.0 (██████████/a)`
+└─── dependencies
+     ├─── "byteorder"
+     └─── "if_chain"
+inherited from `[workspace.dependencies]`:
+└─── workspace
+     ├─── "byteorder" (still inherited by `b`)
+     └─── "if_chain" (unused by every member, the workspace entry can be removed too)
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some of dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}